@@ -0,0 +1,664 @@
+//! The HTTP routes of the API.
+
+use axum::Router;
+use axum::middleware;
+use tower_http::trace::TraceLayer;
+
+use crate::state::AppState;
+use crate::{health, metrics, routes, status};
+
+/// Build the router serving all endpoints on `state`.
+pub fn router(state: AppState) -> Router {
+    Router::new()
+        // `get` also serves HEAD requests
+        .route(
+            "/heartbeat/{slug}",
+            axum::routing::get(routes::heartbeat_handler).post(routes::heartbeat_handler),
+        )
+        .route(
+            "/heartbeat/{slug}/fail",
+            axum::routing::post(routes::fail_handler),
+        )
+        .route(
+            "/heartbeat/{slug}/{exit_code}",
+            axum::routing::get(routes::exit_code_handler).post(routes::exit_code_handler),
+        )
+        .route(
+            "/ping/{token}",
+            axum::routing::get(routes::token_heartbeat_handler)
+                .post(routes::token_heartbeat_handler),
+        )
+        .route(
+            "/ping/{token}/fail",
+            axum::routing::post(routes::token_fail_handler),
+        )
+        .route(
+            "/ping/{token}/{exit_code}",
+            axum::routing::get(routes::token_exit_code_handler)
+                .post(routes::token_exit_code_handler),
+        )
+        .route(
+            "/monitors",
+            axum::routing::get(routes::list_monitors_handler),
+        )
+        .route(
+            "/monitors/{slug}",
+            axum::routing::get(routes::get_monitor_handler).delete(routes::delete_monitor_handler),
+        )
+        .route(
+            "/monitors/{slug}/pause",
+            axum::routing::post(routes::pause_handler),
+        )
+        .route(
+            "/monitors/{slug}/unpause",
+            axum::routing::post(routes::unpause_handler),
+        )
+        .route(
+            "/monitors/{slug}/rotate-ping-token",
+            axum::routing::post(routes::rotate_ping_token_handler),
+        )
+        .route(
+            "/keys/{prefix}/revoke",
+            axum::routing::post(routes::revoke_key_handler),
+        )
+        .route("/audit", axum::routing::get(routes::audit_handler))
+        .route("/metrics", axum::routing::get(metrics::metrics_handler))
+        .route(
+            "/metrics/monitors",
+            axum::routing::get(metrics::monitor_metrics_handler),
+        )
+        .route("/status", axum::routing::get(status::status_handler))
+        .route("/healthz", axum::routing::get(health::healthz_handler))
+        .route("/readyz", axum::routing::get(health::readyz_handler))
+        .layer(middleware::from_fn(metrics::track_requests))
+        .layer(TraceLayer::new_for_http())
+        .with_state(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use axum::body::{Body, to_bytes};
+    use axum::http::{Method, Request, StatusCode};
+    use chrono::Utc;
+    use heartbeat_core::{
+        ApiKeyRecord, AuditAction, AuditEntry, Clock, ManualClock, Monitor, MonitorId, Scope, Slug,
+        StorageConfig, Stores,
+    };
+    use metrics_exporter_prometheus::PrometheusBuilder;
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::health::Readiness;
+    use crate::key_cache::{self, KeyCache};
+    use crate::metrics::{MonitorLabels, MonitorSnapshot};
+
+    /// The API on a fresh in-memory SQLite database, without a key cache so
+    /// that key changes apply immediately.
+    async fn test_state() -> AppState {
+        let stores = Stores::connect(&StorageConfig::Sql {
+            url: "sqlite::memory:".to_string(),
+        })
+        .await
+        .unwrap();
+
+        AppState {
+            monitors_store: stores.monitors,
+            keys_store: stores.api_keys,
+            key_cache: KeyCache::new(Duration::ZERO, 0),
+            plaintext_key_fallback: true,
+            audit_store: stores.audit,
+            metrics: PrometheusBuilder::new().build_recorder().handle(),
+            monitor_labels: MonitorLabels::new(0),
+            monitor_snapshot: MonitorSnapshot::new(Duration::ZERO),
+            readiness: Readiness::new(health::CHECK_TTL),
+            status_store: stores.status,
+            checker_stale_after: status::DEFAULT_CHECKER_STALE_AFTER,
+            due_updates: None,
+            clock: Clock::System,
+        }
+    }
+
+    /// Store a key for `token` in the default project with `scopes`.
+    async fn add_key(state: &AppState, token: &str, scopes: &[Scope]) -> ApiKeyRecord {
+        let mut record = ApiKeyRecord::new(token, "test", Utc::now().timestamp());
+        record.scopes = Some(scopes.to_vec());
+        state.keys_store.put_key(&record).await.unwrap();
+        record
+    }
+
+    /// Send a request with `token` as bearer key, returning the status and
+    /// the JSON body (`Null` if empty).
+    async fn send(
+        state: &AppState,
+        method: Method,
+        uri: &str,
+        token: Option<&str>,
+        body: &str,
+    ) -> (StatusCode, Value) {
+        let mut request = Request::builder().method(method).uri(uri);
+        if let Some(token) = token {
+            request = request.header("Authorization", format!("Bearer {token}"));
+        }
+        let response = router(state.clone())
+            .oneshot(request.body(Body::from(body.to_string())).unwrap())
+            .await
+            .unwrap();
+
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json = if bytes.is_empty() {
+            Value::Null
+        } else {
+            serde_json::from_slice(&bytes).unwrap()
+        };
+        (status, json)
+    }
+
+    async fn stored(state: &AppState, slug: &str) -> Option<Monitor> {
+        let id = MonitorId {
+            project: "default".to_string(),
+            slug: Slug::new(slug).unwrap(),
+        };
+        state.monitors_store.get_monitor(&id).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn ping_creates_monitor_and_keeps_interval() {
+        let state = test_state().await;
+        add_key(&state, "ping-key", &[Scope::Ping]).await;
+
+        let (status, body) = send(
+            &state,
+            Method::POST,
+            "/heartbeat/backup?interval=10m",
+            Some("ping-key"),
+            "",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["ok"], true);
+        assert_eq!(body["status"], "new");
+
+        let monitor = stored(&state, "backup").await.unwrap();
+        assert_eq!(monitor.interval_secs, 600);
+        assert_eq!(monitor.next_due, monitor.last_ping + 600);
+        assert!(monitor.ping_token.is_some());
+        assert_eq!(monitor.last_ping_meta.unwrap().method, "POST");
+
+        // Without ?interval, the stored interval is kept
+        let (status, _) = send(
+            &state,
+            Method::GET,
+            "/heartbeat/backup",
+            Some("ping-key"),
+            "",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(stored(&state, "backup").await.unwrap().interval_secs, 600);
+
+        let (status, _) = send(
+            &state,
+            Method::POST,
+            "/heartbeat/Not_A_Slug",
+            Some("ping-key"),
+            "",
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn ping_token_is_only_shown_to_manage_keys() {
+        let state = test_state().await;
+        add_key(&state, "read-key", &[Scope::Ping, Scope::Read]).await;
+        add_key(&state, "manage-key", &[Scope::Manage]).await;
+        send(
+            &state,
+            Method::POST,
+            "/heartbeat/backup",
+            Some("read-key"),
+            "",
+        )
+        .await;
+        let token = stored(&state, "backup").await.unwrap().ping_token.unwrap();
+
+        let (status, body) = send(
+            &state,
+            Method::GET,
+            "/monitors/backup",
+            Some("read-key"),
+            "",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["slug"], "backup");
+        assert!(body.get("ping_token").is_none());
+
+        let (_, body) = send(
+            &state,
+            Method::GET,
+            "/monitors/backup",
+            Some("manage-key"),
+            "",
+        )
+        .await;
+        assert_eq!(body["ping_token"], token);
+    }
+
+    #[tokio::test]
+    async fn fail_records_failure_and_lists_monitor_as_failed() {
+        let state = test_state().await;
+        add_key(&state, "ping-key", &[Scope::Ping]).await;
+        add_key(&state, "read-key", &[Scope::Read]).await;
+
+        let (status, body) = send(
+            &state,
+            Method::POST,
+            "/heartbeat/backup/fail",
+            Some("ping-key"),
+            "disk full\n",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "failed");
+
+        let monitor = stored(&state, "backup").await.unwrap();
+        let failure = monitor.last_failure.unwrap();
+        assert_eq!(failure.log.as_deref(), Some("disk full"));
+        assert_eq!(failure.exit_code, None);
+
+        let (status, body) = send(&state, Method::GET, "/monitors", Some("read-key"), "").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["monitors"][0]["slug"], "backup");
+        assert_eq!(body["monitors"][0]["status"], "failed");
+
+        // A zero exit code is a successful ping, resolving the failure
+        let (status, body) = send(
+            &state,
+            Method::POST,
+            "/heartbeat/backup/0",
+            Some("ping-key"),
+            "",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["ok"], true);
+        assert!(body["next_due"].is_string());
+        assert_eq!(stored(&state, "backup").await.unwrap().last_failure, None);
+    }
+
+    #[tokio::test]
+    async fn pause_unpause_and_delete() {
+        let state = test_state().await;
+        add_key(&state, "ping-key", &[Scope::Ping]).await;
+        add_key(&state, "manage-key", &[Scope::Read, Scope::Manage]).await;
+        send(
+            &state,
+            Method::POST,
+            "/heartbeat/backup",
+            Some("ping-key"),
+            "",
+        )
+        .await;
+
+        let (status, _) = send(
+            &state,
+            Method::POST,
+            "/monitors/backup/pause",
+            Some("manage-key"),
+            "",
+        )
+        .await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        assert_eq!(stored(&state, "backup").await.unwrap().paused, Some(true));
+        let (_, body) = send(
+            &state,
+            Method::GET,
+            "/monitors/backup",
+            Some("manage-key"),
+            "",
+        )
+        .await;
+        assert_eq!(body["status"], "paused");
+
+        let (status, _) = send(
+            &state,
+            Method::POST,
+            "/monitors/backup/unpause",
+            Some("manage-key"),
+            "",
+        )
+        .await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        assert_eq!(stored(&state, "backup").await.unwrap().paused, None);
+
+        let (status, _) = send(
+            &state,
+            Method::DELETE,
+            "/monitors/backup",
+            Some("manage-key"),
+            "",
+        )
+        .await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        assert!(stored(&state, "backup").await.is_none());
+
+        let (status, _) = send(
+            &state,
+            Method::DELETE,
+            "/monitors/backup",
+            Some("manage-key"),
+            "",
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = send(
+            &state,
+            Method::POST,
+            "/monitors/backup/pause",
+            Some("manage-key"),
+            "",
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn ping_sets_and_keeps_timezone() {
+        let state = test_state().await;
+        add_key(&state, "key", &[Scope::Ping, Scope::Read]).await;
+
+        let (status, _) = send(
+            &state,
+            Method::POST,
+            "/heartbeat/backup?tz=Europe/Berlin",
+            Some("key"),
+            "",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        send(&state, Method::POST, "/heartbeat/backup/0", Some("key"), "").await;
+        let (_, body) = send(&state, Method::GET, "/monitors/backup", Some("key"), "").await;
+        assert_eq!(body["timezone"], "Europe/Berlin");
+
+        let (status, body) = send(
+            &state,
+            Method::POST,
+            "/heartbeat/backup?tz=Mars/Olympus",
+            Some("key"),
+            "",
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "Unknown time zone: Mars/Olympus");
+        let monitor = stored(&state, "backup").await.unwrap();
+        assert_eq!(monitor.timezone.as_deref(), Some("Europe/Berlin"));
+
+        send(
+            &state,
+            Method::POST,
+            "/heartbeat/backup?tz=",
+            Some("key"),
+            "",
+        )
+        .await;
+        assert_eq!(stored(&state, "backup").await.unwrap().timezone, None);
+    }
+
+    #[tokio::test]
+    async fn statuses_follow_the_clock() {
+        let clock = ManualClock::new(1_700_000_000);
+        let state = AppState {
+            clock: clock.clone().into(),
+            ..test_state().await
+        };
+        add_key(&state, "key", &[Scope::Ping, Scope::Read]).await;
+
+        send(
+            &state,
+            Method::POST,
+            "/heartbeat/backup?interval=5m",
+            Some("key"),
+            "",
+        )
+        .await;
+        let monitor = stored(&state, "backup").await.unwrap();
+        assert_eq!(monitor.last_ping, 1_700_000_000);
+        assert_eq!(monitor.next_due, 1_700_000_300);
+
+        clock.advance(300);
+        let (_, body) = send(&state, Method::GET, "/monitors/backup", Some("key"), "").await;
+        assert_eq!(body["status"], "new");
+
+        clock.advance(1);
+        let (_, body) = send(&state, Method::GET, "/monitors/backup", Some("key"), "").await;
+        assert_eq!(body["status"], "overdue");
+
+        let (_, body) = send(&state, Method::POST, "/heartbeat/backup", Some("key"), "").await;
+        assert_eq!(body["status"], "ok");
+    }
+
+    #[tokio::test]
+    async fn last_event_in_the_same_second_wins() {
+        let clock = ManualClock::new(1_700_000_000);
+        let state = AppState {
+            clock: clock.clone().into(),
+            ..test_state().await
+        };
+        add_key(&state, "key", &[Scope::Ping]).await;
+        send(&state, Method::POST, "/heartbeat/backup", Some("key"), "").await;
+
+        clock.advance(60);
+        send(&state, Method::POST, "/heartbeat/backup", Some("key"), "").await;
+        let (_, body) = send(
+            &state,
+            Method::POST,
+            "/heartbeat/backup/fail",
+            Some("key"),
+            "",
+        )
+        .await;
+        assert_eq!(body["status"], "failed");
+        let (_, body) = send(&state, Method::POST, "/heartbeat/backup", Some("key"), "").await;
+        assert_eq!(body["status"], "ok");
+        assert!(
+            stored(&state, "backup")
+                .await
+                .unwrap()
+                .last_failure
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_missing_unknown_and_revoked_keys() {
+        let state = test_state().await;
+        let record = add_key(&state, "revoked-key", &[Scope::Ping]).await;
+        state
+            .keys_store
+            .revoke_key(&record.key_hash, Utc::now().timestamp() - 1)
+            .await
+            .unwrap();
+
+        for token in [None, Some("unknown-key"), Some("revoked-key")] {
+            let (status, body) = send(&state, Method::POST, "/heartbeat/backup", token, "").await;
+            assert_eq!(status, StatusCode::UNAUTHORIZED, "token {token:?}");
+            assert!(body["error"].is_string());
+        }
+        assert!(stored(&state, "backup").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn revoking_a_key_drops_it_from_the_cache() {
+        let mut state = test_state().await;
+        state.key_cache = KeyCache::new(key_cache::DEFAULT_TTL, 10);
+        add_key(&state, "admin-key", &[Scope::Admin]).await;
+        let record = add_key(&state, "old-key", &[Scope::Ping]).await;
+        let prefix = record.prefix();
+
+        let (status, _) = send(&state, Method::POST, "/heartbeat/db", Some("old-key"), "").await;
+        assert_eq!(status, StatusCode::OK);
+
+        // Revoked elsewhere: still accepted from the cache
+        state
+            .keys_store
+            .revoke_key(&record.key_hash, Utc::now().timestamp())
+            .await
+            .unwrap();
+        let (status, _) = send(&state, Method::POST, "/heartbeat/db", Some("old-key"), "").await;
+        assert_eq!(status, StatusCode::OK);
+
+        let uri = format!("/keys/{prefix}/revoke");
+        let (status, _) = send(&state, Method::POST, &uri, Some("old-key"), "").await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = send(&state, Method::POST, &uri, Some("admin-key"), "").await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let (status, _) = send(&state, Method::POST, "/heartbeat/db", Some("old-key"), "").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, _) = send(
+            &state,
+            Method::POST,
+            "/keys/00000000/revoke",
+            Some("admin-key"),
+            "",
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn audit_lists_newest_first_and_filters_before_the_limit() {
+        let state = test_state().await;
+        add_key(&state, "admin-key", &[Scope::Admin]).await;
+        let mut prefixed = ApiKeyRecord::new("prefixed-key", "test", Utc::now().timestamp());
+        prefixed.slug_prefix = Some("backup-".to_string());
+        state.keys_store.put_key(&prefixed).await.unwrap();
+        for (at, slug) in [
+            (1000, "backup-a"),
+            (1001, "db"),
+            (1002, "db"),
+            (1003, "backup-b"),
+        ] {
+            let mut entry = AuditEntry::new("default", at, AuditAction::Pause, "ops");
+            entry.slug = Some(slug.to_string());
+            state.audit_store.record(&entry).await.unwrap();
+        }
+        let slugs = |body: &Value| -> Vec<String> {
+            body["entries"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|entry| entry["slug"].as_str().unwrap().to_string())
+                .collect()
+        };
+
+        let (status, body) =
+            send(&state, Method::GET, "/audit?limit=2", Some("admin-key"), "").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(slugs(&body), ["backup-b", "db"]);
+        let before = body["next_before"].as_str().unwrap();
+        let uri = format!("/audit?limit=2&before={before}");
+        let (_, body) = send(&state, Method::GET, &uri, Some("admin-key"), "").await;
+        assert_eq!(slugs(&body), ["db", "backup-a"]);
+
+        // Entries of other monitors don't use up the limit
+        let (_, body) = send(
+            &state,
+            Method::GET,
+            "/audit?limit=2",
+            Some("prefixed-key"),
+            "",
+        )
+        .await;
+        assert_eq!(slugs(&body), ["backup-b", "backup-a"]);
+        let (_, body) = send(
+            &state,
+            Method::GET,
+            "/audit?limit=3",
+            Some("prefixed-key"),
+            "",
+        )
+        .await;
+        assert_eq!(slugs(&body), ["backup-b", "backup-a"]);
+        assert!(body.get("next_before").is_none());
+    }
+
+    #[tokio::test]
+    async fn plaintext_keys_until_fallback_is_off() {
+        let mut state = test_state().await;
+        let mut record = ApiKeyRecord::new("legacy-key", "test", Utc::now().timestamp());
+        record.key_hash = "legacy-key".to_string();
+        record.key_prefix = None;
+        state.keys_store.put_key(&record).await.unwrap();
+
+        let (status, _) = send(
+            &state,
+            Method::POST,
+            "/heartbeat/backup",
+            Some("legacy-key"),
+            "",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+
+        state.plaintext_key_fallback = false;
+        let (status, _) = send(
+            &state,
+            Method::POST,
+            "/heartbeat/backup",
+            Some("legacy-key"),
+            "",
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn enforces_scopes_and_slug_prefix() {
+        let state = test_state().await;
+        add_key(&state, "ping-key", &[Scope::Ping]).await;
+        add_key(&state, "read-key", &[Scope::Read]).await;
+        let mut prefixed = ApiKeyRecord::new("prefixed-key", "test", Utc::now().timestamp());
+        prefixed.slug_prefix = Some("backup-".to_string());
+        state.keys_store.put_key(&prefixed).await.unwrap();
+
+        // Reading and managing need more than the ping scope
+        let (status, _) = send(&state, Method::GET, "/monitors", Some("ping-key"), "").await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = send(&state, Method::POST, "/heartbeat/db", Some("read-key"), "").await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        send(&state, Method::POST, "/heartbeat/db", Some("ping-key"), "").await;
+        let (status, _) = send(&state, Method::DELETE, "/monitors/db", Some("read-key"), "").await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert!(stored(&state, "db").await.is_some());
+
+        // Slug prefixes limit both pings and listing
+        let (status, _) = send(
+            &state,
+            Method::POST,
+            "/heartbeat/db",
+            Some("prefixed-key"),
+            "",
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = send(
+            &state,
+            Method::POST,
+            "/heartbeat/backup-db",
+            Some("prefixed-key"),
+            "",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, body) = send(&state, Method::GET, "/monitors", Some("prefixed-key"), "").await;
+        assert_eq!(body["monitors"].as_array().unwrap().len(), 1);
+        assert_eq!(body["monitors"][0]["slug"], "backup-db");
+    }
+}
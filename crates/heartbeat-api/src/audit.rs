@@ -0,0 +1,72 @@
+use heartbeat_core::{AuditAction, AuditEntry, MonitorId};
+
+use crate::auth::ApiKey;
+use crate::state::AppState;
+
+/// How a ping or failure changed a monitor's configuration.
+pub enum MonitorChange {
+    /// Nothing worth auditing: a regular ping or failure.
+    Unchanged,
+    /// The monitor was created with the given interval.
+    Created { interval_secs: u64 },
+    /// The interval of an existing monitor was changed.
+    IntervalChanged { from: u64, to: u64 },
+}
+
+/// Start an audit entry for `action` on monitor `id`, made with `api_key`
+/// from `source_ip`.
+pub fn monitor_entry(
+    state: &AppState,
+    api_key: &ApiKey,
+    action: AuditAction,
+    id: &MonitorId,
+    source_ip: Option<String>,
+) -> AuditEntry {
+    let mut entry = AuditEntry::new(
+        &id.project,
+        state.clock.now(),
+        action,
+        &api_key.record.description,
+    );
+    entry.key_prefix = Some(api_key.record.prefix());
+    entry.slug = Some(id.slug.to_string());
+    entry.source_ip = source_ip;
+    entry
+}
+
+/// Record a monitor created or updated by a ping made with `api_key`.
+pub async fn record_change(
+    state: &AppState,
+    api_key: &ApiKey,
+    id: &MonitorId,
+    change: MonitorChange,
+    source_ip: Option<String>,
+) {
+    let (action, detail) = match change {
+        MonitorChange::Unchanged => return,
+        MonitorChange::Created { interval_secs } => {
+            (AuditAction::Create, format!("interval {interval_secs}s"))
+        }
+        MonitorChange::IntervalChanged { from, to } => {
+            (AuditAction::Update, format!("interval {from}s -> {to}s"))
+        }
+    };
+
+    let mut entry = monitor_entry(state, api_key, action, id, source_ip);
+    entry.detail = Some(detail);
+    record(state, entry).await;
+}
+
+/// Append `entry` to the audit log.
+///
+/// Failures are logged rather than returned: the action itself has already
+/// been performed.
+pub async fn record(state: &AppState, entry: AuditEntry) {
+    if let Err(e) = state.audit_store.record(&entry).await {
+        tracing::error!(
+            action = ?entry.action,
+            slug = ?entry.slug,
+            "Failed to record audit entry: {e}"
+        );
+    }
+}
@@ -1,16 +1,50 @@
-use axum::extract::FromRequestParts;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::ops::Deref;
+
+use axum::extract::{FromRequestParts, Path};
 use axum::http::request::Parts;
-use aws_sdk_dynamodb::types::AttributeValue;
+use heartbeat_core::{ApiKeyRecord, CoreError, MonitorId, Scope, Slug, hash_api_key};
 
 use crate::errors::ApiError;
+use crate::key_cache::Cached;
+use crate::metrics::record_auth_failure;
 use crate::state::AppState;
 
+/// How often `last_used_at` is refreshed for a key in use: 5 minutes.
+const LAST_USED_RESOLUTION_SECS: i64 = 300;
+
 /// An authenticated API key extracted from the `Authorization: Bearer <key>` header.
 ///
-/// Validates the key against the DynamoDB API keys table.
-#[allow(dead_code)]
+/// Validates the key against the DynamoDB API keys table, which stores only
+/// key hashes, rejecting revoked and expired keys. Lookups go through the
+/// [`KeyCache`](crate::key_cache::KeyCache) in [`AppState`]. Handlers use one of the scoped aliases ([`PingKey`],
+/// [`ReadKey`], [`ManageKey`], [`AdminKey`]) to also require a scope.
 pub struct ApiKey {
-    pub key: String,
+    pub record: ApiKeyRecord,
+}
+
+impl ApiKey {
+    /// The project of this key, the only one whose monitors it can access.
+    pub fn project(&self) -> &str {
+        &self.record.project
+    }
+
+    /// Resolve `slug` to a monitor in the key's project, rejecting access to
+    /// monitors outside the key's slug prefix (if any).
+    pub fn monitor_id(&self, slug: Slug) -> Result<MonitorId, ApiError> {
+        if self.record.allows_slug(slug.as_ref()) {
+            Ok(MonitorId {
+                project: self.record.project.clone(),
+                slug,
+            })
+        } else {
+            record_auth_failure("slug_prefix");
+            Err(ApiError::Forbidden(format!(
+                "API key is not allowed to access monitor {slug}"
+            )))
+        }
+    }
 }
 
 impl FromRequestParts<AppState> for ApiKey {
@@ -20,39 +54,229 @@ impl FromRequestParts<AppState> for ApiKey {
         parts: &mut Parts,
         state: &AppState,
     ) -> Result<Self, Self::Rejection> {
-        let header = parts
+        let unauthorized = |reason| {
+            record_auth_failure(reason);
+            ApiError::Unauthorized
+        };
+
+        let token = parts
             .headers
             .get("authorization")
             .and_then(|v| v.to_str().ok())
-            .ok_or(ApiError::Unauthorized)?;
+            .and_then(|header| header.strip_prefix("Bearer "))
+            .filter(|token| !token.is_empty())
+            .ok_or_else(|| unauthorized("missing"))?;
 
-        let token = header
-            .strip_prefix("Bearer ")
-            .ok_or(ApiError::Unauthorized)?;
+        let token_hash = hash_api_key(token);
+        let record = cached_lookup(state, token, &token_hash)
+            .await?
+            .ok_or_else(|| unauthorized("unknown"))?;
 
-        if token.is_empty() {
-            return Err(ApiError::Unauthorized);
+        let now = state.clock.now();
+        if !record.is_active(now) {
+            return Err(unauthorized("inactive"));
         }
 
-        // Look up the key in DynamoDB
-        let result = state
-            .dynamo_client
-            .get_item()
-            .table_name(&state.keys_table)
-            .key("api_key", AttributeValue::S(token.to_string()))
-            .send()
-            .await
-            .map_err(|e| {
+        // Track usage at coarse resolution, off the request path
+        if record
+            .last_used_at
+            .is_none_or(|last| now - last >= LAST_USED_RESOLUTION_SECS)
+        {
+            state.key_cache.mark_used(&token_hash, now);
+
+            let keys_store = state.keys_store.clone();
+            let key_cache = state.key_cache.clone();
+            let key_hash = record.key_hash.clone();
+            tokio::spawn(async move {
+                match keys_store.touch_key(&key_hash, now).await {
+                    Ok(()) => {}
+                    // Deleted (or migrated) since it was cached
+                    Err(CoreError::NotFound(_)) => key_cache.invalidate(&token_hash),
+                    Err(e) => tracing::warn!("Failed to update API key last_used_at: {e}"),
+                }
+            });
+        }
+
+        Ok(ApiKey { record })
+    }
+}
+
+/// Look up the record for a bearer token through the key cache.
+///
+/// If DynamoDB can't be reached, falls back to the last cached answer so a
+/// brief outage doesn't reject every request. The cache bounds how old that
+/// answer can be, so a key revoked during a longer outage stops working.
+async fn cached_lookup(
+    state: &AppState,
+    token: &str,
+    token_hash: &str,
+) -> Result<Option<ApiKeyRecord>, ApiError> {
+    let stale = match state.key_cache.get(token_hash) {
+        Cached::Fresh(record) => return Ok(record),
+        Cached::Stale(record) => Some(record),
+        Cached::Missing => None,
+    };
+
+    match lookup_key(state, token, token_hash).await {
+        Ok(record) => {
+            state.key_cache.insert(token_hash, record.clone());
+            Ok(record)
+        }
+        Err(e) => match stale {
+            Some(record) => {
+                tracing::warn!("DynamoDB key lookup error, using cached result: {e}");
+                Ok(record)
+            }
+            None => {
                 tracing::error!("DynamoDB key lookup error: {e}");
-                ApiError::Internal
-            })?;
+                Err(ApiError::Internal)
+            }
+        },
+    }
+}
+
+/// Look up the record for a bearer token by its hash.
+///
+/// Falls back to a plaintext lookup for keys created before hashing, unless
+/// turned off once `api-keys migrate` has been run. Only plaintext records
+/// match there, so a stored hash can't be presented as a token.
+async fn lookup_key(
+    state: &AppState,
+    token: &str,
+    token_hash: &str,
+) -> Result<Option<ApiKeyRecord>, CoreError> {
+    if let Some(record) = state.keys_store.get_key(token_hash).await? {
+        return Ok(Some(record));
+    }
+    if !state.plaintext_key_fallback {
+        return Ok(None);
+    }
+
+    let record = state.keys_store.get_key(token).await?;
+    Ok(record.filter(ApiKeyRecord::is_plaintext))
+}
+
+/// A scope required by a route, used as the parameter of [`ScopedKey`].
+pub trait RequiredScope {
+    const SCOPE: Scope;
+}
+
+/// Requires the `ping` scope.
+pub struct PingScope;
+
+/// Requires the `read` scope.
+pub struct ReadScope;
+
+/// Requires the `manage` scope.
+pub struct ManageScope;
+
+/// Requires the `admin` scope.
+pub struct AdminScope;
+
+impl RequiredScope for PingScope {
+    const SCOPE: Scope = Scope::Ping;
+}
+
+impl RequiredScope for ReadScope {
+    const SCOPE: Scope = Scope::Read;
+}
+
+impl RequiredScope for ManageScope {
+    const SCOPE: Scope = Scope::Manage;
+}
+
+impl RequiredScope for AdminScope {
+    const SCOPE: Scope = Scope::Admin;
+}
+
+/// An authenticated API key that grants the scope `S`.
+///
+/// Rejects with 403 if the key is valid but lacks the scope.
+pub struct ScopedKey<S> {
+    pub key: ApiKey,
+    _scope: PhantomData<S>,
+}
+
+/// An API key granting the `ping` scope.
+pub type PingKey = ScopedKey<PingScope>;
+
+/// An API key granting the `read` scope.
+pub type ReadKey = ScopedKey<ReadScope>;
+
+/// An API key granting the `manage` scope.
+pub type ManageKey = ScopedKey<ManageScope>;
+
+/// An API key granting the `admin` scope.
+pub type AdminKey = ScopedKey<AdminScope>;
+
+impl<S> Deref for ScopedKey<S> {
+    type Target = ApiKey;
+
+    fn deref(&self) -> &ApiKey {
+        &self.key
+    }
+}
+
+impl<S: RequiredScope> FromRequestParts<AppState> for ScopedKey<S> {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let key = ApiKey::from_request_parts(parts, state).await?;
 
-        if result.item.is_none() {
-            return Err(ApiError::Unauthorized);
+        if !key.record.grants(S::SCOPE) {
+            record_auth_failure("scope");
+            return Err(ApiError::Forbidden(format!(
+                "API key lacks the '{}' scope",
+                S::SCOPE
+            )));
         }
 
-        Ok(ApiKey {
-            key: token.to_string(),
+        Ok(ScopedKey {
+            key,
+            _scope: PhantomData,
         })
     }
 }
+
+/// A monitor resolved from the `{token}` segment of a `/ping/{token}` URL.
+///
+/// The token itself is the credential: it only grants recording pings and
+/// failures for the one monitor it belongs to.
+pub struct PingToken {
+    pub id: MonitorId,
+}
+
+impl FromRequestParts<AppState> for PingToken {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let unknown = || {
+            record_auth_failure("ping_token");
+            ApiError::NotFound("Unknown ping token".to_string())
+        };
+
+        let Path(params) = Path::<HashMap<String, String>>::from_request_parts(parts, state)
+            .await
+            .map_err(|_| unknown())?;
+
+        // Tokens are UUIDs; skip the index lookup for anything else
+        let token = params
+            .get("token")
+            .filter(|t| uuid::Uuid::try_parse(t).is_ok())
+            .ok_or_else(unknown)?;
+
+        let id = state
+            .monitors_store
+            .find_monitor_by_ping_token(token)
+            .await?
+            .ok_or_else(unknown)?;
+
+        Ok(PingToken { id })
+    }
+}
@@ -0,0 +1,438 @@
+use chrono::{DateTime, Utc};
+use heartbeat_core::{
+    API_KEY_PREFIX_LEN, ApiKeyRecord, ApiKeyStore, AuditAction, AuditEntry, AuditStore,
+    DEFAULT_PROJECT, DynamoTables, Scope, Slug, StorageConfig, Stores, hash_api_key,
+};
+use rand::Rng;
+
+const USAGE: &str = "\
+usage:
+  api-keys create --description <text> --scope <scope>[,<scope>...] [--scope <scope>...]
+                  [--project <project>] [--slug-prefix <prefix>] [--expires-in <duration>]
+  api-keys list
+  api-keys revoke <key>
+  api-keys rotate <key> [--grace <duration>] [--expires-in <duration>]
+  api-keys migrate
+
+scopes: ping, read, manage, admin
+<key> is the full key or the 8-character prefix shown by `list`.
+keys only access monitors of their project (default: default).
+durations: e.g. 90d, 12h, 30m
+`migrate` replaces keys stored in plaintext by their hashes.
+create, revoke, rotate and migrate are recorded in the audit log.";
+
+/// A parsed subcommand.
+enum Command {
+    Create(CreateArgs),
+    List,
+    Revoke {
+        key: String,
+    },
+    Rotate {
+        key: String,
+        grace_secs: Option<i64>,
+        expires_in_secs: Option<i64>,
+    },
+    Migrate,
+}
+
+/// Arguments of `create`.
+struct CreateArgs {
+    description: String,
+    project: String,
+    scopes: Vec<Scope>,
+    slug_prefix: Option<String>,
+    expires_in_secs: Option<i64>,
+}
+
+#[tokio::main]
+async fn main() {
+    let mut tables = DynamoTables::default();
+    if let Ok(name) = std::env::var("KEYS_TABLE") {
+        tables.api_keys = name;
+    }
+    if let Ok(name) = std::env::var("AUDIT_TABLE") {
+        tables.audit = name;
+    }
+
+    let command = match parse_args(std::env::args().skip(1)) {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("{e}\n\n{USAGE}");
+            std::process::exit(1);
+        }
+    };
+
+    // DynamoDB, or the SQL database in DATABASE_URL as used by the API
+    let stores = match Stores::connect(&StorageConfig::from_env(tables)).await {
+        Ok(stores) => stores,
+        Err(e) => {
+            eprintln!("failed to connect to storage: {e}");
+            std::process::exit(1);
+        }
+    };
+    let (store, audit) = (stores.api_keys, stores.audit);
+
+    let result = match command {
+        Command::Create(args) => create(&store, &audit, args).await.map(|_| ()),
+        Command::List => list(&store).await,
+        Command::Revoke { key } => revoke(&store, &audit, &key).await,
+        Command::Rotate {
+            key,
+            grace_secs,
+            expires_in_secs,
+        } => rotate(&store, &audit, &key, grace_secs, expires_in_secs).await,
+        Command::Migrate => migrate(&store, &audit).await,
+    };
+
+    if let Err(e) = result {
+        eprintln!("{e}");
+        std::process::exit(1);
+    }
+}
+
+/// Create and store a new key, returning its record. Only its hash is stored,
+/// so the key is shown once here and cannot be recovered later.
+async fn create(
+    store: &ApiKeyStore,
+    audit: &AuditStore,
+    args: CreateArgs,
+) -> Result<ApiKeyRecord, String> {
+    let now = Utc::now().timestamp();
+    let token = generate_key();
+
+    let record = ApiKeyRecord {
+        project: args.project,
+        scopes: Some(args.scopes),
+        slug_prefix: args.slug_prefix,
+        expires_at: args.expires_in_secs.map(|secs| now + secs),
+        ..ApiKeyRecord::new(&token, args.description, now)
+    };
+
+    store
+        .put_key(&record)
+        .await
+        .map_err(|e| format!("Failed to store API key in DynamoDB: {e}"))?;
+
+    println!("New API key: {token} [{}]", record.description);
+    println!("{}", describe(&record, now));
+
+    record_audit(audit, AuditAction::CreateKey, &record, None).await;
+    Ok(record)
+}
+
+/// Print all keys, identified by their prefix.
+async fn list(store: &ApiKeyStore) -> Result<(), String> {
+    let now = Utc::now().timestamp();
+
+    let mut keys = store
+        .list_keys()
+        .await
+        .map_err(|e| format!("Failed to list API keys: {e}"))?;
+    keys.sort_by(|a, b| (&a.project, a.created_at).cmp(&(&b.project, b.created_at)));
+
+    for record in &keys {
+        println!(
+            "{}  [{}]  {}",
+            key_id(record),
+            record.description,
+            describe(record, now)
+        );
+    }
+
+    Ok(())
+}
+
+/// Revoke a key immediately.
+async fn revoke(store: &ApiKeyStore, audit: &AuditStore, key: &str) -> Result<(), String> {
+    let record = find_key(store, key).await?;
+    let now = Utc::now().timestamp();
+
+    store
+        .revoke_key(&record.key_hash, now)
+        .await
+        .map_err(|e| format!("Failed to revoke API key: {e}"))?;
+
+    println!("Revoked API key [{}]", record.description);
+
+    record_audit(audit, AuditAction::RevokeKey, &record, None).await;
+    Ok(())
+}
+
+/// Replace a key with a new one with the same description, project, scopes
+/// and slug prefix. The old key is revoked, or expires after the grace period if given.
+async fn rotate(
+    store: &ApiKeyStore,
+    audit: &AuditStore,
+    key: &str,
+    grace_secs: Option<i64>,
+    expires_in_secs: Option<i64>,
+) -> Result<(), String> {
+    let old = find_key(store, key).await?;
+    let now = Utc::now().timestamp();
+
+    if !old.is_active(now) {
+        return Err("Cannot rotate a revoked or expired API key".to_string());
+    }
+
+    let new = create(
+        store,
+        audit,
+        CreateArgs {
+            description: old.description.clone(),
+            project: old.project.clone(),
+            scopes: old.scopes.clone().unwrap_or_else(|| Scope::ALL.to_vec()),
+            slug_prefix: old.slug_prefix.clone(),
+            expires_in_secs,
+        },
+    )
+    .await?;
+
+    let detail = match grace_secs {
+        Some(grace) => {
+            store
+                .set_key_expiry(&old.key_hash, now + grace)
+                .await
+                .map_err(|e| format!("Failed to expire old API key: {e}"))?;
+            println!("Old API key expires at {}", format_time(Some(now + grace)));
+            format!(
+                "replaced by {}, expires at {}",
+                new.prefix(),
+                format_time(Some(now + grace))
+            )
+        }
+        None => {
+            store
+                .revoke_key(&old.key_hash, now)
+                .await
+                .map_err(|e| format!("Failed to revoke old API key: {e}"))?;
+            println!("Old API key revoked");
+            format!("replaced by {}, revoked", new.prefix())
+        }
+    };
+
+    record_audit(audit, AuditAction::RotateKey, &old, Some(detail)).await;
+    Ok(())
+}
+
+/// Replace every key stored in plaintext by its hash and prefix.
+///
+/// The hashed record is written before the plaintext one is deleted, and the
+/// API accepts both forms, so keys keep working throughout.
+async fn migrate(store: &ApiKeyStore, audit: &AuditStore) -> Result<(), String> {
+    let keys = store
+        .list_keys()
+        .await
+        .map_err(|e| format!("Failed to list API keys: {e}"))?;
+
+    let mut migrated = 0;
+    for old in keys.into_iter().filter(ApiKeyRecord::is_plaintext) {
+        let token = old.key_hash.clone();
+        let hashed = ApiKeyRecord::new(&token, "", old.created_at);
+        let record = ApiKeyRecord {
+            key_hash: hashed.key_hash,
+            key_prefix: hashed.key_prefix,
+            ..old
+        };
+
+        store
+            .put_key(&record)
+            .await
+            .map_err(|e| format!("Failed to store hashed API key: {e}"))?;
+        store
+            .delete_key(&token)
+            .await
+            .map_err(|e| format!("Failed to delete plaintext API key: {e}"))?;
+
+        println!("Migrated {}  [{}]", key_id(&record), record.description);
+        record_audit(audit, AuditAction::MigrateKey, &record, None).await;
+        migrated += 1;
+    }
+
+    println!("Migrated {migrated} API key(s)");
+    Ok(())
+}
+
+/// Record a key operation in the audit log of the key's project.
+///
+/// Failures are reported but not fatal: the operation itself already succeeded.
+async fn record_audit(
+    audit: &AuditStore,
+    action: AuditAction,
+    record: &ApiKeyRecord,
+    detail: Option<String>,
+) {
+    let user = std::env::var("USER").unwrap_or_else(|_| "unknown".to_string());
+    let mut entry = AuditEntry::new(
+        &record.project,
+        Utc::now().timestamp(),
+        action,
+        format!("api-keys CLI ({user})"),
+    );
+    entry.target_key = Some(record.prefix());
+    entry.detail = detail;
+
+    if let Err(e) = audit.record(&entry).await {
+        eprintln!("Warning: failed to record audit entry: {e}");
+    }
+}
+
+/// Find a key by its full value or its prefix.
+async fn find_key(store: &ApiKeyStore, key: &str) -> Result<ApiKeyRecord, String> {
+    if key.len() < API_KEY_PREFIX_LEN {
+        return Err(format!(
+            "Key prefix must be at least {API_KEY_PREFIX_LEN} characters"
+        ));
+    }
+    let key_hash = hash_api_key(key);
+
+    let keys = store
+        .list_keys()
+        .await
+        .map_err(|e| format!("Failed to list API keys: {e}"))?;
+
+    let mut matches = keys.into_iter().filter(|k| match &k.key_prefix {
+        Some(prefix) => k.key_hash == key_hash || prefix == key,
+        None => k.key_hash.starts_with(key),
+    });
+    match (matches.next(), matches.next()) {
+        (Some(record), None) => Ok(record),
+        (None, _) => Err(format!("No API key matches {key}")),
+        (Some(_), Some(_)) => Err(format!("More than one API key matches {key}")),
+    }
+}
+
+/// Generate a 32-byte random key, hex-encoded to 64 characters.
+fn generate_key() -> String {
+    let random_bytes: [u8; 32] = rand::rng().random();
+    random_bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// The non-secret identifier shown for a key: its prefix, marked if the key
+/// is still stored in plaintext.
+fn key_id(record: &ApiKeyRecord) -> String {
+    if record.is_plaintext() {
+        format!("{} (plaintext)", record.prefix())
+    } else {
+        record.prefix()
+    }
+}
+
+/// One-line summary of a key's status, scopes and timestamps.
+fn describe(record: &ApiKeyRecord, now: i64) -> String {
+    let status = if record.revoked_at.is_some() {
+        "revoked"
+    } else if record.is_active(now) {
+        "active"
+    } else {
+        "expired"
+    };
+
+    let scopes = match &record.scopes {
+        Some(scopes) => scopes
+            .iter()
+            .map(|s| s.as_str())
+            .collect::<Vec<_>>()
+            .join(","),
+        None => "all (legacy)".to_string(),
+    };
+
+    format!(
+        "{status}  project: {}  scopes: {scopes}  slug prefix: {}  created: {}  expires: {}  last used: {}",
+        record.project,
+        record.slug_prefix.as_deref().unwrap_or("-"),
+        format_time(Some(record.created_at)),
+        format_time(record.expires_at),
+        format_time(record.last_used_at),
+    )
+}
+
+/// Format an optional epoch timestamp for display.
+fn format_time(epoch: Option<i64>) -> String {
+    epoch
+        .and_then(|e| DateTime::<Utc>::from_timestamp(e, 0))
+        .map(|dt| dt.format("%Y-%m-%d %H:%M UTC").to_string())
+        .unwrap_or_else(|| "-".to_string())
+}
+
+/// Parse the subcommand and its arguments.
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let mut iter = args.into_iter();
+    let command = iter.next().ok_or("missing command")?;
+
+    let mut positional = Vec::new();
+    let mut description = None;
+    let mut project = DEFAULT_PROJECT.to_string();
+    let mut scopes = Vec::new();
+    let mut slug_prefix = None;
+    let mut expires_in_secs = None;
+    let mut grace_secs = None;
+
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().ok_or_else(|| format!("{arg} requires a value"));
+        match arg.as_str() {
+            "--description" => description = Some(value()?),
+            "--scope" => {
+                for scope in value()?.split(',') {
+                    let scope: Scope = scope.trim().parse()?;
+                    if !scopes.contains(&scope) {
+                        scopes.push(scope);
+                    }
+                }
+            }
+            "--project" => {
+                // Projects follow the slug rules, which also keeps `#` out of monitor keys
+                let name = value()?;
+                Slug::new(&name).map_err(|e| format!("invalid project '{name}': {e}"))?;
+                project = name;
+            }
+            "--slug-prefix" => slug_prefix = Some(value()?),
+            "--expires-in" => expires_in_secs = Some(parse_duration(&value()?)?),
+            "--grace" => grace_secs = Some(parse_duration(&value()?)?),
+            other if other.starts_with("--") => return Err(format!("unknown option: {other}")),
+            _ => positional.push(arg),
+        }
+    }
+
+    let single_key = |positional: Vec<String>| match <[String; 1]>::try_from(positional) {
+        Ok([key]) => Ok(key),
+        Err(_) => Err(format!("{command} takes exactly one <key>")),
+    };
+
+    match command.as_str() {
+        "create" => {
+            if !positional.is_empty() {
+                return Err("create takes no positional arguments".to_string());
+            }
+            let description = description.ok_or("--description is mandatory")?;
+            if scopes.is_empty() {
+                return Err("at least one --scope is mandatory".to_string());
+            }
+            Ok(Command::Create(CreateArgs {
+                description,
+                project,
+                scopes,
+                slug_prefix,
+                expires_in_secs,
+            }))
+        }
+        "list" => Ok(Command::List),
+        "migrate" => Ok(Command::Migrate),
+        "revoke" => Ok(Command::Revoke {
+            key: single_key(positional)?,
+        }),
+        "rotate" => Ok(Command::Rotate {
+            key: single_key(positional)?,
+            grace_secs,
+            expires_in_secs,
+        }),
+        other => Err(format!("unknown command: {other}")),
+    }
+}
+
+/// Parse a human-readable duration (e.g. "90d") into seconds.
+fn parse_duration(s: &str) -> Result<i64, String> {
+    humantime::parse_duration(s)
+        .map(|d| d.as_secs() as i64)
+        .map_err(|e| format!("invalid duration '{s}': {e}"))
+}
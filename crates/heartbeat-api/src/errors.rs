@@ -6,10 +6,18 @@ use axum::response::{IntoResponse, Response};
 pub enum ApiError {
     /// Missing or invalid API key.
     Unauthorized,
+    /// Valid API key without permission for the request.
+    Forbidden(String),
     /// Invalid slug format.
     InvalidSlug(String),
     /// Invalid interval value.
     InvalidInterval(String),
+    /// Unknown time zone name.
+    InvalidTimezone(String),
+    /// Invalid exit code in the ping path.
+    InvalidExitCode(String),
+    /// Malformed request body.
+    InvalidBody(String),
     /// Resource not found.
     NotFound(String),
     /// Internal server error.
@@ -23,8 +31,12 @@ impl IntoResponse for ApiError {
                 StatusCode::UNAUTHORIZED,
                 "Invalid or missing API key".to_string(),
             ),
+            ApiError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg),
             ApiError::InvalidSlug(msg) => (StatusCode::BAD_REQUEST, msg),
             ApiError::InvalidInterval(msg) => (StatusCode::BAD_REQUEST, msg),
+            ApiError::InvalidTimezone(msg) => (StatusCode::BAD_REQUEST, msg),
+            ApiError::InvalidExitCode(msg) => (StatusCode::BAD_REQUEST, msg),
+            ApiError::InvalidBody(msg) => (StatusCode::BAD_REQUEST, msg),
             ApiError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
             ApiError::Internal => (
                 StatusCode::INTERNAL_SERVER_ERROR,
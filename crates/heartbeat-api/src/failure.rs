@@ -0,0 +1,132 @@
+use serde::Deserialize;
+
+/// Maximum stored length of a failure message, in bytes.
+pub const MAX_MESSAGE_BYTES: usize = 256;
+
+/// Maximum stored length of a failure log tail, in bytes.
+pub const MAX_LOG_BYTES: usize = 2048;
+
+/// Failure details supplied in the body of a failure request.
+#[derive(Debug, Default, PartialEq, Eq, Deserialize)]
+pub struct FailureBody {
+    pub exit_code: Option<i32>,
+    pub message: Option<String>,
+    pub log: Option<String>,
+}
+
+/// Parse the body of a failure request.
+///
+/// Accepts either:
+/// - JSON (`Content-Type: application/json`) with optional `exit_code`,
+///   `message` and `log` fields
+/// - Any other body as plain text, stored as the log output
+///
+/// Messages are truncated to [`MAX_MESSAGE_BYTES`], keeping the start; logs
+/// are truncated to [`MAX_LOG_BYTES`], keeping the tail.
+pub fn parse_failure_body(content_type: Option<&str>, body: &[u8]) -> Result<FailureBody, String> {
+    let is_json = content_type
+        .and_then(|ct| ct.split(';').next())
+        .is_some_and(|mime| mime.trim().eq_ignore_ascii_case("application/json"));
+
+    let parsed = if is_json {
+        serde_json::from_slice::<FailureBody>(body)
+            .map_err(|e| format!("Cannot parse failure body: {e}"))?
+    } else {
+        FailureBody {
+            log: Some(String::from_utf8_lossy(body).into_owned()),
+            ..FailureBody::default()
+        }
+    };
+
+    Ok(FailureBody {
+        exit_code: parsed.exit_code,
+        message: parsed
+            .message
+            .map(|m| truncate_head(m.trim(), MAX_MESSAGE_BYTES))
+            .filter(|m| !m.is_empty()),
+        log: parsed
+            .log
+            .map(|l| truncate_tail(l.trim_end(), MAX_LOG_BYTES))
+            .filter(|l| !l.trim().is_empty()),
+    })
+}
+
+/// Keep at most `max` bytes from the start of `s`, on a char boundary.
+pub fn truncate_head(s: &str, max: usize) -> String {
+    let mut end = s.len().min(max);
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    s[..end].to_string()
+}
+
+/// Keep at most `max` bytes from the end of `s`, on a char boundary.
+fn truncate_tail(s: &str, max: usize) -> String {
+    let mut start = s.len().saturating_sub(max);
+    while !s.is_char_boundary(start) {
+        start += 1;
+    }
+    s[start..].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_json_body() {
+        let body = br#"{"exit_code": 2, "message": "disk full", "log": "line 1\nline 2"}"#;
+        let parsed = parse_failure_body(Some("application/json"), body).unwrap();
+        assert_eq!(parsed.exit_code, Some(2));
+        assert_eq!(parsed.message.as_deref(), Some("disk full"));
+        assert_eq!(parsed.log.as_deref(), Some("line 1\nline 2"));
+    }
+
+    #[test]
+    fn parse_json_with_charset() {
+        let body = br#"{"message": "oops"}"#;
+        let parsed = parse_failure_body(Some("application/json; charset=utf-8"), body).unwrap();
+        assert_eq!(parsed.message.as_deref(), Some("oops"));
+    }
+
+    #[test]
+    fn parse_invalid_json() {
+        assert!(parse_failure_body(Some("application/json"), b"{not json").is_err());
+    }
+
+    #[test]
+    fn parse_text_body_as_log() {
+        let parsed =
+            parse_failure_body(Some("text/plain"), b"error: connection refused\n").unwrap();
+        assert_eq!(parsed.log.as_deref(), Some("error: connection refused"));
+        assert_eq!(parsed.message, None);
+        assert_eq!(parsed.exit_code, None);
+    }
+
+    #[test]
+    fn parse_empty_body() {
+        assert_eq!(
+            parse_failure_body(None, b"").unwrap(),
+            FailureBody::default()
+        );
+    }
+
+    #[test]
+    fn log_keeps_tail() {
+        let log = format!("{}END", "x".repeat(MAX_LOG_BYTES));
+        let parsed = parse_failure_body(None, log.as_bytes()).unwrap();
+        let stored = parsed.log.unwrap();
+        assert_eq!(stored.len(), MAX_LOG_BYTES);
+        assert!(stored.ends_with("END"));
+    }
+
+    #[test]
+    fn message_keeps_head_on_char_boundary() {
+        let message = "ä".repeat(MAX_MESSAGE_BYTES);
+        let body = serde_json::json!({ "message": message }).to_string();
+        let parsed = parse_failure_body(Some("application/json"), body.as_bytes()).unwrap();
+        let stored = parsed.message.unwrap();
+        assert!(stored.len() <= MAX_MESSAGE_BYTES);
+        assert!(stored.chars().all(|c| c == 'ä'));
+    }
+}
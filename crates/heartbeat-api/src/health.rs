@@ -0,0 +1,165 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use serde::Serialize;
+
+use crate::state::AppState;
+
+/// Maximum time the DynamoDB readiness check may take: 2 seconds.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long a readiness check result is reused: 5 seconds.
+pub const CHECK_TTL: Duration = Duration::from_secs(5);
+
+/// Default time between failing readiness and stopping the server: 5 seconds.
+pub const DEFAULT_DRAIN_DELAY: Duration = Duration::from_secs(5);
+
+/// Readiness of the API to serve traffic.
+///
+/// Not ready while draining for shutdown, or if DynamoDB can't be reached.
+/// Check results are reused for a short time, so frequent probes from
+/// several kubelets don't each call DynamoDB.
+#[derive(Clone)]
+pub struct Readiness {
+    draining: Arc<AtomicBool>,
+    last_check: Arc<Mutex<Option<(Instant, bool)>>>,
+    check_ttl: Duration,
+}
+
+impl Readiness {
+    /// Create a readiness state reusing check results for `check_ttl`.
+    pub fn new(check_ttl: Duration) -> Self {
+        Self {
+            draining: Arc::new(AtomicBool::new(false)),
+            last_check: Arc::new(Mutex::new(None)),
+            check_ttl,
+        }
+    }
+
+    /// Report not ready from now on, so the load balancer stops sending traffic.
+    pub fn start_draining(&self) {
+        self.draining.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the server is draining for shutdown.
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::Relaxed)
+    }
+
+    /// The last check result, if made within the TTL.
+    fn cached(&self) -> Option<bool> {
+        self.last_check
+            .lock()
+            .unwrap()
+            .filter(|(at, _)| at.elapsed() < self.check_ttl)
+            .map(|(_, ok)| ok)
+    }
+
+    fn store(&self, ok: bool) {
+        *self.last_check.lock().unwrap() = Some((Instant::now(), ok));
+    }
+}
+
+#[derive(Serialize)]
+pub struct HealthResponse {
+    pub status: &'static str,
+}
+
+/// GET /healthz
+///
+/// Liveness: succeeds as long as the server is handling requests.
+pub async fn healthz_handler() -> Json<HealthResponse> {
+    Json(HealthResponse { status: "ok" })
+}
+
+/// GET /readyz
+///
+/// Readiness: fails with 503 while draining for shutdown or if the monitors
+/// or API keys table can't be described within 2 seconds.
+pub async fn readyz_handler(State(state): State<AppState>) -> (StatusCode, Json<HealthResponse>) {
+    let readiness = &state.readiness;
+    if readiness.is_draining() {
+        return not_ready("draining");
+    }
+
+    let ok = match readiness.cached() {
+        Some(ok) => ok,
+        None => {
+            let ok = check_tables(&state).await;
+            readiness.store(ok);
+            ok
+        }
+    };
+
+    if ok {
+        (StatusCode::OK, Json(HealthResponse { status: "ready" }))
+    } else {
+        not_ready("unavailable")
+    }
+}
+
+fn not_ready(status: &'static str) -> (StatusCode, Json<HealthResponse>) {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(HealthResponse { status }),
+    )
+}
+
+/// Check that both tables can be reached within [`CHECK_TIMEOUT`].
+async fn check_tables(state: &AppState) -> bool {
+    let checks = async {
+        tokio::try_join!(
+            state.monitors_store.check_table(),
+            state.keys_store.check_table(),
+        )
+    };
+
+    match tokio::time::timeout(CHECK_TIMEOUT, checks).await {
+        Ok(Ok(_)) => true,
+        Ok(Err(e)) => {
+            tracing::warn!("Readiness check failed: {e}");
+            false
+        }
+        Err(_) => {
+            tracing::warn!("Readiness check timed out");
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn caches_check_results() {
+        let readiness = Readiness::new(CHECK_TTL);
+        assert_eq!(readiness.cached(), None);
+
+        readiness.store(false);
+        assert_eq!(readiness.cached(), Some(false));
+        readiness.store(true);
+        assert_eq!(readiness.cached(), Some(true));
+    }
+
+    #[test]
+    fn expired_results_are_not_reused() {
+        let readiness = Readiness::new(Duration::ZERO);
+        readiness.store(true);
+        assert_eq!(readiness.cached(), None);
+    }
+
+    #[test]
+    fn draining_is_shared_between_clones() {
+        let readiness = Readiness::new(CHECK_TTL);
+        let clone = readiness.clone();
+        assert!(!readiness.is_draining());
+
+        clone.start_draining();
+        assert!(readiness.is_draining());
+    }
+}
@@ -0,0 +1,232 @@
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use heartbeat_core::ApiKeyRecord;
+use lru::LruCache;
+
+/// Default time a key lookup is trusted before it is refreshed: 60 seconds.
+pub const DEFAULT_TTL: Duration = Duration::from_secs(60);
+
+/// Default time an expired lookup can still be used while the keys table is
+/// unreachable: 10 minutes.
+pub const DEFAULT_MAX_STALE: Duration = Duration::from_secs(600);
+
+/// Default maximum number of cached lookups.
+pub const DEFAULT_CAPACITY: usize = 10_000;
+
+/// Result of a cache lookup.
+#[derive(Debug, PartialEq)]
+pub enum Cached {
+    /// Looked up within the TTL: `Some` for a known key, `None` for an unknown one.
+    Fresh(Option<ApiKeyRecord>),
+    /// Looked up longer ago than the TTL, but within the maximum staleness.
+    /// Only used if a refresh fails.
+    Stale(Option<ApiKeyRecord>),
+    /// Never looked up, evicted, or too old to be used at all.
+    Missing,
+}
+
+/// A bounded in-process cache of API key lookups, keyed by token hash.
+///
+/// Caches both known keys and unknown tokens, so neither repeated pings nor
+/// repeated bad tokens hit DynamoDB on every request. Expired entries are kept
+/// until evicted, so a failed refresh can fall back to the last known answer
+/// for up to the maximum staleness.
+///
+/// The cache is per process, so revocations made elsewhere (e.g. with
+/// `api-keys revoke`) take effect once the entry expires: after at most the
+/// TTL, or the maximum staleness while the keys table is unreachable.
+#[derive(Clone)]
+pub struct KeyCache {
+    entries: Arc<Mutex<Option<Entries>>>,
+    ttl: Duration,
+    max_stale: Duration,
+}
+
+/// Known keys and unknown tokens, each evicting its least recently used entry
+/// when full. Unknown tokens have a smaller bound, so a stream of random
+/// tokens can't push out the keys in use.
+struct Entries {
+    known: LruCache<String, Entry>,
+    unknown: LruCache<String, Instant>,
+}
+
+struct Entry {
+    record: ApiKeyRecord,
+    fetched_at: Instant,
+}
+
+impl KeyCache {
+    /// Create a cache trusting lookups for `ttl`, holding at most `capacity`
+    /// known keys and a tenth as many unknown tokens.
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        let entries = NonZeroUsize::new(capacity).map(|known| Entries {
+            known: LruCache::new(known),
+            unknown: LruCache::new(NonZeroUsize::new(capacity.div_ceil(10)).unwrap_or(known)),
+        });
+        Self {
+            entries: Arc::new(Mutex::new(entries)),
+            ttl,
+            max_stale: DEFAULT_MAX_STALE,
+        }
+    }
+
+    /// Use expired lookups for at most `max_stale` after they were fetched.
+    pub fn with_max_stale(mut self, max_stale: Duration) -> Self {
+        self.max_stale = max_stale;
+        self
+    }
+
+    /// Look up a token hash.
+    pub fn get(&self, token_hash: &str) -> Cached {
+        let mut entries = self.entries.lock().unwrap();
+        let Some(entries) = entries.as_mut() else {
+            return Cached::Missing;
+        };
+        let (record, fetched_at) = if let Some(entry) = entries.known.get(token_hash) {
+            (Some(entry.record.clone()), entry.fetched_at)
+        } else if let Some(fetched_at) = entries.unknown.get(token_hash) {
+            (None, *fetched_at)
+        } else {
+            return Cached::Missing;
+        };
+
+        let age = fetched_at.elapsed();
+        if age < self.ttl {
+            Cached::Fresh(record)
+        } else if age < self.max_stale {
+            Cached::Stale(record)
+        } else {
+            Cached::Missing
+        }
+    }
+
+    /// Store the result of a lookup: `Some` for a known key, `None` for an unknown one.
+    ///
+    /// When full, the least recently used entry of the same kind is dropped.
+    pub fn insert(&self, token_hash: &str, record: Option<ApiKeyRecord>) {
+        let mut entries = self.entries.lock().unwrap();
+        let Some(entries) = entries.as_mut() else {
+            return;
+        };
+        let fetched_at = Instant::now();
+        match record {
+            Some(record) => {
+                entries.unknown.pop(token_hash);
+                entries
+                    .known
+                    .put(token_hash.to_string(), Entry { record, fetched_at });
+            }
+            None => {
+                entries.known.pop(token_hash);
+                entries.unknown.put(token_hash.to_string(), fetched_at);
+            }
+        }
+    }
+
+    /// Record that a cached key was used at `now_epoch`, so usage isn't
+    /// tracked again until the next refresh is due.
+    pub fn mark_used(&self, token_hash: &str, now_epoch: i64) {
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(entry) = entries
+            .as_mut()
+            .and_then(|entries| entries.known.peek_mut(token_hash))
+        {
+            entry.record.last_used_at = Some(now_epoch);
+        }
+    }
+
+    /// Drop a cached lookup, so the next request reads the key again.
+    pub fn invalidate(&self, token_hash: &str) {
+        if let Some(entries) = self.entries.lock().unwrap().as_mut() {
+            entries.known.pop(token_hash);
+            entries.unknown.pop(token_hash);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record() -> ApiKeyRecord {
+        ApiKeyRecord::new("0123456789abcdef", "test", 1000)
+    }
+
+    #[test]
+    fn fresh_positive_and_negative_entries() {
+        let cache = KeyCache::new(DEFAULT_TTL, 10);
+        assert_eq!(cache.get("a"), Cached::Missing);
+
+        cache.insert("a", Some(record()));
+        cache.insert("b", None);
+        assert!(matches!(cache.get("a"), Cached::Fresh(Some(_))));
+        assert_eq!(cache.get("b"), Cached::Fresh(None));
+    }
+
+    #[test]
+    fn expired_entries_are_stale() {
+        let cache = KeyCache::new(Duration::ZERO, 10);
+        cache.insert("a", Some(record()));
+        assert!(matches!(cache.get("a"), Cached::Stale(Some(_))));
+    }
+
+    #[test]
+    fn entries_past_max_stale_are_missing() {
+        let cache = KeyCache::new(Duration::ZERO, 10).with_max_stale(Duration::ZERO);
+        cache.insert("a", Some(record()));
+        assert_eq!(cache.get("a"), Cached::Missing);
+    }
+
+    #[test]
+    fn invalidate_drops_entry() {
+        let cache = KeyCache::new(DEFAULT_TTL, 10);
+        cache.insert("a", Some(record()));
+        cache.invalidate("a");
+        assert_eq!(cache.get("a"), Cached::Missing);
+    }
+
+    #[test]
+    fn mark_used_updates_cached_record() {
+        let cache = KeyCache::new(DEFAULT_TTL, 10);
+        cache.insert("a", Some(record()));
+        cache.mark_used("a", 2000);
+        let Cached::Fresh(Some(cached)) = cache.get("a") else {
+            panic!("expected a fresh entry");
+        };
+        assert_eq!(cached.last_used_at, Some(2000));
+    }
+
+    #[test]
+    fn evicts_least_recently_used_when_full() {
+        let cache = KeyCache::new(DEFAULT_TTL, 2);
+        cache.insert("a", Some(record()));
+        cache.insert("b", Some(record()));
+        assert!(matches!(cache.get("a"), Cached::Fresh(Some(_))));
+        cache.insert("c", Some(record()));
+        assert!(matches!(cache.get("a"), Cached::Fresh(Some(_))));
+        assert_eq!(cache.get("b"), Cached::Missing);
+        assert!(matches!(cache.get("c"), Cached::Fresh(Some(_))));
+    }
+
+    #[test]
+    fn unknown_tokens_do_not_evict_known_keys() {
+        let cache = KeyCache::new(DEFAULT_TTL, 20);
+        cache.insert("key", Some(record()));
+        for i in 0..100 {
+            cache.insert(&format!("random-{i}"), None);
+        }
+        assert!(matches!(cache.get("key"), Cached::Fresh(Some(_))));
+        assert_eq!(cache.get("random-97"), Cached::Missing);
+        assert_eq!(cache.get("random-98"), Cached::Fresh(None));
+        assert_eq!(cache.get("random-99"), Cached::Fresh(None));
+    }
+
+    #[test]
+    fn zero_capacity_disables_cache() {
+        let cache = KeyCache::new(DEFAULT_TTL, 0);
+        cache.insert("a", None);
+        assert_eq!(cache.get("a"), Cached::Missing);
+    }
+}
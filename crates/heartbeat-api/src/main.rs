@@ -1,18 +1,36 @@
+mod app;
+mod audit;
 mod auth;
 mod errors;
+mod failure;
+mod health;
 mod interval;
+mod key_cache;
+mod metrics;
+mod notifier;
+mod ping;
 mod routes;
 mod state;
+mod status;
 
 use std::net::SocketAddr;
+use std::time::Duration;
 
-use axum::Router;
 use tokio::net::TcpListener;
-use tower_http::trace::TraceLayer;
 use tracing_subscriber::EnvFilter;
 
-use heartbeat_core::DynamoStore;
+use heartbeat_checker::alerts::AlertTemplates;
+use heartbeat_checker::checker::Checker;
+use heartbeat_checker::leader;
+use heartbeat_checker::runner::{self, Schedule, ScheduleMode};
+use heartbeat_checker::scheduler::DueUpdates;
+use heartbeat_checker::telegram::TelegramClient;
+use heartbeat_core::{Clock, DynamoTables, StorageConfig, Stores};
 
+use crate::health::Readiness;
+use crate::key_cache::KeyCache;
+use crate::metrics::{MonitorLabels, MonitorSnapshot};
+use crate::notifier::Notifier;
 use crate::state::AppState;
 
 #[tokio::main]
@@ -27,50 +45,172 @@ async fn main() {
         std::env::var("MONITORS_TABLE").unwrap_or_else(|_| "heartbeat-monitors".to_string());
     let keys_table =
         std::env::var("KEYS_TABLE").unwrap_or_else(|_| "heartbeat-api-keys".to_string());
+    let audit_table =
+        std::env::var("AUDIT_TABLE").unwrap_or_else(|_| "heartbeat-audit".to_string());
+    let status_table =
+        std::env::var("STATUS_TABLE").unwrap_or_else(|_| "heartbeat-status".to_string());
+    // An SQL database in DATABASE_URL replaces the tables above
+    let storage = StorageConfig::from_env(DynamoTables {
+        monitors: monitors_table,
+        api_keys: keys_table,
+        audit: audit_table,
+        status: status_table,
+        ..DynamoTables::default()
+    });
     let bind_addr = std::env::var("BIND_ADDR").unwrap_or_else(|_| "0.0.0.0:3000".to_string());
+    let key_cache_ttl = std::env::var("KEY_CACHE_TTL")
+        .ok()
+        .map(|v| humantime::parse_duration(&v).expect("Invalid KEY_CACHE_TTL"))
+        .unwrap_or(key_cache::DEFAULT_TTL);
+    let key_cache_max_stale = std::env::var("KEY_CACHE_MAX_STALE")
+        .ok()
+        .map(|v| humantime::parse_duration(&v).expect("Invalid KEY_CACHE_MAX_STALE"))
+        .unwrap_or(key_cache::DEFAULT_MAX_STALE);
+    let key_cache_capacity = std::env::var("KEY_CACHE_CAPACITY")
+        .ok()
+        .map(|v| v.parse::<usize>().expect("Invalid KEY_CACHE_CAPACITY"))
+        .unwrap_or(key_cache::DEFAULT_CAPACITY);
+    // Off once `api-keys migrate` has hashed all keys, saving a lookup per
+    // unknown token
+    let plaintext_key_fallback = match std::env::var("API_KEYS_PLAINTEXT_FALLBACK").as_deref() {
+        Ok("true" | "1") | Err(_) => true,
+        Ok("false" | "0") => false,
+        Ok(other) => panic!("Invalid API_KEYS_PLAINTEXT_FALLBACK: {other}"),
+    };
+    let metrics_max_monitors = std::env::var("METRICS_MAX_MONITORS")
+        .ok()
+        .map(|v| v.parse::<usize>().expect("Invalid METRICS_MAX_MONITORS"))
+        .unwrap_or(metrics::DEFAULT_MAX_MONITOR_LABELS);
+    let metrics_refresh_interval = std::env::var("METRICS_REFRESH_INTERVAL")
+        .ok()
+        .map(|v| humantime::parse_duration(&v).expect("Invalid METRICS_REFRESH_INTERVAL"))
+        .unwrap_or(metrics::DEFAULT_REFRESH_INTERVAL);
+    let drain_delay = std::env::var("SHUTDOWN_DRAIN_DELAY")
+        .ok()
+        .map(|v| humantime::parse_duration(&v).expect("Invalid SHUTDOWN_DRAIN_DELAY"))
+        .unwrap_or(health::DEFAULT_DRAIN_DELAY);
+    let checker_stale_after = std::env::var("CHECKER_STALE_AFTER")
+        .ok()
+        .map(|v| humantime::parse_duration(&v).expect("Invalid CHECKER_STALE_AFTER"))
+        .unwrap_or(status::DEFAULT_CHECKER_STALE_AFTER);
+
+    // Optional Telegram chat for the API's own alerts
+    let notifier = match (
+        std::env::var("TELEGRAM_BOT_TOKEN"),
+        std::env::var("TELEGRAM_CHAT_ID"),
+    ) {
+        (Ok(bot_token), Ok(chat_id)) => {
+            let notifier = Notifier::new(bot_token, chat_id);
+            Some(match std::env::var("TELEGRAM_API_URL") {
+                Ok(api_url) if !api_url.is_empty() => notifier.with_api_url(api_url),
+                _ => notifier,
+            })
+        }
+        _ => None,
+    };
 
-    tracing::info!(monitors_table, keys_table, bind_addr, "Starting heartbeat-api");
+    // Optional checker running alongside the server, for deployments without
+    // the Lambda. It shares the Telegram settings above, and also reads
+    // TELEGRAM_PROJECT_CHAT_IDS, ALERT_TEMPLATES and the *_FILE variants.
+    let embedded_checker = match std::env::var("EMBEDDED_CHECKER").as_deref() {
+        Ok("true" | "1") => {
+            let schedule = Schedule::from_env().expect("Invalid embedded checker schedule");
+            let telegram =
+                TelegramClient::from_env().expect("Invalid embedded checker Telegram settings");
+            let templates =
+                AlertTemplates::from_env().expect("Invalid embedded checker alert templates");
+            Some((schedule, telegram, templates))
+        }
+        Ok("false" | "0") | Err(_) => None,
+        Ok(other) => panic!("Invalid EMBEDDED_CHECKER: {other}"),
+    };
+    // Pings reschedule the embedded checker directly when it runs on due times
+    let (due_updates, due_updates_rx) = match embedded_checker {
+        Some((schedule, _, _)) if schedule.mode == ScheduleMode::Due => {
+            let (sender, receiver) = DueUpdates::channel();
+            (Some(sender), Some(receiver))
+        }
+        _ => (None, None),
+    };
 
-    // Initialize DynamoDB store
-    let monitors_store = DynamoStore::new(&monitors_table).await;
+    tracing::info!(
+        storage = storage.backend(),
+        bind_addr,
+        key_cache_ttl_secs = key_cache_ttl.as_secs(),
+        key_cache_max_stale_secs = key_cache_max_stale.as_secs(),
+        key_cache_capacity,
+        plaintext_key_fallback,
+        metrics_max_monitors,
+        metrics_refresh_interval_secs = metrics_refresh_interval.as_secs(),
+        drain_delay_secs = drain_delay.as_secs(),
+        checker_stale_after_secs = checker_stale_after.as_secs(),
+        notifier = notifier.is_some(),
+        embedded_checker = embedded_checker.is_some(),
+        "Starting heartbeat-api"
+    );
 
-    // Share the underlying DynamoDB client for key lookups
-    let dynamo_client = monitors_store.client().clone();
+    // Install the metrics recorder before the DynamoDB client records into it
+    let metrics_handle = metrics::install();
+
+    // Connect to the storage backend, sharing one DynamoDB client or SQL
+    // connection pool between the stores
+    let stores = Stores::connect(&storage)
+        .await
+        .expect("Failed to connect to storage");
 
     let state = AppState {
-        monitors_store,
-        dynamo_client,
-        keys_table,
+        monitors_store: stores.monitors,
+        keys_store: stores.api_keys,
+        key_cache: KeyCache::new(key_cache_ttl, key_cache_capacity)
+            .with_max_stale(key_cache_max_stale),
+        plaintext_key_fallback,
+        audit_store: stores.audit,
+        metrics: metrics_handle,
+        monitor_labels: MonitorLabels::new(metrics_max_monitors),
+        monitor_snapshot: MonitorSnapshot::new(metrics_refresh_interval),
+        readiness: Readiness::new(health::CHECK_TTL),
+        status_store: stores.status,
+        checker_stale_after,
+        due_updates,
+        clock: Clock::System,
     };
+    let readiness = state.readiness.clone();
 
-    // Build router
-    let app = Router::new()
-        .route(
-            "/heartbeat/{slug}",
-            axum::routing::get(routes::heartbeat_handler),
-        )
-        .route(
-            "/heartbeat/{slug}/fail",
-            axum::routing::post(routes::fail_handler),
-        )
-        .route(
-            "/monitors",
-            axum::routing::get(routes::list_monitors_handler),
-        )
-        .route(
-            "/monitors/{slug}",
-            axum::routing::delete(routes::delete_monitor_handler),
-        )
-        .route(
-            "/monitors/{slug}/pause",
-            axum::routing::post(routes::pause_handler),
-        )
-        .route(
-            "/monitors/{slug}/unpause",
-            axum::routing::post(routes::unpause_handler),
+    metrics::spawn_status_refresh(
+        state.monitors_store.clone(),
+        state.monitor_snapshot.clone(),
+        metrics_refresh_interval,
+        state.clock.clone(),
+    );
+    status::spawn_checker_watchdog(
+        state.status_store.clone(),
+        notifier,
+        checker_stale_after,
+        state.clock.clone(),
+    );
+
+    // Stopped once the server has shut down
+    let (checker_shutdown, checker_shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+    let checker = embedded_checker.map(|(schedule, telegram, templates)| {
+        let checker = Checker::new(
+            state.monitors_store.clone(),
+            state.status_store.clone(),
+            telegram,
         )
-        .layer(TraceLayer::new_for_http())
-        .with_state(state);
+        .with_clock(state.clock.clone())
+        .with_templates(templates);
+        tokio::spawn(runner::run(
+            checker,
+            schedule,
+            leader::owner_id(std::env::var("HOSTNAME").ok()),
+            due_updates_rx,
+            async move {
+                let _ = checker_shutdown_rx.await;
+            },
+        ))
+    });
+
+    let app = app::router(state);
 
     // Bind and serve
     let addr: SocketAddr = bind_addr.parse().expect("Invalid BIND_ADDR");
@@ -80,14 +220,28 @@ async fn main() {
 
     tracing::info!(%addr, "Listening");
 
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal())
-        .await
-        .expect("Server error");
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown_signal(readiness, drain_delay))
+    .await
+    .expect("Server error");
+
+    // Let a cycle in progress finish and release the leader lease
+    drop(checker_shutdown);
+    if let Some(checker) = checker
+        && let Err(e) = checker.await
+    {
+        tracing::error!("Embedded checker failed: {e}");
+    }
 }
 
 /// Wait for SIGTERM or SIGINT for graceful shutdown.
-async fn shutdown_signal() {
+///
+/// Readiness then fails for `drain_delay` before the server stops accepting
+/// connections, giving Kubernetes time to stop routing traffic to it.
+async fn shutdown_signal(readiness: Readiness, drain_delay: Duration) {
     let ctrl_c = async {
         tokio::signal::ctrl_c()
             .await
@@ -109,4 +263,8 @@ async fn shutdown_signal() {
         _ = ctrl_c => tracing::info!("Ctrl+C received, shutting down"),
         _ = terminate => tracing::info!("SIGTERM received, shutting down"),
     }
+
+    readiness.start_draining();
+    tokio::time::sleep(drain_delay).await;
+    tracing::info!("Drained, stopping server");
 }
@@ -0,0 +1,482 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::extract::{MatchedPath, Query, Request, State};
+use axum::http::header;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use serde::Deserialize;
+
+use heartbeat_core::{Clock, CoreError, Monitor, MonitorId, MonitorStatus, MonitorStore};
+
+use crate::auth::ReadKey;
+use crate::errors::ApiError;
+use crate::state::AppState;
+
+/// Counter of HTTP requests, labelled by `method`, `route` and `status`.
+pub const HTTP_REQUESTS: &str = "heartbeat_http_requests_total";
+
+/// Histogram of HTTP request durations, labelled by `method`, `route` and `status`.
+pub const HTTP_DURATION: &str = "heartbeat_http_request_duration_seconds";
+
+/// Counter of recorded pings, labelled by `project`, `monitor` and `result`.
+pub const PINGS: &str = "heartbeat_pings_total";
+
+/// Counter of rejected credentials, labelled by `reason`.
+pub const AUTH_FAILURES: &str = "heartbeat_auth_failures_total";
+
+/// Gauge of monitors, labelled by `project` and `status`.
+pub const MONITORS: &str = "heartbeat_monitors";
+
+/// Default maximum number of monitors with their own `monitor` label.
+pub const DEFAULT_MAX_MONITOR_LABELS: usize = 1000;
+
+/// Default interval between refreshes of the monitor status gauges: 60 seconds.
+pub const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// `monitor` label of pings for monitors beyond the label limit.
+const OTHER_MONITORS: &str = "_other";
+
+/// How often histograms are drained into their buckets.
+const UPKEEP_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Buckets of the `*_duration_seconds` histograms, from 5ms to 10s.
+const DURATION_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Install the global Prometheus recorder and keep its histograms drained.
+///
+/// Also receives the DynamoDB metrics recorded by `heartbeat-core`.
+pub fn install() -> PrometheusHandle {
+    let handle = PrometheusBuilder::new()
+        .set_buckets_for_metric(
+            Matcher::Suffix("duration_seconds".to_string()),
+            DURATION_BUCKETS,
+        )
+        .expect("Invalid histogram buckets")
+        .install_recorder()
+        .expect("Failed to install metrics recorder");
+
+    let upkeep = handle.clone();
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(UPKEEP_INTERVAL);
+        loop {
+            ticker.tick().await;
+            upkeep.run_upkeep();
+        }
+    });
+
+    handle
+}
+
+/// GET /metrics
+///
+/// Returns all metrics in the Prometheus text format.
+pub async fn metrics_handler(State(state): State<AppState>) -> String {
+    state.metrics.render()
+}
+
+#[derive(Deserialize)]
+pub struct MonitorMetricsQuery {
+    pub prefix: Option<String>,
+}
+
+/// GET /metrics/monitors?prefix=<slug prefix>
+///
+/// Returns the state of the monitors visible to the API key (its project's
+/// monitors, filtered by its slug prefix and `prefix`) in the Prometheus text
+/// format, for alerting from Prometheus instead of, or as well as, Telegram.
+///
+/// Monitors have no tags, so series are selected by slug prefix rather than
+/// by tag: name related monitors alike (e.g. `backup-`) to scrape them
+/// together.
+///
+/// Monitors are read from the [`MonitorSnapshot`], so scrapes don't read the
+/// whole table; the statuses are derived at the time of the scrape.
+pub async fn monitor_metrics_handler(
+    State(state): State<AppState>,
+    api_key: ReadKey,
+    Query(query): Query<MonitorMetricsQuery>,
+) -> Result<Response, ApiError> {
+    let snapshot = state.monitor_snapshot.get(&state.monitors_store).await?;
+    let mut monitors: Vec<&Monitor> = snapshot
+        .iter()
+        .filter(|m| m.project == api_key.project() && api_key.record.allows_slug(&m.slug))
+        .filter(|m| query.prefix.as_ref().is_none_or(|p| m.slug.starts_with(p)))
+        .collect();
+    monitors.sort_by(|a, b| a.slug.cmp(&b.slug));
+
+    let body = render_monitors(&monitors, state.clock.now());
+    Ok(([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body).into_response())
+}
+
+/// Render per-monitor series, labelled by `project` and `monitor`.
+fn render_monitors(monitors: &[&Monitor], now_epoch: i64) -> String {
+    let mut out = String::new();
+
+    let mut gauge = |name: &str, help: &str, value: &dyn Fn(&Monitor) -> i64| {
+        writeln!(out, "# HELP {name} {help}").unwrap();
+        writeln!(out, "# TYPE {name} gauge").unwrap();
+        for monitor in monitors {
+            let labels = series_labels(monitor);
+            writeln!(out, "{name}{{{labels}}} {}", value(monitor)).unwrap();
+        }
+    };
+
+    gauge(
+        "heartbeat_last_ping_timestamp",
+        "Unix time of the last successful ping.",
+        &|m| m.last_ping,
+    );
+    gauge(
+        "heartbeat_next_due_timestamp",
+        "Unix time the next ping is due.",
+        &|m| m.next_due,
+    );
+    gauge(
+        "heartbeat_interval_seconds",
+        "Expected interval between pings.",
+        &|m| m.interval_secs as i64,
+    );
+    gauge(
+        "heartbeat_alert_count",
+        "Alerts sent for the current incident.",
+        &|m| i64::from(m.alert_count.unwrap_or(0)),
+    );
+
+    out.push_str("# HELP heartbeat_status 1 for the monitor's current status, 0 otherwise.\n");
+    out.push_str("# TYPE heartbeat_status gauge\n");
+    for monitor in monitors {
+        let labels = series_labels(monitor);
+        let current = MonitorStatus::derive(monitor, now_epoch);
+        for status in MonitorStatus::ALL {
+            writeln!(
+                out,
+                "heartbeat_status{{{labels},status=\"{}\"}} {}",
+                status.as_str(),
+                u8::from(status == current)
+            )
+            .unwrap();
+        }
+    }
+
+    out
+}
+
+/// The `project` and `monitor` labels of a monitor.
+fn series_labels(monitor: &Monitor) -> String {
+    format!(
+        "project=\"{}\",monitor=\"{}\"",
+        escape_label(&monitor.project),
+        escape_label(&monitor.slug)
+    )
+}
+
+/// Escape a label value for the Prometheus text format.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Middleware counting and timing requests per route and status.
+///
+/// Requests that match no route are labelled `unmatched`, so scans for
+/// random paths can't create new series.
+pub async fn track_requests(request: Request, next: Next) -> Response {
+    let start = Instant::now();
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or("unmatched", MatchedPath::as_str)
+        .to_string();
+
+    let response = next.run(request).await;
+
+    let labels = [
+        ("method", method),
+        ("route", route),
+        ("status", response.status().as_u16().to_string()),
+    ];
+    metrics::counter!(HTTP_REQUESTS, &labels).increment(1);
+    metrics::histogram!(HTTP_DURATION, &labels).record(start.elapsed());
+
+    response
+}
+
+/// Count a rejected API key or ping token.
+pub fn record_auth_failure(reason: &'static str) {
+    metrics::counter!(AUTH_FAILURES, "reason" => reason).increment(1);
+}
+
+/// Assigns `monitor` labels to the first monitors pinged, up to a limit.
+///
+/// Pings of later monitors are counted under `_other`, bounding the number
+/// of series no matter how many monitors are created. A limit of 0 counts
+/// all pings under `_other`.
+#[derive(Clone)]
+pub struct MonitorLabels {
+    labelled: Arc<Mutex<HashSet<String>>>,
+    capacity: usize,
+}
+
+impl MonitorLabels {
+    /// Create labels for at most `capacity` monitors.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            labelled: Arc::new(Mutex::new(HashSet::new())),
+            capacity,
+        }
+    }
+
+    /// The `monitor` label for a monitor.
+    pub fn label(&self, id: &MonitorId) -> String {
+        let key = id.key();
+        let mut labelled = self.labelled.lock().unwrap();
+        if labelled.contains(&key) || labelled.len() < self.capacity {
+            labelled.insert(key);
+            id.slug.to_string()
+        } else {
+            OTHER_MONITORS.to_string()
+        }
+    }
+
+    /// Count a ping for a monitor: `result` is `ok` or `fail`.
+    pub fn record_ping(&self, id: &MonitorId, result: &'static str) {
+        metrics::counter!(
+            PINGS,
+            "project" => id.project.clone(),
+            "monitor" => self.label(id),
+            "result" => result
+        )
+        .increment(1);
+    }
+}
+
+/// All monitors, as last read from the store, shared by the status gauges and
+/// `/metrics/monitors`.
+///
+/// Read again when older than `max_age`, and replaced by every refresh of
+/// the gauges, so Prometheus scrapes cost no reads of their own.
+#[derive(Clone)]
+pub struct MonitorSnapshot {
+    snapshot: Arc<tokio::sync::Mutex<Option<Snapshot>>>,
+    max_age: Duration,
+}
+
+struct Snapshot {
+    monitors: Arc<Vec<Monitor>>,
+    read_at: Instant,
+}
+
+impl Snapshot {
+    fn new(monitors: Vec<Monitor>) -> Self {
+        Self {
+            monitors: Arc::new(monitors),
+            read_at: Instant::now(),
+        }
+    }
+}
+
+impl MonitorSnapshot {
+    /// Create an empty snapshot, read on first use.
+    pub fn new(max_age: Duration) -> Self {
+        Self {
+            snapshot: Arc::new(tokio::sync::Mutex::new(None)),
+            max_age,
+        }
+    }
+
+    /// The monitors, read from `store` if the snapshot is missing or older
+    /// than its maximum age. Concurrent callers wait for a single read.
+    pub async fn get(&self, store: &MonitorStore) -> Result<Arc<Vec<Monitor>>, CoreError> {
+        let mut snapshot = self.snapshot.lock().await;
+        if let Some(current) = snapshot.as_ref()
+            && current.read_at.elapsed() < self.max_age
+        {
+            return Ok(current.monitors.clone());
+        }
+
+        let current = snapshot.insert(Snapshot::new(store.list_monitors().await?));
+        Ok(current.monitors.clone())
+    }
+
+    /// Replace the snapshot by `monitors`, just read from the store.
+    async fn set(&self, monitors: Vec<Monitor>) {
+        *self.snapshot.lock().await = Some(Snapshot::new(monitors));
+    }
+}
+
+/// Refresh the monitor status gauges every `interval` in the background,
+/// deriving the statuses at the time on `clock`, and keep `snapshot` up to
+/// date with the monitors read.
+///
+/// Projects that no longer have monitors are reported with zero counts
+/// rather than left at their last value.
+pub fn spawn_status_refresh(
+    store: MonitorStore,
+    snapshot: MonitorSnapshot,
+    interval: Duration,
+    clock: Clock,
+) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        let mut projects = HashSet::new();
+
+        loop {
+            ticker.tick().await;
+
+            let monitors = match store.list_monitors().await {
+                Ok(monitors) => monitors,
+                Err(e) => {
+                    tracing::warn!("Failed to refresh monitor metrics: {e}");
+                    continue;
+                }
+            };
+
+            let counts = count_by_status(&monitors, clock.now());
+            projects.extend(counts.keys().cloned());
+            snapshot.set(monitors).await;
+
+            for project in &projects {
+                let project_counts = counts.get(project).copied().unwrap_or_default();
+                for status in MonitorStatus::ALL {
+                    metrics::gauge!(
+                        MONITORS,
+                        "project" => project.clone(),
+                        "status" => status.as_str()
+                    )
+                    .set(project_counts[status as usize] as f64);
+                }
+            }
+        }
+    });
+}
+
+/// Number of monitors per project in each status, indexed like [`MonitorStatus::ALL`].
+fn count_by_status(monitors: &[Monitor], now_epoch: i64) -> HashMap<String, [u64; 5]> {
+    let mut counts: HashMap<String, [u64; 5]> = HashMap::new();
+    for monitor in monitors {
+        let status = MonitorStatus::derive(monitor, now_epoch);
+        counts.entry(monitor.project.clone()).or_default()[status as usize] += 1;
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use heartbeat_core::Slug;
+
+    use super::*;
+
+    fn id(project: &str, slug: &str) -> MonitorId {
+        MonitorId {
+            project: project.to_string(),
+            slug: Slug::new(slug).unwrap(),
+        }
+    }
+
+    fn monitor(project: &str, next_due: i64, paused: bool) -> Monitor {
+        Monitor {
+            slug: "job".to_string(),
+            project: project.to_string(),
+            interval_secs: 300,
+            last_ping: 1000,
+            next_due,
+            check_partition: "CHECK".to_string(),
+            first_alerted_at: None,
+            last_alerted_at: None,
+            alert_count: None,
+            alert_status: None,
+            created_at: 500,
+            paused: paused.then_some(true),
+            expires_at: 0,
+            last_failure: None,
+            last_ping_meta: None,
+            ping_token: None,
+            timezone: None,
+        }
+    }
+
+    #[test]
+    fn renders_monitor_series() {
+        let mut overdue = monitor("team-a", 1000, false);
+        overdue.slug = "backup".to_string();
+        overdue.alert_count = Some(2);
+
+        let out = render_monitors(&[&overdue], 1500);
+        let labels = r#"project="team-a",monitor="backup""#;
+        assert!(out.contains(&format!("heartbeat_last_ping_timestamp{{{labels}}} 1000\n")));
+        assert!(out.contains(&format!("heartbeat_next_due_timestamp{{{labels}}} 1000\n")));
+        assert!(out.contains(&format!("heartbeat_alert_count{{{labels}}} 2\n")));
+        assert!(out.contains(&format!(
+            r#"heartbeat_status{{{labels},status="overdue"}} 1"#
+        )));
+        assert!(out.contains(&format!(r#"heartbeat_status{{{labels},status="ok"}} 0"#)));
+        assert!(out.contains("# TYPE heartbeat_status gauge\n"));
+    }
+
+    #[tokio::test]
+    async fn snapshot_is_read_once_per_max_age() {
+        let store = heartbeat_core::Stores::connect(&heartbeat_core::StorageConfig::Sql {
+            url: "sqlite::memory:".to_string(),
+        })
+        .await
+        .unwrap()
+        .monitors;
+        let snapshot = MonitorSnapshot::new(Duration::from_secs(60));
+        assert!(snapshot.get(&store).await.unwrap().is_empty());
+
+        let mut m = monitor("default", 2000, false);
+        m.expires_at = i64::MAX;
+        store.upsert_monitor(&m).await.unwrap();
+        assert!(snapshot.get(&store).await.unwrap().is_empty());
+
+        // Refreshed with the status gauges
+        snapshot.set(store.list_monitors().await.unwrap()).await;
+        assert_eq!(snapshot.get(&store).await.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn escapes_label_values() {
+        assert_eq!(escape_label(r#"a"b\c"#), r#"a\"b\\c"#);
+        assert_eq!(escape_label("a\nb"), r"a\nb");
+    }
+
+    #[test]
+    fn monitor_labels_are_bounded() {
+        let labels = MonitorLabels::new(2);
+        assert_eq!(labels.label(&id("default", "a")), "a");
+        assert_eq!(labels.label(&id("team-a", "a")), "a");
+        assert_eq!(labels.label(&id("default", "b")), "_other");
+        // Monitors keep the label they were given
+        assert_eq!(labels.label(&id("default", "a")), "a");
+    }
+
+    #[test]
+    fn zero_monitor_labels() {
+        let labels = MonitorLabels::new(0);
+        assert_eq!(labels.label(&id("default", "a")), "_other");
+    }
+
+    #[test]
+    fn counts_monitors_by_project_and_status() {
+        let monitors = [
+            monitor("default", 2000, false),
+            monitor("default", 1000, false),
+            monitor("team-a", 1000, true),
+        ];
+        let counts = count_by_status(&monitors, 1500);
+
+        assert_eq!(counts["default"][MonitorStatus::Ok as usize], 1);
+        assert_eq!(counts["default"][MonitorStatus::Overdue as usize], 1);
+        assert_eq!(counts["team-a"][MonitorStatus::Paused as usize], 1);
+        assert_eq!(counts["team-a"].iter().sum::<u64>(), 1);
+    }
+}
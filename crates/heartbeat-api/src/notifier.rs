@@ -0,0 +1,55 @@
+use heartbeat_checker::telegram::DEFAULT_API_URL;
+use serde::Serialize;
+
+/// Sends the API's own alerts to a Telegram chat.
+///
+/// Monitor alerts are sent by the checker; this is for problems the checker
+/// can't report itself, such as the checker not running.
+#[derive(Clone)]
+pub struct Notifier {
+    http: reqwest::Client,
+    api_url: String,
+    bot_token: String,
+    chat_id: String,
+}
+
+#[derive(Serialize)]
+struct SendMessageRequest<'a> {
+    chat_id: &'a str,
+    text: &'a str,
+}
+
+impl Notifier {
+    /// Create a notifier sending to `chat_id` with the bot `bot_token`.
+    pub fn new(bot_token: String, chat_id: String) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            api_url: DEFAULT_API_URL.to_string(),
+            bot_token,
+            chat_id,
+        }
+    }
+
+    /// Send messages to the Bot API at `api_url` instead of Telegram's.
+    pub fn with_api_url(mut self, api_url: impl Into<String>) -> Self {
+        self.api_url = api_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Send a plain text message.
+    pub async fn send(&self, text: &str) -> Result<(), reqwest::Error> {
+        let url = format!("{}/bot{}/sendMessage", self.api_url, self.bot_token);
+        let body = SendMessageRequest {
+            chat_id: &self.chat_id,
+            text,
+        };
+
+        self.http
+            .post(&url)
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
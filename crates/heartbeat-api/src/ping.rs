@@ -0,0 +1,144 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+
+use axum::extract::{ConnectInfo, FromRequestParts};
+use axum::http::header;
+use axum::http::request::Parts;
+
+use heartbeat_core::PingMetadata;
+
+use crate::failure::truncate_head;
+
+/// Maximum stored length of a ping body, in bytes.
+pub const MAX_PING_BODY_BYTES: usize = 1024;
+
+/// Maximum stored length of a ping user agent, in bytes.
+pub const MAX_USER_AGENT_BYTES: usize = 256;
+
+/// How a ping request was made: method, client IP and user agent.
+///
+/// The client IP is taken from the first `X-Forwarded-For` entry or
+/// `X-Real-IP` when running behind a proxy, falling back to the peer address.
+/// These headers are client-controlled, so the value is informational only.
+pub struct PingSource {
+    pub method: String,
+    pub source_ip: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for PingSource {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let header_str = |name: &str| {
+            parts
+                .headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+        };
+
+        let forwarded_ip = header_str("x-forwarded-for")
+            .and_then(|v| v.split(',').next())
+            .map(str::trim)
+            .or_else(|| header_str("x-real-ip"))
+            .map(String::from);
+
+        let peer_ip = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip().to_string());
+
+        let user_agent = header_str(header::USER_AGENT.as_str())
+            .map(|ua| truncate_head(ua, MAX_USER_AGENT_BYTES));
+
+        Ok(PingSource {
+            method: parts.method.to_string(),
+            source_ip: forwarded_ip.or(peer_ip),
+            user_agent,
+        })
+    }
+}
+
+/// Build the metadata stored with a successful ping.
+///
+/// The body is decoded lossily as UTF-8 and truncated to
+/// [`MAX_PING_BODY_BYTES`], keeping the start. Empty bodies are not stored.
+pub fn ping_metadata(source: PingSource, body: &[u8]) -> PingMetadata {
+    let body = String::from_utf8_lossy(body);
+    let body = body.trim();
+
+    PingMetadata {
+        method: source.method,
+        source_ip: source.source_ip,
+        user_agent: source.user_agent,
+        body: (!body.is_empty()).then(|| truncate_head(body, MAX_PING_BODY_BYTES)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(method: &str) -> PingSource {
+        PingSource {
+            method: method.into(),
+            source_ip: Some("10.0.0.1".into()),
+            user_agent: Some("curl/8.5.0".into()),
+        }
+    }
+
+    #[test]
+    fn metadata_without_body() {
+        let meta = ping_metadata(source("HEAD"), b"");
+        assert_eq!(meta.method, "HEAD");
+        assert_eq!(meta.source_ip.as_deref(), Some("10.0.0.1"));
+        assert_eq!(meta.user_agent.as_deref(), Some("curl/8.5.0"));
+        assert_eq!(meta.body, None);
+    }
+
+    #[test]
+    fn metadata_with_truncated_body() {
+        let body = "x".repeat(MAX_PING_BODY_BYTES + 10);
+        let meta = ping_metadata(source("POST"), body.as_bytes());
+        assert_eq!(meta.method, "POST");
+        assert_eq!(meta.body.unwrap().len(), MAX_PING_BODY_BYTES);
+    }
+
+    #[tokio::test]
+    async fn source_prefers_forwarded_for() {
+        let request = axum::http::Request::builder()
+            .method("POST")
+            .header("x-forwarded-for", "203.0.113.7, 10.0.0.2")
+            .header("user-agent", "systemd")
+            .body(())
+            .unwrap();
+        let (mut parts, ()) = request.into_parts();
+        parts
+            .extensions
+            .insert(ConnectInfo(SocketAddr::from(([10, 0, 0, 2], 1234))));
+
+        let source = PingSource::from_request_parts(&mut parts, &())
+            .await
+            .unwrap();
+        assert_eq!(source.method, "POST");
+        assert_eq!(source.source_ip.as_deref(), Some("203.0.113.7"));
+        assert_eq!(source.user_agent.as_deref(), Some("systemd"));
+    }
+
+    #[tokio::test]
+    async fn source_falls_back_to_peer_address() {
+        let request = axum::http::Request::builder().body(()).unwrap();
+        let (mut parts, ()) = request.into_parts();
+        parts
+            .extensions
+            .insert(ConnectInfo(SocketAddr::from(([10, 0, 0, 2], 1234))));
+
+        let source = PingSource::from_request_parts(&mut parts, &())
+            .await
+            .unwrap();
+        assert_eq!(source.source_ip.as_deref(), Some("10.0.0.2"));
+        assert_eq!(source.user_agent, None);
+    }
+}
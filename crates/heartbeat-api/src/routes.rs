@@ -1,14 +1,21 @@
+use axum::body::Bytes;
 use axum::extract::{Path, Query, State};
-use axum::http::StatusCode;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
 use axum::Json;
-use chrono::Utc;
 use serde::{Deserialize, Serialize};
 
-use heartbeat_core::{Monitor, MonitorStatus, Slug};
+use heartbeat_core::{
+    hash_api_key, AuditAction, AuditEntry, Failure, Monitor, MonitorId, MonitorStatus,
+    PingMetadata, Scope, Slug,
+};
 
-use crate::auth::ApiKey;
+use crate::audit::{self, MonitorChange};
+use crate::auth::{AdminKey, ManageKey, PingKey, PingToken, ReadKey};
 use crate::errors::ApiError;
+use crate::failure::{parse_failure_body, FailureBody};
 use crate::interval::{parse_interval, MAX_INTERVAL, MIN_INTERVAL};
+use crate::ping::{ping_metadata, PingSource};
 use crate::state::AppState;
 
 /// Default heartbeat interval: 5 minutes.
@@ -17,9 +24,11 @@ const DEFAULT_INTERVAL_SECS: u64 = 300;
 /// TTL: 90 days in seconds.
 const TTL_SECS: i64 = 90 * 24 * 60 * 60;
 
-#[derive(Deserialize)]
+#[derive(Default, Deserialize)]
 pub struct HeartbeatQuery {
     pub interval: Option<String>,
+    /// IANA time zone of the monitor's alerts; empty to use the checker's.
+    pub tz: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -48,23 +57,247 @@ pub struct MonitorListResponse {
     pub monitors: Vec<MonitorListItem>,
 }
 
-/// GET /heartbeat/{slug}?interval=5m
+#[derive(Serialize)]
+pub struct MonitorDetailResponse {
+    pub slug: String,
+    pub project: String,
+    pub status: MonitorStatus,
+    pub interval_secs: u64,
+    pub created_at: i64,
+    pub last_ping: i64,
+    pub next_due: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alert_count: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_ping_meta: Option<PingMetadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_failure: Option<Failure>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ping_token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct RotatePingTokenResponse {
+    pub ping_token: String,
+}
+
+#[derive(Deserialize)]
+pub struct AuditQuery {
+    pub since: Option<i64>,
+    /// `event_id` of the last entry of the previous page.
+    pub before: Option<String>,
+    pub limit: Option<i32>,
+}
+
+#[derive(Serialize)]
+pub struct AuditListResponse {
+    pub entries: Vec<AuditEntry>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_before: Option<String>,
+}
+
+/// Default number of audit entries returned by `GET /audit`.
+const DEFAULT_AUDIT_LIMIT: i32 = 100;
+
+/// Maximum number of audit entries returned by `GET /audit`.
+const MAX_AUDIT_LIMIT: i32 = 1000;
+
+/// GET|POST|HEAD /heartbeat/{slug}?interval=5m&tz=Europe/Berlin
 ///
 /// Records a heartbeat ping. Creates the monitor on first ping.
 /// If `?interval` is omitted and the monitor already exists, the existing
 /// interval is preserved. If the monitor does not exist and no interval is
 /// given, defaults to 5 minutes.
+///
+/// `?tz` sets the IANA time zone the monitor's alerts show times in, and an
+/// empty `?tz=` resets it to the checker's. Without it, the existing time
+/// zone is preserved.
+///
+/// The request method, source IP, user agent and an optional small body
+/// (e.g. a run summary) are stored as the monitor's last ping metadata.
 pub async fn heartbeat_handler(
     State(state): State<AppState>,
-    _api_key: ApiKey,
+    api_key: PingKey,
     Path(slug_str): Path<String>,
     Query(query): Query<HeartbeatQuery>,
+    source: PingSource,
+    body: Bytes,
 ) -> Result<Json<HeartbeatResponse>, ApiError> {
     // Validate slug
     let slug = Slug::new(&slug_str).map_err(|e| ApiError::InvalidSlug(e.to_string()))?;
+    let id = api_key.monitor_id(slug)?;
+
+    let source_ip = source.source_ip.clone();
+    let meta = ping_metadata(source, &body);
+    let (response, change) = record_ping(&state, &id, &query, meta).await?;
+    audit::record_change(&state, &api_key, &id, change, source_ip).await;
+    Ok(Json(response))
+}
+
+/// POST /heartbeat/{slug}/fail
+///
+/// Immediately marks a monitor as failed by recording `last_failure`.
+/// Creates the monitor in failed state if it does not exist.
+///
+/// The optional body carries failure details: either JSON with `exit_code`,
+/// `message` and `log` fields, or plain text stored as the log output.
+pub async fn fail_handler(
+    State(state): State<AppState>,
+    api_key: PingKey,
+    Path(slug_str): Path<String>,
+    source: PingSource,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<FailResponse>, ApiError> {
+    // Validate slug
+    let slug = Slug::new(&slug_str).map_err(|e| ApiError::InvalidSlug(e.to_string()))?;
+    let id = api_key.monitor_id(slug)?;
+
+    let details =
+        parse_failure_body(content_type(&headers), &body).map_err(ApiError::InvalidBody)?;
+
+    let (response, change) = record_failure(&state, &id, details).await?;
+    audit::record_change(&state, &api_key, &id, change, source.source_ip).await;
+    Ok(Json(response))
+}
+
+/// GET|POST|HEAD /heartbeat/{slug}/{exit_code}
+///
+/// Reports the exit code of a job run. An exit code of `0` records a regular
+/// heartbeat ping (honouring `?interval` and `?tz`); any other exit code records a
+/// failure like `/fail`, with the path exit code taking precedence over one
+/// given in the body.
+pub async fn exit_code_handler(
+    State(state): State<AppState>,
+    api_key: PingKey,
+    Path((slug_str, exit_code_str)): Path<(String, String)>,
+    Query(query): Query<HeartbeatQuery>,
+    source: PingSource,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, ApiError> {
+    // Validate slug
+    let slug = Slug::new(&slug_str).map_err(|e| ApiError::InvalidSlug(e.to_string()))?;
+    let id = api_key.monitor_id(slug)?;
+
+    let source_ip = source.source_ip.clone();
+    let (response, change) = report_exit_code(
+        &state,
+        &id,
+        &exit_code_str,
+        &query,
+        source,
+        content_type(&headers),
+        &body,
+    )
+    .await?;
+    audit::record_change(&state, &api_key, &id, change, source_ip).await;
+    Ok(response)
+}
+
+/// GET|POST|HEAD /ping/{token}
+///
+/// Records a heartbeat ping for the monitor owning `token`. Like
+/// `/heartbeat/{slug}`, but authenticated by the per-monitor ping token
+/// instead of an API key. The interval and time zone cannot be changed this
+/// way.
+pub async fn token_heartbeat_handler(
+    State(state): State<AppState>,
+    token: PingToken,
+    source: PingSource,
+    body: Bytes,
+) -> Result<Json<HeartbeatResponse>, ApiError> {
+    let meta = ping_metadata(source, &body);
+    let (response, _) = record_ping(&state, &token.id, &HeartbeatQuery::default(), meta).await?;
+    Ok(Json(response))
+}
+
+/// POST /ping/{token}/fail
+///
+/// Records a failure for the monitor owning `token`, like `/heartbeat/{slug}/fail`.
+pub async fn token_fail_handler(
+    State(state): State<AppState>,
+    token: PingToken,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<FailResponse>, ApiError> {
+    let details =
+        parse_failure_body(content_type(&headers), &body).map_err(ApiError::InvalidBody)?;
+
+    let (response, _) = record_failure(&state, &token.id, details).await?;
+    Ok(Json(response))
+}
+
+/// GET|POST|HEAD /ping/{token}/{exit_code}
+///
+/// Reports the exit code of a job run for the monitor owning `token`, like
+/// `/heartbeat/{slug}/{exit_code}`.
+pub async fn token_exit_code_handler(
+    State(state): State<AppState>,
+    token: PingToken,
+    Path((_token, exit_code_str)): Path<(String, String)>,
+    source: PingSource,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, ApiError> {
+    let (response, _) = report_exit_code(
+        &state,
+        &token.id,
+        &exit_code_str,
+        &HeartbeatQuery::default(),
+        source,
+        content_type(&headers),
+        &body,
+    )
+    .await?;
+    Ok(response)
+}
+
+/// Record a ping for exit code `0`, or a failure for any other exit code.
+///
+/// Also returns how the monitor changed, for the audit log.
+async fn report_exit_code(
+    state: &AppState,
+    id: &MonitorId,
+    exit_code_str: &str,
+    query: &HeartbeatQuery,
+    source: PingSource,
+    content_type: Option<&str>,
+    body: &[u8],
+) -> Result<(Response, MonitorChange), ApiError> {
+    let exit_code: i32 = exit_code_str.parse().map_err(|_| {
+        ApiError::InvalidExitCode(format!("Cannot parse exit code: {exit_code_str}"))
+    })?;
+
+    if exit_code == 0 {
+        let meta = ping_metadata(source, body);
+        let (response, change) = record_ping(state, id, query, meta).await?;
+        return Ok((Json(response).into_response(), change));
+    }
+
+    let mut details = parse_failure_body(content_type, body).map_err(ApiError::InvalidBody)?;
+    details.exit_code = Some(exit_code);
+
+    let (response, change) = record_failure(state, id, details).await?;
+    Ok((Json(response).into_response(), change))
+}
+
+/// Record a successful ping for a monitor, creating it if needed, with the
+/// interval and time zone in `query`.
+///
+/// Also returns how the monitor changed, for the audit log.
+async fn record_ping(
+    state: &AppState,
+    id: &MonitorId,
+    query: &HeartbeatQuery,
+    meta: PingMetadata,
+) -> Result<(HeartbeatResponse, MonitorChange), ApiError> {
+    let existing = state.monitors_store.get_monitor(id).await?;
 
     // Determine interval
-    let interval_secs = match &query.interval {
+    let interval_secs = match query.interval.as_deref() {
         Some(interval_str) => {
             let duration = parse_interval(interval_str).ok_or_else(|| {
                 ApiError::InvalidInterval(format!("Cannot parse interval: {interval_str}"))
@@ -86,20 +319,41 @@ pub async fn heartbeat_handler(
 
             duration.as_secs()
         }
-        None => {
-            // No interval specified: check if monitor already exists
-            match state.monitors_store.get_monitor(&slug).await? {
-                Some(existing) => existing.interval_secs,
-                None => DEFAULT_INTERVAL_SECS,
+        // No interval specified: keep the existing one, if any
+        None => existing
+            .as_ref()
+            .map_or(DEFAULT_INTERVAL_SECS, |existing| existing.interval_secs),
+    };
+
+    // Determine time zone: keep the existing one unless given
+    let timezone = match query.tz.as_deref() {
+        Some("") => None,
+        Some(tz) => {
+            let tz: chrono_tz::Tz = tz
+                .parse()
+                .map_err(|_| ApiError::InvalidTimezone(format!("Unknown time zone: {tz}")))?;
+            Some(tz.name().to_string())
+        }
+        None => existing.as_ref().and_then(|m| m.timezone.clone()),
+    };
+
+    let change = match &existing {
+        None => MonitorChange::Created { interval_secs },
+        Some(existing) if existing.interval_secs != interval_secs => {
+            MonitorChange::IntervalChanged {
+                from: existing.interval_secs,
+                to: interval_secs,
             }
         }
+        Some(_) => MonitorChange::Unchanged,
     };
 
-    let now = Utc::now().timestamp();
+    let now = state.clock.now();
     let next_due = now + interval_secs as i64;
 
     let monitor = Monitor {
-        slug: slug.to_string(),
+        slug: id.slug.to_string(),
+        project: id.project.clone(),
         interval_secs,
         last_ping: now,
         next_due,
@@ -107,81 +361,113 @@ pub async fn heartbeat_handler(
         first_alerted_at: None,
         last_alerted_at: None,
         alert_count: None,
+        alert_status: None,
         created_at: now,
         paused: None,
         expires_at: now + TTL_SECS,
+        last_failure: None,
+        last_ping_meta: Some(meta),
+        ping_token: None,
+        timezone,
     };
 
-    state.monitors_store.upsert_monitor(&monitor).await?;
+    let stored = state.monitors_store.upsert_monitor(&monitor).await?;
+    state.monitor_labels.record_ping(id, "ok");
+    if let Some(due_updates) = &state.due_updates {
+        due_updates.monitor_changed(&stored);
+    }
 
-    let status = MonitorStatus::derive(&monitor, now);
+    let status = MonitorStatus::derive(&stored, now);
     let next_due_str = chrono::DateTime::from_timestamp(next_due, 0)
         .map(|dt| dt.to_rfc3339())
         .unwrap_or_else(|| next_due.to_string());
 
-    Ok(Json(HeartbeatResponse {
+    let response = HeartbeatResponse {
         ok: true,
         next_due: next_due_str,
         status,
-    }))
+    };
+    Ok((response, change))
 }
 
-/// POST /heartbeat/{slug}/fail
+/// Record an explicit failure for a monitor, creating it if needed.
 ///
-/// Immediately marks a monitor as overdue by setting `next_due = 0`.
-/// Creates the monitor in overdue state if it does not exist.
-pub async fn fail_handler(
-    State(state): State<AppState>,
-    _api_key: ApiKey,
-    Path(slug_str): Path<String>,
-) -> Result<Json<FailResponse>, ApiError> {
-    // Validate slug
-    let slug = Slug::new(&slug_str).map_err(|e| ApiError::InvalidSlug(e.to_string()))?;
-
-    let now = Utc::now().timestamp();
+/// Also returns how the monitor changed, for the audit log.
+async fn record_failure(
+    state: &AppState,
+    id: &MonitorId,
+    details: FailureBody,
+) -> Result<(FailResponse, MonitorChange), ApiError> {
+    let now = state.clock.now();
 
     // Determine interval: use existing if present, else default
-    let interval_secs = match state.monitors_store.get_monitor(&slug).await? {
-        Some(existing) => existing.interval_secs,
-        None => DEFAULT_INTERVAL_SECS,
+    let (interval_secs, change) = match state.monitors_store.get_monitor(id).await? {
+        Some(existing) => (existing.interval_secs, MonitorChange::Unchanged),
+        None => (
+            DEFAULT_INTERVAL_SECS,
+            MonitorChange::Created {
+                interval_secs: DEFAULT_INTERVAL_SECS,
+            },
+        ),
     };
 
     let monitor = Monitor {
-        slug: slug.to_string(),
+        slug: id.slug.to_string(),
+        project: id.project.clone(),
         interval_secs,
-        last_ping: now,
-        next_due: 0, // Immediately overdue
+        last_ping: now, // Only used if the monitor is new
+        next_due: now,  // Picked up by the checker's overdue query next cycle
         check_partition: "CHECK".to_string(),
         first_alerted_at: None,
         last_alerted_at: None,
         alert_count: None,
+        alert_status: None,
         created_at: now,
         paused: None,
         expires_at: now + TTL_SECS,
+        last_failure: Some(Failure {
+            at: now,
+            exit_code: details.exit_code,
+            message: details.message,
+            log: details.log,
+        }),
+        last_ping_meta: None,
+        ping_token: None,
+        timezone: None,
     };
 
-    state.monitors_store.upsert_monitor(&monitor).await?;
+    let stored = state.monitors_store.record_failure(&monitor).await?;
+    state.monitor_labels.record_ping(id, "fail");
+    if let Some(due_updates) = &state.due_updates {
+        due_updates.monitor_changed(&stored);
+    }
 
-    let status = MonitorStatus::derive(&monitor, now);
+    let status = MonitorStatus::derive(&stored, now);
 
-    Ok(Json(FailResponse {
-        ok: true,
-        status,
-    }))
+    Ok((FailResponse { ok: true, status }, change))
+}
+
+/// The request `Content-Type` header, if present and valid.
+fn content_type(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
 }
 
 /// GET /monitors
 ///
-/// Returns all monitors sorted alphabetically by slug.
+/// Returns all monitors visible to the API key (its project's monitors,
+/// filtered by its slug prefix), sorted alphabetically by slug.
 pub async fn list_monitors_handler(
     State(state): State<AppState>,
-    _api_key: ApiKey,
+    api_key: ReadKey,
 ) -> Result<Json<MonitorListResponse>, ApiError> {
     let monitors = state.monitors_store.list_monitors().await?;
-    let now = Utc::now().timestamp();
+    let now = state.clock.now();
 
     let mut items: Vec<MonitorListItem> = monitors
         .iter()
+        .filter(|m| m.project == api_key.project() && api_key.record.allows_slug(&m.slug))
         .map(|m| MonitorListItem {
             slug: m.slug.clone(),
             status: MonitorStatus::derive(m, now),
@@ -195,41 +481,254 @@ pub async fn list_monitors_handler(
     Ok(Json(MonitorListResponse { monitors: items }))
 }
 
+/// GET /monitors/{slug}
+///
+/// Returns a single monitor with its last ping metadata and last failure.
+/// The ping token is a credential of its own, so it is only included for
+/// keys with the `manage` scope.
+/// Returns 404 if not found.
+pub async fn get_monitor_handler(
+    State(state): State<AppState>,
+    api_key: ReadKey,
+    Path(slug_str): Path<String>,
+) -> Result<Json<MonitorDetailResponse>, ApiError> {
+    let slug = Slug::new(&slug_str).map_err(|e| ApiError::InvalidSlug(e.to_string()))?;
+    let id = api_key.monitor_id(slug)?;
+    let monitor = state
+        .monitors_store
+        .get_monitor(&id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(id.slug.to_string()))?;
+    let now = state.clock.now();
+
+    Ok(Json(MonitorDetailResponse {
+        status: MonitorStatus::derive(&monitor, now),
+        slug: monitor.slug,
+        project: monitor.project,
+        interval_secs: monitor.interval_secs,
+        created_at: monitor.created_at,
+        last_ping: monitor.last_ping,
+        next_due: monitor.next_due,
+        alert_count: monitor.alert_count,
+        last_ping_meta: monitor.last_ping_meta,
+        last_failure: monitor.last_failure,
+        ping_token: monitor.ping_token.filter(|_| api_key.record.grants(Scope::Manage)),
+        timezone: monitor.timezone,
+    }))
+}
+
 /// DELETE /monitors/{slug}
 ///
 /// Removes a monitor from DynamoDB. Returns 204 on success, 404 if not found.
+/// Recorded in the audit log.
 pub async fn delete_monitor_handler(
     State(state): State<AppState>,
-    _api_key: ApiKey,
+    api_key: ManageKey,
     Path(slug_str): Path<String>,
+    source: PingSource,
 ) -> Result<StatusCode, ApiError> {
     let slug = Slug::new(&slug_str).map_err(|e| ApiError::InvalidSlug(e.to_string()))?;
-    state.monitors_store.delete_monitor(&slug).await?;
+    let id = api_key.monitor_id(slug)?;
+    state.monitors_store.delete_monitor(&id).await?;
+    let entry = audit::monitor_entry(
+        &state,
+        &api_key,
+        AuditAction::Delete,
+        &id,
+        source.source_ip,
+    );
+    audit::record(&state, entry).await;
     Ok(StatusCode::NO_CONTENT)
 }
 
 /// POST /monitors/{slug}/pause
 ///
 /// Pauses a monitor, clearing alert state. Returns 204 on success, 404 if not found.
+/// Recorded in the audit log.
 pub async fn pause_handler(
     State(state): State<AppState>,
-    _api_key: ApiKey,
+    api_key: ManageKey,
     Path(slug_str): Path<String>,
+    source: PingSource,
 ) -> Result<StatusCode, ApiError> {
     let slug = Slug::new(&slug_str).map_err(|e| ApiError::InvalidSlug(e.to_string()))?;
-    state.monitors_store.set_paused(&slug, true).await?;
+    let id = api_key.monitor_id(slug)?;
+    state.monitors_store.set_paused(&id, true).await?;
+    let entry = audit::monitor_entry(
+        &state,
+        &api_key,
+        AuditAction::Pause,
+        &id,
+        source.source_ip,
+    );
+    audit::record(&state, entry).await;
     Ok(StatusCode::NO_CONTENT)
 }
 
 /// POST /monitors/{slug}/unpause
 ///
 /// Unpauses a monitor. Returns 204 on success, 404 if not found.
+/// Recorded in the audit log.
 pub async fn unpause_handler(
     State(state): State<AppState>,
-    _api_key: ApiKey,
+    api_key: ManageKey,
     Path(slug_str): Path<String>,
+    source: PingSource,
 ) -> Result<StatusCode, ApiError> {
     let slug = Slug::new(&slug_str).map_err(|e| ApiError::InvalidSlug(e.to_string()))?;
-    state.monitors_store.set_paused(&slug, false).await?;
+    let id = api_key.monitor_id(slug)?;
+    state.monitors_store.set_paused(&id, false).await?;
+    let entry = audit::monitor_entry(
+        &state,
+        &api_key,
+        AuditAction::Unpause,
+        &id,
+        source.source_ip,
+    );
+    audit::record(&state, entry).await;
     Ok(StatusCode::NO_CONTENT)
 }
+
+/// POST /monitors/{slug}/rotate-ping-token
+///
+/// Replaces the monitor's ping token, invalidating its old `/ping/{token}`
+/// URL. Returns the new token, or 404 if not found.
+/// Recorded in the audit log.
+pub async fn rotate_ping_token_handler(
+    State(state): State<AppState>,
+    api_key: ManageKey,
+    Path(slug_str): Path<String>,
+    source: PingSource,
+) -> Result<Json<RotatePingTokenResponse>, ApiError> {
+    let slug = Slug::new(&slug_str).map_err(|e| ApiError::InvalidSlug(e.to_string()))?;
+    let id = api_key.monitor_id(slug)?;
+    let ping_token = state.monitors_store.rotate_ping_token(&id).await?;
+    let entry = audit::monitor_entry(
+        &state,
+        &api_key,
+        AuditAction::RotatePingToken,
+        &id,
+        source.source_ip,
+    );
+    audit::record(&state, entry).await;
+    Ok(Json(RotatePingTokenResponse { ping_token }))
+}
+
+/// POST /keys/{prefix}/revoke
+///
+/// Revokes the key of the caller's project with this prefix, and drops it
+/// from this instance's key cache so it is rejected right away. Returns 204 on
+/// success (also if already revoked), 404 if no key matches.
+/// A key restricted to a slug prefix can only revoke keys restricted to the
+/// same or a longer prefix.
+/// Recorded in the audit log.
+pub async fn revoke_key_handler(
+    State(state): State<AppState>,
+    api_key: AdminKey,
+    Path(prefix): Path<String>,
+    source: PingSource,
+) -> Result<StatusCode, ApiError> {
+    let mut matches = state
+        .keys_store
+        .list_keys()
+        .await?
+        .into_iter()
+        .filter(|k| k.project == api_key.project() && k.prefix() == prefix);
+    let record = match (matches.next(), matches.next()) {
+        (Some(record), None) => record,
+        (None, _) => return Err(ApiError::NotFound(format!("No API key matches {prefix}"))),
+        (Some(_), Some(_)) => {
+            return Err(ApiError::InvalidBody(format!(
+                "More than one API key matches {prefix}"
+            )))
+        }
+    };
+
+    let within_prefix = match (&api_key.record.slug_prefix, &record.slug_prefix) {
+        (None, _) => true,
+        (Some(own), Some(other)) => other.starts_with(own.as_str()),
+        (Some(_), None) => false,
+    };
+    if !within_prefix {
+        return Err(ApiError::Forbidden(format!(
+            "API key is not allowed to revoke key {prefix}"
+        )));
+    }
+
+    let now = state.clock.now();
+    if record.revoked_at.is_none() {
+        state.keys_store.revoke_key(&record.key_hash, now).await?;
+    }
+
+    // Cached by the hash of the token, which plaintext keys store as is
+    let token_hash = if record.is_plaintext() {
+        hash_api_key(&record.key_hash)
+    } else {
+        record.key_hash.clone()
+    };
+    state.key_cache.invalidate(&token_hash);
+
+    let mut entry = AuditEntry::new(
+        &record.project,
+        now,
+        AuditAction::RevokeKey,
+        &api_key.record.description,
+    );
+    entry.key_prefix = Some(api_key.record.prefix());
+    entry.target_key = Some(record.prefix());
+    entry.source_ip = source.source_ip;
+    audit::record(&state, entry).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /audit?since=<epoch>&before=<event_id>&limit=<n>
+///
+/// Returns audit log entries of the key's project made at or after `since`
+/// (default: all), newest first, up to `limit` (default 100, max 1000).
+/// Keys restricted to a slug prefix only see entries for matching monitors.
+/// A full page includes `next_before`, the `before` of the next page.
+pub async fn audit_handler(
+    State(state): State<AppState>,
+    api_key: AdminKey,
+    Query(query): Query<AuditQuery>,
+) -> Result<Json<AuditListResponse>, ApiError> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_AUDIT_LIMIT)
+        .clamp(1, MAX_AUDIT_LIMIT);
+    let visible = |entry: &AuditEntry| match (&entry.slug, &api_key.record.slug_prefix) {
+        (_, None) => true,
+        (Some(slug), Some(_)) => api_key.record.allows_slug(slug),
+        (None, Some(_)) => false,
+    };
+
+    // Filter before limiting, reading more pages until the page is full
+    let mut entries = Vec::new();
+    let mut before = query.before;
+    loop {
+        let page = state
+            .audit_store
+            .list(
+                api_key.project(),
+                query.since.unwrap_or(0),
+                before.as_deref(),
+                limit,
+            )
+            .await?;
+        let more = page.len() == limit as usize;
+        before = page.last().map(|entry| entry.event_id.clone());
+        entries.extend(page.into_iter().filter(|entry| visible(entry)));
+        if !more || entries.len() >= limit as usize {
+            break;
+        }
+    }
+    entries.truncate(limit as usize);
+
+    let next_before = (entries.len() == limit as usize)
+        .then(|| entries.last().map(|entry| entry.event_id.clone()))
+        .flatten();
+    Ok(Json(AuditListResponse {
+        entries,
+        next_before,
+    }))
+}
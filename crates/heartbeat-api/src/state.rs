@@ -1,13 +1,42 @@
-use aws_sdk_dynamodb::Client;
-use heartbeat_core::DynamoStore;
+use std::time::Duration;
+
+use heartbeat_checker::scheduler::DueUpdates;
+use heartbeat_core::{ApiKeyStore, AuditStore, Clock, MonitorStore, StatusStore};
+use metrics_exporter_prometheus::PrometheusHandle;
+
+use crate::health::Readiness;
+use crate::key_cache::KeyCache;
+use crate::metrics::{MonitorLabels, MonitorSnapshot};
 
 /// Shared application state passed to all Axum handlers.
 #[derive(Clone)]
 pub struct AppState {
-    /// DynamoDB store for monitor operations.
-    pub monitors_store: DynamoStore,
-    /// DynamoDB client for API key lookups.
-    pub dynamo_client: Client,
-    /// DynamoDB table name for API keys.
-    pub keys_table: String,
+    /// Store for monitor operations.
+    pub monitors_store: MonitorStore,
+    /// Store for API key lookups.
+    pub keys_store: ApiKeyStore,
+    /// Cache of API key lookups in front of `keys_store`.
+    pub key_cache: KeyCache,
+    /// Whether unknown tokens are also looked up as plaintext keys, stored
+    /// before keys were hashed.
+    pub plaintext_key_fallback: bool,
+    /// Store for the audit log of management actions.
+    pub audit_store: AuditStore,
+    /// Renders the metrics served on `/metrics`.
+    pub metrics: PrometheusHandle,
+    /// `monitor` labels of the ping metrics.
+    pub monitor_labels: MonitorLabels,
+    /// Monitors served on `/metrics/monitors`.
+    pub monitor_snapshot: MonitorSnapshot,
+    /// Readiness reported on `/readyz`.
+    pub readiness: Readiness,
+    /// Store for the checker heartbeat.
+    pub status_store: StatusStore,
+    /// Age after which the checker heartbeat counts as stale.
+    pub checker_stale_after: Duration,
+    /// Due-time updates for the embedded checker, when it schedules cycles
+    /// on due times.
+    pub due_updates: Option<DueUpdates>,
+    /// Source of the current time for pings, key expiry and statuses.
+    pub clock: Clock,
 }
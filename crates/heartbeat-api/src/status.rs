@@ -0,0 +1,415 @@
+use std::time::Duration;
+
+use axum::Json;
+use axum::extract::State;
+use serde::Serialize;
+
+use heartbeat_core::{CheckerHeartbeat, Clock, StatusStore};
+
+use crate::auth::ReadKey;
+use crate::errors::ApiError;
+use crate::notifier::Notifier;
+use crate::state::AppState;
+
+/// Default age after which the checker counts as not running: 10 minutes,
+/// five cycles of the 2-minute schedule.
+pub const DEFAULT_CHECKER_STALE_AFTER: Duration = Duration::from_secs(600);
+
+/// How often the watchdog reads the checker heartbeat.
+const WATCHDOG_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Repeat alert interval while the checker isn't running: 1 hour in seconds.
+const REPEAT_ALERT_INTERVAL_SECS: i64 = 3600;
+
+/// How long a sent checker alert is remembered against resending: 1 day.
+const ALERT_CLAIM_TTL_SECS: i64 = 86_400;
+
+/// Gauge of the Unix time of the checker's last completed cycle.
+const CHECKER_LAST_RUN: &str = "heartbeat_checker_last_run_timestamp";
+
+#[derive(Serialize)]
+pub struct StatusResponse {
+    /// The checker's last completed cycle, or `None` if it never completed one.
+    pub checker: Option<CheckerStatus>,
+}
+
+#[derive(Serialize)]
+pub struct CheckerStatus {
+    pub last_run: String,
+    pub age_secs: i64,
+    /// Whether the last cycle is older than the stale threshold.
+    pub stale: bool,
+    pub overdue: u64,
+    pub alerted: u64,
+    pub alerts_sent: u64,
+    pub errors: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+/// GET /status
+///
+/// Returns the result of the checker's last completed cycle, so a checker
+/// that stopped running can be noticed.
+pub async fn status_handler(
+    State(state): State<AppState>,
+    _api_key: ReadKey,
+) -> Result<Json<StatusResponse>, ApiError> {
+    let heartbeat = state.status_store.get_checker_heartbeat().await?;
+    let now = state.clock.now();
+    let stale_after = state.checker_stale_after.as_secs() as i64;
+
+    let checker = heartbeat.map(|h| CheckerStatus {
+        last_run: format_time(h.at),
+        age_secs: now - h.at,
+        stale: h.is_stale(now, stale_after),
+        overdue: h.overdue,
+        alerted: h.alerted,
+        alerts_sent: h.alerts_sent,
+        errors: h.errors,
+        last_error: h.last_error,
+    });
+
+    Ok(Json(StatusResponse { checker }))
+}
+
+/// Watch the checker heartbeat in the background and alert through
+/// `notifier` when the checker hasn't completed a cycle for `stale_after` on
+/// `clock`.
+///
+/// Alerts repeat hourly until the checker runs again, which is notified too.
+/// Without a notifier, the alerts are only logged. Every API replica watches
+/// the checker, but each alert is claimed in `store` before it is sent, so
+/// only one replica sends it, and a restarted replica doesn't send it again.
+pub fn spawn_checker_watchdog(
+    store: StatusStore,
+    notifier: Option<Notifier>,
+    stale_after: Duration,
+    clock: Clock,
+) {
+    tokio::spawn(async move {
+        let mut watchdog = Watchdog::new(clock.now(), stale_after.as_secs() as i64);
+        let mut ticker = tokio::time::interval(WATCHDOG_INTERVAL);
+
+        loop {
+            ticker.tick().await;
+
+            let heartbeat = match store.get_checker_heartbeat().await {
+                Ok(heartbeat) => heartbeat,
+                Err(e) => {
+                    tracing::warn!("Failed to read checker heartbeat: {e}");
+                    continue;
+                }
+            };
+            if let Some(h) = &heartbeat {
+                metrics::gauge!(CHECKER_LAST_RUN).set(h.at as f64);
+            }
+
+            let now = clock.now();
+            let Some(alert) = watchdog.check(heartbeat.as_ref(), now) else {
+                continue;
+            };
+
+            let key = alert.idempotency_key();
+            match store
+                .claim_alert(&key, now, now + ALERT_CLAIM_TTL_SECS)
+                .await
+            {
+                Ok(true) => {}
+                Ok(false) => {
+                    tracing::debug!(key, "Checker alert already sent by another replica");
+                    watchdog.sent(alert);
+                    continue;
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to claim checker alert: {e}");
+                    continue;
+                }
+            }
+
+            let text = alert.message(now);
+            tracing::error!("{text}");
+            match &notifier {
+                Some(notifier) => match notifier.send(&text).await {
+                    Ok(()) => watchdog.sent(alert),
+                    Err(e) => {
+                        tracing::warn!("Failed to send checker alert: {e}");
+                        if let Err(e) = store.release_alert(&key).await {
+                            tracing::warn!("Failed to release checker alert claim: {e}");
+                        }
+                    }
+                },
+                None => watchdog.sent(alert),
+            }
+        }
+    });
+}
+
+/// An alert about the checker, whose last completed cycle was at `last_run`
+/// (if it ever completed one).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CheckerAlert {
+    /// Not running, first alerted in `period` (see [`Watchdog::period`]).
+    Stale { last_run: Option<i64>, period: i64 },
+    /// Still not running, in a later `period`.
+    Repeat { last_run: Option<i64>, period: i64 },
+    /// Running again.
+    Recovered { last_run: Option<i64> },
+}
+
+impl CheckerAlert {
+    fn message(self, now: i64) -> String {
+        match self {
+            CheckerAlert::Stale {
+                last_run: Some(last_run),
+                ..
+            } => format!(
+                "heartbeat-checker has not completed a check cycle since {} ({} ago). \
+                 Monitors are not being checked.",
+                format_time(last_run),
+                format_ago(now - last_run)
+            ),
+            CheckerAlert::Stale { last_run: None, .. } => {
+                "heartbeat-checker has never completed a check cycle. \
+                 Monitors are not being checked."
+                    .to_string()
+            }
+            CheckerAlert::Repeat {
+                last_run: Some(last_run),
+                ..
+            } => format!(
+                "heartbeat-checker is still not running (last cycle {} ago).",
+                format_ago(now - last_run)
+            ),
+            CheckerAlert::Repeat { last_run: None, .. } => {
+                "heartbeat-checker has still never completed a check cycle.".to_string()
+            }
+            CheckerAlert::Recovered {
+                last_run: Some(last_run),
+            } => format!(
+                "heartbeat-checker is running again, {} after its previous cycle.",
+                format_ago(now - last_run)
+            ),
+            CheckerAlert::Recovered { last_run: None } => {
+                "heartbeat-checker has completed its first check cycle.".to_string()
+            }
+        }
+    }
+
+    /// Key under which the alert is claimed, the same in every replica: the
+    /// last cycle identifies the incident.
+    fn idempotency_key(self) -> String {
+        let incident = |last_run: Option<i64>| match last_run {
+            Some(at) => format!("checker#{at}"),
+            None => "checker#never".to_string(),
+        };
+        match self {
+            CheckerAlert::Stale { last_run, period }
+            | CheckerAlert::Repeat { last_run, period } => {
+                format!("{}#{period}", incident(last_run))
+            }
+            CheckerAlert::Recovered { last_run } => format!("{}#recovered", incident(last_run)),
+        }
+    }
+}
+
+/// Alert state of the checker watchdog in one replica.
+struct Watchdog {
+    started_at: i64,
+    stale_after_secs: i64,
+    /// The incident alerted, by any replica: its last cycle and the last
+    /// period alerted.
+    alerted: Option<(Option<i64>, i64)>,
+}
+
+impl Watchdog {
+    fn new(started_at: i64, stale_after_secs: i64) -> Self {
+        Self {
+            started_at,
+            stale_after_secs,
+            alerted: None,
+        }
+    }
+
+    /// The alert to send for `heartbeat` at `now`, if any.
+    ///
+    /// Without any heartbeat, the checker gets `stale_after` from the API's
+    /// start before it counts as not running.
+    fn check(&self, heartbeat: Option<&CheckerHeartbeat>, now: i64) -> Option<CheckerAlert> {
+        let last_run = heartbeat.map(|h| h.at);
+        let stale = match heartbeat {
+            Some(h) => h.is_stale(now, self.stale_after_secs),
+            None => now - self.started_at > self.stale_after_secs,
+        };
+
+        match (stale, self.alerted) {
+            (true, Some((alerted_run, alerted_period))) if alerted_run == last_run => {
+                let period = self.period(last_run, now);
+                (period > alerted_period).then_some(CheckerAlert::Repeat { last_run, period })
+            }
+            (true, _) => {
+                let period = self.period(last_run, now);
+                Some(if last_run.is_some() && period > 0 {
+                    // Alerted before this replica started
+                    CheckerAlert::Repeat { last_run, period }
+                } else {
+                    CheckerAlert::Stale { last_run, period }
+                })
+            }
+            (false, Some((alerted_run, _))) => Some(CheckerAlert::Recovered {
+                last_run: alerted_run,
+            }),
+            (false, None) => None,
+        }
+    }
+
+    /// Hours since the checker became stale, counted from its last cycle so
+    /// that all replicas agree. A checker that never ran is counted in hours
+    /// of the clock instead.
+    fn period(&self, last_run: Option<i64>, now: i64) -> i64 {
+        match last_run {
+            Some(at) => (now - at - self.stale_after_secs).max(0) / REPEAT_ALERT_INTERVAL_SECS,
+            None => now / REPEAT_ALERT_INTERVAL_SECS,
+        }
+    }
+
+    /// Record that `alert` was sent, here or by another replica.
+    fn sent(&mut self, alert: CheckerAlert) {
+        self.alerted = match alert {
+            CheckerAlert::Stale { last_run, period }
+            | CheckerAlert::Repeat { last_run, period } => Some((last_run, period)),
+            CheckerAlert::Recovered { .. } => None,
+        };
+    }
+}
+
+fn format_time(epoch: i64) -> String {
+    chrono::DateTime::from_timestamp(epoch, 0)
+        .map(|dt| dt.to_rfc3339())
+        .unwrap_or_else(|| epoch.to_string())
+}
+
+/// A duration rounded to minutes, e.g. `1h 5m`.
+fn format_ago(secs: i64) -> String {
+    let minutes = (secs.max(0) as u64 / 60).max(1);
+    humantime::format_duration(Duration::from_secs(minutes * 60)).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn heartbeat(at: i64) -> CheckerHeartbeat {
+        CheckerHeartbeat {
+            at,
+            overdue: 0,
+            alerted: 0,
+            alerts_sent: 0,
+            errors: 0,
+            last_error: None,
+        }
+    }
+
+    #[test]
+    fn no_alert_while_checker_runs() {
+        let watchdog = Watchdog::new(0, 600);
+        assert_eq!(watchdog.check(Some(&heartbeat(1000)), 1500), None);
+    }
+
+    #[test]
+    fn alerts_once_then_hourly_then_recovers() {
+        let mut watchdog = Watchdog::new(0, 600);
+        let last = heartbeat(1000);
+
+        let alert = watchdog.check(Some(&last), 1700).unwrap();
+        assert_eq!(
+            alert,
+            CheckerAlert::Stale {
+                last_run: Some(1000),
+                period: 0
+            }
+        );
+        assert_eq!(alert.idempotency_key(), "checker#1000#0");
+        watchdog.sent(alert);
+
+        assert_eq!(watchdog.check(Some(&last), 1760), None);
+        let alert = watchdog.check(Some(&last), 1600 + 3600).unwrap();
+        assert_eq!(
+            alert,
+            CheckerAlert::Repeat {
+                last_run: Some(1000),
+                period: 1
+            }
+        );
+        assert_eq!(alert.idempotency_key(), "checker#1000#1");
+        watchdog.sent(alert);
+        assert_eq!(watchdog.check(Some(&last), 1700 + 3600), None);
+
+        let alert = watchdog.check(Some(&heartbeat(6000)), 6100).unwrap();
+        assert_eq!(
+            alert,
+            CheckerAlert::Recovered {
+                last_run: Some(1000)
+            }
+        );
+        assert_eq!(alert.idempotency_key(), "checker#1000#recovered");
+        watchdog.sent(alert);
+        assert_eq!(watchdog.check(Some(&heartbeat(6000)), 6200), None);
+    }
+
+    #[test]
+    fn replicas_agree_on_alert_keys() {
+        // Started at different times, or restarted during the incident
+        let early = Watchdog::new(0, 600);
+        let restarted = Watchdog::new(5000, 600);
+        let last = heartbeat(1000);
+
+        let now = 1600 + 2 * 3600 + 30;
+        let alert = early.check(Some(&last), now).unwrap();
+        assert_eq!(alert, restarted.check(Some(&last), now).unwrap());
+        assert_eq!(alert.idempotency_key(), "checker#1000#2");
+    }
+
+    #[test]
+    fn unsent_alert_is_retried() {
+        let watchdog = Watchdog::new(0, 600);
+        assert!(watchdog.check(Some(&heartbeat(1000)), 1700).is_some());
+        assert!(watchdog.check(Some(&heartbeat(1000)), 1760).is_some());
+    }
+
+    #[test]
+    fn missing_heartbeat_is_stale_after_grace() {
+        let mut watchdog = Watchdog::new(1000, 600);
+        assert_eq!(watchdog.check(None, 1500), None);
+        let alert = watchdog.check(None, 1700).unwrap();
+        assert_eq!(
+            alert,
+            CheckerAlert::Stale {
+                last_run: None,
+                period: 0
+            }
+        );
+        watchdog.sent(alert);
+
+        // Repeated in the next hour of the clock
+        assert_eq!(watchdog.check(None, 3599), None);
+        assert_eq!(
+            watchdog.check(None, 3600),
+            Some(CheckerAlert::Repeat {
+                last_run: None,
+                period: 1
+            })
+        );
+    }
+
+    #[test]
+    fn stale_message_names_last_run() {
+        let alert = CheckerAlert::Stale {
+            last_run: Some(1000),
+            period: 0,
+        };
+        let message = alert.message(1000 + 3900);
+        assert!(message.contains("1970-01-01T00:16:40+00:00"));
+        assert!(message.contains("1h 5m ago"));
+    }
+}
@@ -1,135 +1,471 @@
-use chrono::{DateTime, Utc};
+use chrono::DateTime;
+use chrono_tz::Tz;
+use heartbeat_core::{Failure, Monitor};
+use serde::Deserialize;
 
-/// Characters that must be escaped in MarkdownV2 (outside of code spans).
-const MARKDOWN_V2_SPECIAL: &[char] = &[
-    '_', '*', '[', ']', '(', ')', '~', '`', '>', '#', '+', '-', '=', '|', '{', '}', '.', '!',
-];
+use crate::template::{Template, Vars};
 
-/// Format a human-readable duration from seconds using humantime.
-fn format_duration(secs: u64) -> String {
-    if secs == 0 {
-        return "0s".to_string();
-    }
-    humantime::format_duration(std::time::Duration::from_secs(secs)).to_string()
-}
-
-/// Format a UTC timestamp from epoch seconds.
-fn format_time(epoch: i64) -> String {
-    DateTime::<Utc>::from_timestamp(epoch, 0)
-        .map(|dt| dt.format("%H:%M UTC").to_string())
-        .unwrap_or_else(|| "unknown".to_string())
-}
+/// Default overdue alert (first alert).
+///
+/// Example: OVERDUE: `my-job` | interval: 5m | last: 12:03 UTC (12m ago) | 7m late
+const OVERDUE: &str = "\u{26a0}\u{fe0f} OVERDUE: `{{slug}}` | interval: {{interval}} | last: {{last_ping}} | {{late}} late";
 
-/// Format an overdue alert message (first alert).
+/// Default failure alert (first alert after an explicit failure), followed by
+/// the reported message and log tail, if any.
 ///
-/// Example: OVERDUE: `my-job` | interval: 5m | last: 12:03 UTC | 7m late
-pub fn format_overdue(slug: &str, interval_secs: u64, last_ping: i64, now: i64) -> String {
-    let interval = format_duration(interval_secs);
-    let last = format_time(last_ping);
-    let late_secs = (now - last_ping).saturating_sub(interval_secs as i64);
-    let late = format_duration(late_secs.max(0) as u64);
-
-    let raw = format!(
-        "\u{26a0}\u{fe0f} OVERDUE: `{slug}` | interval: {interval} | last: {last} | {late} late"
-    );
-
-    escape_around_code_spans(&raw)
-}
+/// Example: FAILED: `my-job` | exit code: 2 | at: 12:03 UTC (just now)
+const FAILED: &str = "\u{274c} FAILED: `{{slug}}`{{#if exit_code}} | exit code: {{exit_code}}{{/if}} | at: {{failed_at}}\
+    {{#if message}}\n{{message}}{{/if}}{{#if log}}\n```\n{{log}}\n```{{/if}}";
 
-/// Format a repeat alert message (still overdue, sent every hour).
+/// Default repeat alert (still overdue, sent every hour).
 ///
 /// Example: STILL OVERDUE: `my-job` | down 23m
-pub fn format_repeat(slug: &str, total_downtime_secs: u64) -> String {
-    let downtime = format_duration(total_downtime_secs);
+const REPEAT: &str = "\u{26a0}\u{fe0f} STILL OVERDUE: `{{slug}}` | down {{downtime}}";
 
-    let raw = format!("\u{26a0}\u{fe0f} STILL OVERDUE: `{slug}` | down {downtime}");
-
-    escape_around_code_spans(&raw)
-}
+/// Default repeat failure alert (still failed, sent every hour).
+///
+/// Example: STILL FAILED: `my-job` | failed 23m ago
+const FAILED_REPEAT: &str = "\u{274c} STILL FAILED: `{{slug}}` | failed {{failed_for}} ago";
 
-/// Format a recovery message.
+/// Default recovery message.
 ///
 /// Example: RECOVERED: `my-job` (was down 23m)
-pub fn format_recovery(slug: &str, downtime_secs: u64) -> String {
-    let downtime = format_duration(downtime_secs);
+const RECOVERY: &str = "\u{2705} RECOVERED: `{{slug}}` (was down {{downtime}})";
 
-    let raw = format!("\u{2705} RECOVERED: `{slug}` \\(was down {downtime}\\)");
+/// Default recovery message for a monitor that had failed.
+///
+/// Example: SUCCEEDED: `my-job` (was failing 23m)
+const FAILED_RECOVERY: &str = "\u{2705} SUCCEEDED: `{{slug}}` (was failing {{downtime}})";
 
-    escape_around_code_spans(&raw)
-}
+/// Variables of every alert: the monitor's name (prefixed by its project
+/// outside the default one) and project.
+const COMMON_VARS: [&str; 2] = ["slug", "project"];
 
-/// Escape MarkdownV2 special characters in text OUTSIDE of backtick code spans.
+/// The messages sent for each kind of alert, as [`Template`]s, and the time
+/// zone their times are shown in unless the monitor has its own.
+///
+/// Besides `slug` and `project`, the templates can use:
+///
+/// - `overdue`: `interval`, `last_ping`, `late`
+/// - `failed`: `failed_at`, `exit_code`, `message`, `log`
+/// - `repeat` and `failed_repeat`: `downtime` or `failed_for`, and
+///   `alert_count`, the number of alerts sent including this one
+/// - `recovery` and `failed_recovery`: `downtime` and `alert_count`, the
+///   number of alerts sent before recovering
 ///
-/// Text inside backticks (e.g., `slug-name`) does not need escaping in MarkdownV2.
-fn escape_around_code_spans(text: &str) -> String {
-    let mut result = String::with_capacity(text.len() * 2);
-    let mut in_code = false;
-
-    let chars: Vec<char> = text.chars().collect();
-    let mut i = 0;
-
-    while i < chars.len() {
-        let ch = chars[i];
-
-        if ch == '`' {
-            in_code = !in_code;
-            result.push(ch);
-        } else if ch == '\\' && !in_code {
-            // Already escaped -- pass through as-is
-            result.push(ch);
-            if i + 1 < chars.len() {
-                i += 1;
-                result.push(chars[i]);
-            }
-        } else if in_code {
-            // Inside code span -- no escaping needed
-            result.push(ch);
-        } else {
-            // Outside code span -- escape special characters
-            if MARKDOWN_V2_SPECIAL.contains(&ch) {
-                result.push('\\');
-            }
-            result.push(ch);
+/// Times (`last_ping`, `failed_at`) include the date unless they are on the
+/// day of the alert, and how long ago they were.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlertTemplates {
+    timezone: Tz,
+    overdue: Template,
+    failed: Template,
+    repeat: Template,
+    failed_repeat: Template,
+    recovery: Template,
+    failed_recovery: Template,
+}
+
+/// Templates replacing the defaults, as configured.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TemplateSources {
+    overdue: Option<String>,
+    failed: Option<String>,
+    repeat: Option<String>,
+    failed_repeat: Option<String>,
+    recovery: Option<String>,
+    failed_recovery: Option<String>,
+}
+
+impl Default for AlertTemplates {
+    fn default() -> Self {
+        Self::from_sources(TemplateSources::default()).expect("default templates are valid")
+    }
+}
+
+impl AlertTemplates {
+    /// Parse templates from a JSON object mapping alert kinds (`overdue`,
+    /// `failed`, `repeat`, `failed_repeat`, `recovery`, `failed_recovery`)
+    /// to templates. Kinds left out keep the default message.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let sources = serde_json::from_str(json).map_err(|e| format!("invalid templates: {e}"))?;
+        Self::from_sources(sources)
+    }
+
+    /// Read the templates from `ALERT_TEMPLATES`, or from the file named by
+    /// `ALERT_TEMPLATES_FILE`, as for [`from_json`](Self::from_json), and the
+    /// time zone from `ALERT_TIMEZONE`. Without them, the default messages
+    /// are used, with times in UTC.
+    pub fn from_env() -> Result<Self, String> {
+        let templates = match crate::telegram::env_secret("ALERT_TEMPLATES")? {
+            Some(json) => Self::from_json(&json)?,
+            None => Self::default(),
+        };
+        match std::env::var("ALERT_TIMEZONE") {
+            Ok(name) if !name.is_empty() => Ok(templates.with_timezone(parse_timezone(&name)?)),
+            _ => Ok(templates),
         }
+    }
+
+    /// Show times in `timezone` instead of UTC, for monitors without a time
+    /// zone of their own.
+    pub fn with_timezone(mut self, timezone: Tz) -> Self {
+        self.timezone = timezone;
+        self
+    }
+
+    fn from_sources(sources: TemplateSources) -> Result<Self, String> {
+        let parse = |kind: &str, source: Option<String>, default: &str, vars: &[&str]| {
+            let allowed: Vec<&str> = COMMON_VARS.iter().chain(vars).copied().collect();
+            Template::parse(source.as_deref().unwrap_or(default), &allowed)
+                .map_err(|e| format!("invalid {kind} template: {e}"))
+        };
+
+        Ok(Self {
+            timezone: Tz::UTC,
+            overdue: parse(
+                "overdue",
+                sources.overdue,
+                OVERDUE,
+                &["interval", "last_ping", "late"],
+            )?,
+            failed: parse(
+                "failed",
+                sources.failed,
+                FAILED,
+                &["failed_at", "exit_code", "message", "log"],
+            )?,
+            repeat: parse(
+                "repeat",
+                sources.repeat,
+                REPEAT,
+                &["downtime", "alert_count"],
+            )?,
+            failed_repeat: parse(
+                "failed_repeat",
+                sources.failed_repeat,
+                FAILED_REPEAT,
+                &["failed_for", "alert_count"],
+            )?,
+            recovery: parse(
+                "recovery",
+                sources.recovery,
+                RECOVERY,
+                &["downtime", "alert_count"],
+            )?,
+            failed_recovery: parse(
+                "failed_recovery",
+                sources.failed_recovery,
+                FAILED_RECOVERY,
+                &["downtime", "alert_count"],
+            )?,
+        })
+    }
+
+    /// Format an overdue alert message (first alert) at `now`.
+    pub fn format_overdue(&self, monitor: &Monitor, now: i64) -> String {
+        let late_secs = (now - monitor.last_ping).saturating_sub(monitor.interval_secs as i64);
+
+        let mut vars = common_vars(monitor);
+        vars.insert("interval", format_duration(monitor.interval_secs));
+        let timezone = self.timezone_of(monitor);
+        vars.insert("last_ping", format_time(monitor.last_ping, now, timezone));
+        vars.insert("late", format_duration(late_secs.max(0) as u64));
+        self.overdue.render(&vars)
+    }
+
+    /// Format a failure alert message (first alert after an explicit failure)
+    /// at `now`.
+    pub fn format_failed(&self, monitor: &Monitor, failure: &Failure, now: i64) -> String {
+        let mut vars = common_vars(monitor);
+        let timezone = self.timezone_of(monitor);
+        vars.insert("failed_at", format_time(failure.at, now, timezone));
+        if let Some(code) = failure.exit_code {
+            vars.insert("exit_code", code.to_string());
+        }
+        if let Some(message) = &failure.message {
+            vars.insert("message", message.clone());
+        }
+        if let Some(log) = &failure.log {
+            vars.insert("log", log.clone());
+        }
+        self.failed.render(&vars)
+    }
+
+    /// Format a repeat alert message (still overdue, sent every hour).
+    pub fn format_repeat(&self, monitor: &Monitor, total_downtime_secs: u64) -> String {
+        let mut vars = repeat_vars(monitor);
+        vars.insert("downtime", format_duration(total_downtime_secs));
+        self.repeat.render(&vars)
+    }
+
+    /// Format a repeat failure message (still failed, sent every hour).
+    pub fn format_failed_repeat(&self, monitor: &Monitor, failed_for_secs: u64) -> String {
+        let mut vars = repeat_vars(monitor);
+        vars.insert("failed_for", format_duration(failed_for_secs));
+        self.failed_repeat.render(&vars)
+    }
+
+    /// Format a recovery message.
+    pub fn format_recovery(&self, monitor: &Monitor, downtime_secs: u64) -> String {
+        self.recovery.render(&recovery_vars(monitor, downtime_secs))
+    }
 
-        i += 1;
+    /// Format a recovery message for a monitor that had failed.
+    pub fn format_failed_recovery(&self, monitor: &Monitor, downtime_secs: u64) -> String {
+        self.failed_recovery
+            .render(&recovery_vars(monitor, downtime_secs))
     }
 
-    result
+    /// The time zone of `monitor`'s alerts: its own if valid, else the default.
+    fn timezone_of(&self, monitor: &Monitor) -> Tz {
+        monitor
+            .timezone
+            .as_deref()
+            .and_then(|name| name.parse().ok())
+            .unwrap_or(self.timezone)
+    }
+}
+
+/// Parse an IANA time zone name such as `Europe/Berlin`.
+pub fn parse_timezone(name: &str) -> Result<Tz, String> {
+    name.parse()
+        .map_err(|_| format!("unknown time zone: {name}"))
+}
+
+fn common_vars(monitor: &Monitor) -> Vars {
+    Vars::from([
+        ("slug", monitor.display_name()),
+        ("project", monitor.project.clone()),
+    ])
+}
+
+fn repeat_vars(monitor: &Monitor) -> Vars {
+    let mut vars = common_vars(monitor);
+    let alert_count = monitor.alert_count.unwrap_or(0) + 1;
+    vars.insert("alert_count", alert_count.to_string());
+    vars
+}
+
+fn recovery_vars(monitor: &Monitor, downtime_secs: u64) -> Vars {
+    let mut vars = common_vars(monitor);
+    let alert_count = monitor.alert_count.unwrap_or(0);
+    vars.insert("alert_count", alert_count.to_string());
+    vars.insert("downtime", format_duration(downtime_secs));
+    vars
+}
+
+/// Format a human-readable duration from seconds using humantime.
+fn format_duration(secs: u64) -> String {
+    if secs == 0 {
+        return "0s".to_string();
+    }
+    humantime::format_duration(std::time::Duration::from_secs(secs)).to_string()
+}
+
+/// Format a timestamp from epoch seconds in `timezone`, for an alert sent at
+/// `now`: the time, preceded by the date unless it is the same day as `now`,
+/// and how long ago it was.
+///
+/// Example: 12:03 CEST (25m ago), or 2026-10-15 12:03 CEST (3 days ago)
+fn format_time(epoch: i64, now: i64, timezone: Tz) -> String {
+    let (Some(at), Some(today)) = (
+        DateTime::from_timestamp(epoch, 0),
+        DateTime::from_timestamp(now, 0),
+    ) else {
+        return "unknown".to_string();
+    };
+    let at = at.with_timezone(&timezone);
+    let today = today.with_timezone(&timezone).date_naive();
+
+    let format = if at.date_naive() == today {
+        "%H:%M %Z"
+    } else {
+        "%Y-%m-%d %H:%M %Z"
+    };
+    format!("{} ({})", at.format(format), format_ago(now - epoch))
+}
+
+/// Format how long ago something happened, at the coarsest unit.
+fn format_ago(secs: i64) -> String {
+    match secs {
+        ..60 => "just now".to_string(),
+        60..3600 => format!("{}m ago", secs / 60),
+        3600..86_400 => format!("{}h ago", secs / 3600),
+        86_400..172_800 => "1 day ago".to_string(),
+        _ => format!("{} days ago", secs / 86_400),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn monitor(slug: &str) -> Monitor {
+        Monitor {
+            slug: slug.into(),
+            project: "default".into(),
+            interval_secs: 300,
+            last_ping: 1000,
+            next_due: 1300,
+            check_partition: "CHECK".into(),
+            first_alerted_at: None,
+            last_alerted_at: None,
+            alert_count: None,
+            alert_status: None,
+            created_at: 0,
+            paused: None,
+            expires_at: 0,
+            last_failure: None,
+            last_ping_meta: None,
+            ping_token: None,
+            timezone: None,
+        }
+    }
+
     #[test]
     fn test_format_overdue_contains_slug() {
-        let msg = format_overdue("my-job", 300, 1000, 1600);
+        let msg = AlertTemplates::default().format_overdue(&monitor("my-job"), 1600);
+        assert_eq!(
+            msg,
+            "\u{26a0}\u{fe0f} OVERDUE: `my-job` \\| interval: 5m \\| last: 00:16 UTC \\(10m ago\\) \\| 5m late"
+        );
+    }
+
+    #[test]
+    fn test_format_failed_contains_details() {
+        let failure = Failure {
+            at: 1000,
+            exit_code: Some(2),
+            message: Some("disk full (98%)".into()),
+            log: Some("rsync: `error`".into()),
+        };
+        let msg = AlertTemplates::default().format_failed(&monitor("my-job"), &failure, 1000);
         assert!(msg.contains("`my-job`"));
-        assert!(msg.contains("OVERDUE"));
+        assert!(msg.contains("FAILED"));
+        assert!(msg.contains("exit code: 2"));
+        assert!(msg.contains("disk full \\(98%\\)"));
+        assert!(msg.contains("```\nrsync: \\`error\\`\n```"));
+    }
+
+    #[test]
+    fn test_format_failed_without_details() {
+        let failure = Failure {
+            at: 1000,
+            exit_code: None,
+            message: None,
+            log: None,
+        };
+        let msg = AlertTemplates::default().format_failed(&monitor("my-job"), &failure, 1030);
+        assert_eq!(
+            msg,
+            "\u{274c} FAILED: `my-job` \\| at: 00:16 UTC \\(just now\\)"
+        );
     }
 
     #[test]
     fn test_format_repeat_contains_slug() {
-        let msg = format_repeat("my-job", 1380);
+        let msg = AlertTemplates::default().format_repeat(&monitor("my-job"), 1380);
         assert!(msg.contains("`my-job`"));
         assert!(msg.contains("STILL OVERDUE"));
     }
 
     #[test]
     fn test_format_recovery_contains_slug() {
-        let msg = format_recovery("my-job", 1380);
+        let msg = AlertTemplates::default().format_recovery(&monitor("my-job"), 1380);
         assert!(msg.contains("`my-job`"));
         assert!(msg.contains("RECOVERED"));
+        assert!(msg.contains("\\(was down 23m\\)"));
+    }
+
+    #[test]
+    fn test_format_failed_repeat_contains_slug() {
+        let msg = AlertTemplates::default().format_failed_repeat(&monitor("my-job"), 1380);
+        assert!(msg.contains("`my-job`"));
+        assert!(msg.contains("STILL FAILED"));
+    }
+
+    #[test]
+    fn test_format_failed_recovery_contains_slug() {
+        let msg = AlertTemplates::default().format_failed_recovery(&monitor("my-job"), 1380);
+        assert!(msg.contains("`my-job`"));
+        assert!(msg.contains("SUCCEEDED"));
+        assert!(msg.contains("\\(was failing 23m\\)"));
+    }
+
+    #[test]
+    fn times_in_configured_and_monitor_time_zones() {
+        let berlin =
+            AlertTemplates::default().with_timezone(parse_timezone("Europe/Berlin").unwrap());
+        let mut m = monitor("my-job");
+        m.last_ping = 1_760_000_000; // 2025-10-09 08:53:20 UTC
+
+        let msg = berlin.format_overdue(&m, m.last_ping + 7200);
+        assert!(msg.contains("last: 10:53 CEST \\(2h ago\\)"), "{msg}");
+
+        // The date is shown from the next day on
+        let msg = berlin.format_overdue(&m, m.last_ping + 3 * 86_400 + 600);
+        assert!(
+            msg.contains("last: 2025\\-10\\-09 10:53 CEST \\(3 days ago\\)"),
+            "{msg}"
+        );
+
+        // The monitor's own time zone takes precedence
+        m.timezone = Some("America/New_York".into());
+        let msg = berlin.format_overdue(&m, m.last_ping + 7200);
+        assert!(msg.contains("last: 04:53 EDT \\(2h ago\\)"), "{msg}");
+
+        assert!(parse_timezone("Mars/Olympus_Mons").is_err());
+    }
+
+    #[test]
+    fn days_follow_the_time_zone() {
+        // 2025-10-09 23:30 UTC, an hour before the alert
+        let last_ping = 1_760_052_600;
+        assert_eq!(
+            format_time(last_ping, last_ping + 3600, Tz::UTC),
+            "2025-10-09 23:30 UTC (1h ago)"
+        );
+        assert_eq!(
+            format_time(last_ping, last_ping + 3600, Tz::Europe__Berlin),
+            "01:30 CEST (1h ago)"
+        );
+    }
+
+    #[test]
+    fn relative_times_use_the_coarsest_unit() {
+        assert_eq!(format_ago(-5), "just now");
+        assert_eq!(format_ago(59), "just now");
+        assert_eq!(format_ago(23 * 60 + 59), "23m ago");
+        assert_eq!(format_ago(5 * 3600 + 1800), "5h ago");
+        assert_eq!(format_ago(86_400 + 3600), "1 day ago");
+        assert_eq!(format_ago(3 * 86_400), "3 days ago");
+    }
+
+    #[test]
+    fn configured_templates_replace_defaults() {
+        let templates = AlertTemplates::from_json(
+            r#"{"repeat": "{{project}}: {{slug}} still down after {{downtime}} (alert #{{alert_count}})"}"#,
+        )
+        .unwrap();
+
+        let mut m = monitor("db_backup");
+        m.project = "team-a".into();
+        m.alert_count = Some(2);
+        assert_eq!(
+            templates.format_repeat(&m, 7200),
+            "team\\-a: team\\-a/db\\_backup still down after 2h \\(alert \\#3\\)"
+        );
+        // Other kinds keep their default
+        assert!(templates.format_recovery(&m, 60).contains("RECOVERED"));
     }
 
     #[test]
-    fn test_escape_around_code_spans_preserves_backtick_content() {
-        let text = "hello `my-slug` world.end";
-        let escaped = escape_around_code_spans(text);
-        // Inside backticks: my-slug should NOT be escaped
-        assert!(escaped.contains("`my-slug`"));
-        // Outside backticks: the dot should be escaped
-        assert!(escaped.contains("world\\.end"));
+    fn rejects_invalid_templates() {
+        assert!(AlertTemplates::from_json(r#"{"overdue": "{{downtime}}"}"#).is_err());
+        assert!(AlertTemplates::from_json(r#"{"recover": "{{slug}}"}"#).is_err());
+        assert!(AlertTemplates::from_json(r#"{"failed": "{{#if log}}"}"#).is_err());
+        assert!(AlertTemplates::from_json("{}").is_ok());
     }
 }
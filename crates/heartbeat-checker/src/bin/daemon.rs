@@ -0,0 +1,82 @@
+//! Standalone checker: runs the check cycle on an internal tick instead of a
+//! Lambda schedule, e.g. on-prem next to the API.
+//!
+//! Several replicas may run at once; a lease in the status table elects the
+//! one running the cycles. Data is stored in DynamoDB, or in the SQL database
+//! in `DATABASE_URL` if set.
+
+use std::env;
+
+use heartbeat_checker::alerts::AlertTemplates;
+use heartbeat_checker::checker::Checker;
+use heartbeat_checker::leader;
+use heartbeat_checker::runner::{self, Schedule};
+use heartbeat_checker::telegram::TelegramClient;
+use heartbeat_core::{DynamoTables, StorageConfig, Stores};
+use tracing::info;
+use tracing_subscriber::EnvFilter;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tracing_subscriber::fmt()
+        .json()
+        .with_env_filter(EnvFilter::from_default_env())
+        .with_target(false)
+        .init();
+
+    let mut tables = DynamoTables::default();
+    if let Ok(name) = env::var("HEARTBEAT_TABLE_NAME") {
+        tables.monitors = name;
+    }
+    if let Ok(name) = env::var("HEARTBEAT_STATUS_TABLE_NAME") {
+        tables.status = name;
+    }
+    let storage = StorageConfig::from_env(tables);
+    let schedule = Schedule::from_env()?;
+    let telegram = TelegramClient::from_env()?;
+    let templates = AlertTemplates::from_env()?;
+
+    info!(
+        storage = storage.backend(),
+        "starting heartbeat checker daemon"
+    );
+
+    let stores = Stores::connect(&storage).await?;
+    let owner = leader::owner_id(env::var("HOSTNAME").ok());
+
+    runner::run(
+        Checker::new(stores.monitors, stores.status, telegram).with_templates(templates),
+        schedule,
+        owner,
+        None,
+        shutdown_signal(),
+    )
+    .await;
+
+    info!("checker daemon stopped");
+    Ok(())
+}
+
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => info!("Ctrl+C received, shutting down"),
+        _ = terminate => info!("SIGTERM received, shutting down"),
+    }
+}
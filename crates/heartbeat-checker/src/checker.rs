@@ -1,144 +1,581 @@
 use std::collections::HashSet;
+use std::time::Instant;
 
-use chrono::Utc;
-use heartbeat_core::{DynamoStore, MonitorStatus};
-use tracing::{info, warn};
+use heartbeat_core::{CHECK_CYCLE_LEASE, Clock, Monitor, MonitorStatus, MonitorStore, StatusStore};
+use tracing::{error, info, warn};
 
-use crate::alerts;
+use crate::alerts::AlertTemplates;
 use crate::errors::CheckerError;
+use crate::report::{AlertKind, CycleReport};
 use crate::telegram::TelegramClient;
 
 /// Fixed repeat alert interval: 1 hour in seconds.
 const REPEAT_ALERT_INTERVAL_SECS: i64 = 3600;
 
-/// Run the heartbeat check cycle.
-///
-/// 1. Query overdue monitors via GSI
-/// 2. Query monitors with active alerts (for recovery detection)
-/// 3. For overdue monitors: send first alert or repeat (if 1h+ since last)
-/// 4. For recovered monitors: send recovery notification and clear alert state
-pub async fn check_monitors(
-    store: &DynamoStore,
-    telegram: &TelegramClient,
-) -> Result<(), CheckerError> {
-    let now = Utc::now().timestamp();
-
-    // Query overdue monitors and monitors with active alerts in parallel
-    let overdue = store.query_overdue(now).await?;
-    let alerted = store.query_alerted().await?;
-
-    info!(
-        overdue_count = overdue.len(),
-        alerted_count = alerted.len(),
-        "check cycle start"
-    );
-
-    // Track which slugs are currently overdue for recovery detection
-    let mut overdue_slugs: HashSet<String> = HashSet::new();
-
-    // Process overdue monitors
-    for monitor in &overdue {
-        let status = MonitorStatus::derive(monitor, now);
-
-        // Skip paused monitors (GSI may return them since it doesn't filter on paused)
-        if status == MonitorStatus::Paused {
-            info!(slug = %monitor.slug, "skipping paused monitor");
-            continue;
+/// How long the cycle lease is held without renewal: 1 minute, twice the
+/// Lambda timeout. Longer cycles renew it as they go.
+const CYCLE_LEASE_TTL_SECS: i64 = 60;
+
+/// How long a sent alert is remembered against resending: 1 day.
+const ALERT_CLAIM_TTL_SECS: i64 = 86_400;
+
+/// What a check cycle reads and writes, the clock it reads the time from and
+/// the templates of its alerts.
+#[derive(Clone)]
+pub struct Checker {
+    pub store: MonitorStore,
+    pub status_store: StatusStore,
+    pub telegram: TelegramClient,
+    pub clock: Clock,
+    pub templates: AlertTemplates,
+}
+
+impl Checker {
+    /// A checker on the system clock, sending the default alert messages.
+    pub fn new(store: MonitorStore, status_store: StatusStore, telegram: TelegramClient) -> Self {
+        Self {
+            store,
+            status_store,
+            telegram,
+            clock: Clock::System,
+            templates: AlertTemplates::default(),
         }
+    }
 
-        overdue_slugs.insert(monitor.slug.clone());
+    /// Read the time from `clock` instead of the system clock.
+    pub fn with_clock(mut self, clock: impl Into<Clock>) -> Self {
+        self.clock = clock.into();
+        self
+    }
 
-        let alert_count = monitor.alert_count.unwrap_or(0);
+    /// Send alerts formatted with `templates` instead of the defaults.
+    pub fn with_templates(mut self, templates: AlertTemplates) -> Self {
+        self.templates = templates;
+        self
+    }
 
-        match monitor.last_alerted_at {
-            None => {
-                // First alert
-                let msg = alerts::format_overdue(
-                    &monitor.slug,
-                    monitor.interval_secs,
-                    monitor.last_ping,
-                    now,
-                );
-                match telegram.send_with_retry(&msg).await {
-                    Ok(()) => {
-                        store
-                            .update_alert_state(&monitor.slug, now, now, alert_count + 1)
-                            .await?;
-                        info!(slug = %monitor.slug, "sent first overdue alert");
+    /// Run a check cycle and record it as the checker heartbeat, so the API
+    /// can detect when the checker stops running.
+    ///
+    /// Failing to record the heartbeat is only logged: the cycle itself ran.
+    pub async fn run_cycle(&self, owner: &str) -> Result<CycleReport, CheckerError> {
+        let report = self.check_monitors(owner).await?;
+        if report.skipped {
+            return Ok(report);
+        }
+
+        let heartbeat = report.heartbeat(self.clock.now());
+        if let Err(e) = self.status_store.put_checker_heartbeat(&heartbeat).await {
+            error!(error = %e, "failed to record checker heartbeat");
+        }
+
+        Ok(report)
+    }
+
+    /// Run the heartbeat check cycle.
+    ///
+    /// 1. Acquire the cycle lease for `owner`, or skip the cycle if another
+    ///    checker is running one
+    /// 2. Query overdue (and failed) monitors via GSI
+    /// 3. Query monitors with active alerts (for recovery detection)
+    /// 4. For overdue and failed monitors: send first alert or repeat (if 1h+ since last)
+    /// 5. For recovered monitors: send recovery notification (worded by the status that
+    ///    triggered the alert) and clear alert state
+    ///
+    /// Alerts are sent to the chats of the monitor's project, at most once per
+    /// idempotency key (see [`idempotency_key`]). Errors for one monitor are
+    /// recorded in the report and don't stop the cycle; only failing to query the
+    /// monitors or losing the lease does.
+    pub async fn check_monitors(&self, owner: &str) -> Result<CycleReport, CheckerError> {
+        let started = Instant::now();
+        let now = self.clock.now();
+
+        if !self
+            .status_store
+            .acquire_lease(CHECK_CYCLE_LEASE, owner, now, CYCLE_LEASE_TTL_SECS)
+            .await?
+        {
+            info!(owner, "another checker is running a cycle, skipping");
+            return Ok(CycleReport {
+                skipped: true,
+                ..CycleReport::default()
+            });
+        }
+
+        let mut cycle = Cycle {
+            status_store: &self.status_store,
+            telegram: &self.telegram,
+            clock: &self.clock,
+            templates: &self.templates,
+            owner,
+            now,
+            renewed_at: now,
+            report: CycleReport::default(),
+        };
+        let result = cycle.check(&self.store).await;
+
+        if let Err(e) = self
+            .status_store
+            .release_lease(CHECK_CYCLE_LEASE, owner)
+            .await
+        {
+            warn!(error = %e, "failed to release cycle lease");
+        }
+        result?;
+
+        let mut report = cycle.report;
+        report.duration_ms = started.elapsed().as_millis() as u64;
+
+        info!(
+            checked = report.checked,
+            alerts_sent = report.alerts_sent(),
+            alerts_failed = report.alerts_failed(),
+            duplicates = report.duplicates,
+            errors = report.errors.len(),
+            duration_ms = report.duration_ms,
+            "check cycle complete"
+        );
+        Ok(report)
+    }
+}
+
+/// A check cycle holding the cycle lease.
+struct Cycle<'a> {
+    status_store: &'a StatusStore,
+    telegram: &'a TelegramClient,
+    clock: &'a Clock,
+    templates: &'a AlertTemplates,
+    owner: &'a str,
+    /// Time the monitors are evaluated at.
+    now: i64,
+    /// Time the lease was last acquired or renewed.
+    renewed_at: i64,
+    report: CycleReport,
+}
+
+impl Cycle<'_> {
+    async fn check(&mut self, store: &MonitorStore) -> Result<(), CheckerError> {
+        let now = self.now;
+
+        // Query overdue monitors and monitors with active alerts
+        let overdue = store.query_overdue(now).await?;
+        let alerted = store.query_alerted().await?;
+
+        info!(
+            overdue_count = overdue.len(),
+            alerted_count = alerted.len(),
+            "check cycle start"
+        );
+
+        self.report.overdue = overdue.len() as u64;
+        self.report.alerted = alerted.len() as u64;
+        let mut checked: HashSet<String> = HashSet::new();
+
+        // Track which monitors (by key) are currently overdue or failed for recovery detection
+        let mut overdue_keys: HashSet<String> = HashSet::new();
+
+        // Process overdue and failed monitors
+        for monitor in &overdue {
+            checked.insert(monitor.key());
+            let status = MonitorStatus::derive(monitor, now);
+            let name = monitor.display_name();
+
+            // Skip paused monitors (GSI may return them since it doesn't filter on paused)
+            if status == MonitorStatus::Paused {
+                info!(slug = %name, "skipping paused monitor");
+                self.report.skipped_paused += 1;
+                continue;
+            }
+
+            overdue_keys.insert(monitor.key());
+
+            let alert_count = monitor.alert_count.unwrap_or(0);
+
+            match monitor.last_alerted_at {
+                None => {
+                    // First alert
+                    let msg = match (&monitor.last_failure, status) {
+                        (Some(failure), MonitorStatus::Failed) => {
+                            self.templates.format_failed(monitor, failure, now)
+                        }
+                        _ => self.templates.format_overdue(monitor, now),
+                    };
+                    // Don't update last_alert_at if Telegram is unreachable
+                    if self.send(AlertKind::First, monitor, status, &msg).await? {
+                        info!(slug = %name, ?status, "sent first alert");
+                        if let Err(e) = store
+                            .update_alert_state(&monitor.key(), now, now, alert_count + 1, status)
+                            .await
+                        {
+                            warn!(slug = %name, error = %e, "failed to update alert state");
+                            self.report.error(&name, e);
+                        }
                     }
-                    Err(e) => {
-                        // Don't update last_alert_at if Telegram is unreachable
-                        warn!(
-                            slug = %monitor.slug,
-                            error = %e,
-                            "failed to send first alert, will retry next cycle"
+                }
+                Some(last_alert) => {
+                    // Check if enough time has passed for a repeat alert (1 hour)
+                    if now - last_alert < REPEAT_ALERT_INTERVAL_SECS {
+                        continue;
+                    }
+                    let msg = match (&monitor.last_failure, status) {
+                        (Some(failure), MonitorStatus::Failed) => {
+                            let failed_for = (now - failure.at).max(0) as u64;
+                            self.templates.format_failed_repeat(monitor, failed_for)
+                        }
+                        _ => {
+                            let total_downtime = (now - monitor.next_due).max(0) as u64;
+                            self.templates.format_repeat(monitor, total_downtime)
+                        }
+                    };
+                    if self.send(AlertKind::Repeat, monitor, status, &msg).await? {
+                        info!(
+                            slug = %name,
+                            ?status,
+                            alert_count = alert_count + 1,
+                            "sent repeat alert"
                         );
+                        let first_alerted_at = monitor.first_alerted_at.unwrap_or(now);
+                        if let Err(e) = store
+                            .update_alert_state(
+                                &monitor.key(),
+                                first_alerted_at,
+                                now,
+                                alert_count + 1,
+                                status,
+                            )
+                            .await
+                        {
+                            warn!(slug = %name, error = %e, "failed to update alert state");
+                            self.report.error(&name, e);
+                        }
                     }
                 }
             }
-            Some(last_alert) => {
-                // Check if enough time has passed for a repeat alert (1 hour)
-                if now - last_alert >= REPEAT_ALERT_INTERVAL_SECS {
-                    let total_downtime = (now - monitor.next_due).max(0) as u64;
-                    let msg = alerts::format_repeat(&monitor.slug, total_downtime);
-                    match telegram.send_with_retry(&msg).await {
-                        Ok(()) => {
-                            store
-                                .update_alert_state(&monitor.slug, monitor.first_alerted_at.unwrap_or(now), now, alert_count + 1)
-                                .await?;
-                            info!(
-                                slug = %monitor.slug,
-                                alert_count = alert_count + 1,
-                                "sent repeat overdue alert"
-                            );
-                        }
-                        Err(e) => {
-                            warn!(
-                                slug = %monitor.slug,
-                                error = %e,
-                                "failed to send repeat alert, will retry next cycle"
-                            );
-                        }
+        }
+
+        // Process recoveries: monitors that had alerts but are no longer overdue
+        for monitor in &alerted {
+            checked.insert(monitor.key());
+            if overdue_keys.contains(&monitor.key()) {
+                // Still overdue -- already handled above
+                continue;
+            }
+
+            let status = MonitorStatus::derive(monitor, now);
+            let name = monitor.display_name();
+
+            // If it's paused, don't send recovery (the operator paused it)
+            if status == MonitorStatus::Paused {
+                self.report.skipped_paused += 1;
+                continue;
+            }
+
+            // Monitor recovered (was alerted, now OK)
+            if let Some(first_alert) = monitor.first_alerted_at {
+                self.report.recoveries += 1;
+                let downtime = (now - first_alert).max(0) as u64;
+                let msg = match monitor.alert_status {
+                    Some(MonitorStatus::Failed) => {
+                        self.templates.format_failed_recovery(monitor, downtime)
+                    }
+                    _ => self.templates.format_recovery(monitor, downtime),
+                };
+                if self
+                    .send(AlertKind::Recovery, monitor, status, &msg)
+                    .await?
+                {
+                    info!(slug = %name, "sent recovery notification");
+                    if let Err(e) = store.clear_alert_state(&monitor.key()).await {
+                        warn!(slug = %name, error = %e, "failed to clear alert state");
+                        self.report.error(&name, e);
                     }
                 }
             }
         }
+
+        self.report.checked = checked.len() as u64;
+        Ok(())
     }
 
-    // Process recoveries: monitors that had alerts but are no longer overdue
-    for monitor in &alerted {
-        if overdue_slugs.contains(&monitor.slug) {
-            // Still overdue -- already handled above
-            continue;
-        }
+    /// Send the `kind` alert `text` for `monitor`, unless it was already sent
+    /// under the same idempotency key.
+    ///
+    /// Returns whether the alert was delivered, now or by an earlier cycle, so
+    /// the monitor's alert state should be updated. Failures are recorded in
+    /// the report and retried next cycle.
+    async fn send(
+        &mut self,
+        kind: AlertKind,
+        monitor: &Monitor,
+        status: MonitorStatus,
+        text: &str,
+    ) -> Result<bool, CheckerError> {
+        self.renew_lease().await?;
 
-        let status = MonitorStatus::derive(monitor, now);
+        let name = monitor.display_name();
+        let key = idempotency_key(monitor, status, kind);
+        let now = self.clock.now();
 
-        // If it's paused, don't send recovery (the operator paused it)
-        if status == MonitorStatus::Paused {
-            continue;
+        match self
+            .status_store
+            .claim_alert(&key, now, now + ALERT_CLAIM_TTL_SECS)
+            .await
+        {
+            Ok(true) => {}
+            Ok(false) => {
+                // Sent by a cycle that failed to update the alert state
+                info!(slug = %name, key, "alert already sent, not sending it again");
+                self.report.duplicates += 1;
+                return Ok(true);
+            }
+            Err(e) => {
+                warn!(slug = %name, error = %e, "failed to claim alert, will retry next cycle");
+                self.report.failed(kind, &name, e);
+                return Ok(false);
+            }
         }
 
-        // Monitor recovered (was alerted, now OK)
-        if let Some(first_alert) = monitor.first_alerted_at {
-            let downtime = (now - first_alert).max(0) as u64;
-            let msg = alerts::format_recovery(&monitor.slug, downtime);
-            match telegram.send_with_retry(&msg).await {
-                Ok(()) => {
-                    store.clear_alert_state(&monitor.slug).await?;
-                    info!(slug = %monitor.slug, "sent recovery notification");
-                }
-                Err(e) => {
-                    warn!(
-                        slug = %monitor.slug,
-                        error = %e,
-                        "failed to send recovery alert, will retry next cycle"
-                    );
+        match self.telegram.send_with_retry(&monitor.project, text).await {
+            Ok(()) => {
+                self.report.sent(kind);
+                Ok(true)
+            }
+            Err(e) => {
+                warn!(
+                    slug = %name,
+                    error = %e,
+                    "failed to send {} alert, will retry next cycle",
+                    kind.as_str()
+                );
+                self.report.failed(kind, &name, e);
+                if let Err(e) = self.status_store.release_alert(&key).await {
+                    warn!(slug = %name, error = %e, "failed to release alert claim");
+                    self.report.error(&name, e);
                 }
+                Ok(false)
+            }
+        }
+    }
+
+    /// Renew the cycle lease once half its TTL has passed, failing if it was
+    /// lost: another checker may be running a cycle by now.
+    async fn renew_lease(&mut self) -> Result<(), CheckerError> {
+        let now = self.clock.now();
+        if now - self.renewed_at < CYCLE_LEASE_TTL_SECS / 2 {
+            return Ok(());
+        }
+
+        if !self
+            .status_store
+            .renew_lease(CHECK_CYCLE_LEASE, self.owner, now, CYCLE_LEASE_TTL_SECS)
+            .await?
+        {
+            return Err(CheckerError::LeaseLost);
+        }
+        self.renewed_at = now;
+        Ok(())
+    }
+}
+
+/// The idempotency key of the `kind` alert for `monitor` in `status`.
+///
+/// The same alert, resent by an overlapping or retried cycle, gets the same
+/// key: first alerts are identified by the incident (the reported failure or
+/// the missed deadline), repeats and recoveries by the alert they follow.
+fn idempotency_key(monitor: &Monitor, status: MonitorStatus, kind: AlertKind) -> String {
+    let key = monitor.key();
+    match kind {
+        AlertKind::First => match (&monitor.last_failure, status) {
+            (Some(failure), MonitorStatus::Failed) => {
+                format!("{key}#first#failed-{}", failure.at)
             }
+            _ => format!("{key}#first#overdue-{}", monitor.next_due),
+        },
+        AlertKind::Repeat | AlertKind::Recovery => format!(
+            "{key}#{}#{}",
+            kind.as_str(),
+            monitor.last_alerted_at.unwrap_or(0)
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    use axum::extract::State;
+    use axum::routing::post;
+    use axum::{Json, Router};
+    use heartbeat_core::{Failure, ManualClock, StorageConfig, Stores};
+    use serde_json::{Value, json};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    fn monitor(project: &str) -> Monitor {
+        Monitor {
+            slug: "backup".into(),
+            project: project.into(),
+            interval_secs: 300,
+            last_ping: 1000,
+            next_due: 1300,
+            check_partition: "CHECK".into(),
+            first_alerted_at: None,
+            last_alerted_at: None,
+            alert_count: None,
+            alert_status: None,
+            created_at: 0,
+            paused: None,
+            expires_at: 0,
+            last_failure: None,
+            last_ping_meta: None,
+            ping_token: None,
+            timezone: None,
         }
     }
 
-    info!("check cycle complete");
-    Ok(())
+    #[test]
+    fn first_alert_keyed_by_incident() {
+        let mut m = monitor("team-a");
+        assert_eq!(
+            idempotency_key(&m, MonitorStatus::Overdue, AlertKind::First),
+            "team-a#backup#first#overdue-1300"
+        );
+
+        m.last_failure = Some(Failure {
+            at: 1100,
+            exit_code: Some(1),
+            message: None,
+            log: None,
+        });
+        assert_eq!(
+            idempotency_key(&m, MonitorStatus::Failed, AlertKind::First),
+            "team-a#backup#first#failed-1100"
+        );
+    }
+
+    #[test]
+    fn repeat_and_recovery_keyed_by_last_alert() {
+        let mut m = monitor("default");
+        m.first_alerted_at = Some(1400);
+        m.last_alerted_at = Some(5000);
+        assert_eq!(
+            idempotency_key(&m, MonitorStatus::Overdue, AlertKind::Repeat),
+            "backup#repeat#5000"
+        );
+        assert_eq!(
+            idempotency_key(&m, MonitorStatus::Ok, AlertKind::Recovery),
+            "backup#recovery#5000"
+        );
+    }
+
+    /// Texts received by [`fake_telegram`].
+    type Sent = Arc<Mutex<Vec<String>>>;
+
+    /// Serve `sendMessage` on a local port, recording the texts sent.
+    async fn fake_telegram() -> (String, Sent) {
+        async fn send_message(State(sent): State<Sent>, Json(body): Json<Value>) -> Json<Value> {
+            sent.lock()
+                .unwrap()
+                .push(body["text"].as_str().unwrap().to_string());
+            Json(json!({ "ok": true }))
+        }
+
+        let sent = Sent::default();
+        let app = Router::new()
+            .route("/{bot}/sendMessage", post(send_message))
+            .with_state(sent.clone());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (url, sent)
+    }
+
+    /// A ping to `monitor` at `now`, as recorded by the API.
+    async fn ping(store: &MonitorStore, monitor: &mut Monitor, now: i64) {
+        monitor.last_ping = now;
+        monitor.next_due = now + monitor.interval_secs as i64;
+        store.upsert_monitor(monitor).await.unwrap();
+    }
+
+    /// Run a cycle at the checker's time, returning the alerts it sent.
+    async fn cycle(checker: &Checker, sent: &Sent) -> Vec<String> {
+        let report = checker.run_cycle("test").await.unwrap();
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        std::mem::take(&mut *sent.lock().unwrap())
+    }
+
+    #[tokio::test]
+    async fn simulates_overdue_repeat_and_recovery() {
+        let stores = Stores::connect(&StorageConfig::Sql {
+            url: "sqlite::memory:".to_string(),
+        })
+        .await
+        .unwrap();
+        let (url, sent) = fake_telegram().await;
+        let telegram =
+            TelegramClient::new("token".into(), "42".into(), HashMap::new()).with_api_url(url);
+        let clock = ManualClock::new(1_700_000_000);
+        let checker = Checker::new(stores.monitors.clone(), stores.status.clone(), telegram)
+            .with_clock(clock.clone());
+
+        let start = clock.now();
+        let mut m = monitor("default");
+        m.created_at = start - 3600;
+        m.expires_at = start + 30 * 86_400;
+        ping(&checker.store, &mut m, start).await;
+
+        // Not due yet
+        clock.advance(299);
+        assert!(cycle(&checker, &sent).await.is_empty());
+
+        // Overdue: first alert, then nothing until an hour has passed
+        let first_alert = clock.advance(61);
+        let alerts = cycle(&checker, &sent).await;
+        assert_eq!(alerts.len(), 1);
+        assert!(alerts[0].contains("OVERDUE: `backup`"), "{}", alerts[0]);
+        assert!(alerts[0].contains("1m late"), "{}", alerts[0]);
+
+        clock.advance(1800);
+        assert!(cycle(&checker, &sent).await.is_empty());
+        clock.set(first_alert + REPEAT_ALERT_INTERVAL_SECS - 1);
+        assert!(cycle(&checker, &sent).await.is_empty());
+
+        // Hourly repeats, counting the downtime since the missed deadline
+        clock.set(first_alert + REPEAT_ALERT_INTERVAL_SECS);
+        let alerts = cycle(&checker, &sent).await;
+        assert_eq!(alerts.len(), 1);
+        assert!(
+            alerts[0].contains("STILL OVERDUE: `backup`"),
+            "{}",
+            alerts[0]
+        );
+        assert!(alerts[0].contains("down 1h 1m"), "{}", alerts[0]);
+
+        clock.advance(REPEAT_ALERT_INTERVAL_SECS);
+        let alerts = cycle(&checker, &sent).await;
+        assert_eq!(alerts.len(), 1);
+        assert!(alerts[0].contains("down 2h 1m"), "{}", alerts[0]);
+
+        let alerted = checker.store.query_alerted().await.unwrap();
+        assert_eq!(alerted[0].alert_count, Some(3));
+        assert_eq!(alerted[0].first_alerted_at, Some(first_alert));
+
+        // A ping recovers the monitor, with the downtime since the first alert
+        let now = clock.advance(600);
+        ping(&checker.store, &mut m, now).await;
+        let alerts = cycle(&checker, &sent).await;
+        assert_eq!(alerts.len(), 1);
+        assert!(alerts[0].contains("RECOVERED: `backup`"), "{}", alerts[0]);
+        assert!(alerts[0].contains("was down 2h 10m"), "{}", alerts[0]);
+        assert!(checker.store.query_alerted().await.unwrap().is_empty());
+
+        clock.advance(60);
+        assert!(cycle(&checker, &sent).await.is_empty());
+
+        // The checker heartbeat is stamped with the simulated time
+        let heartbeat = checker.status_store.get_checker_heartbeat().await.unwrap();
+        assert_eq!(heartbeat.unwrap().at, clock.now());
+    }
 }
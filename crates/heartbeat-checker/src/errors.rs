@@ -20,4 +20,7 @@ pub enum CheckerError {
     /// Error from Telegram API.
     #[error("checker telegram error: {0}")]
     Telegram(#[from] TelegramError),
+    /// The cycle lease expired mid-cycle and may be held by another checker.
+    #[error("cycle lease lost, stopping the cycle")]
+    LeaseLost,
 }
@@ -0,0 +1,102 @@
+use std::time::Duration;
+
+use heartbeat_core::{CHECKER_LEASE, StatusStore};
+use tracing::{info, warn};
+
+/// Leader election between checker replicas, so that only one of them runs
+/// each cycle and monitors aren't alerted twice.
+///
+/// The leader holds a lease item in the status table and renews it before
+/// every cycle. If it stops renewing, another replica takes over once the
+/// lease has expired.
+pub struct LeaderLease {
+    store: StatusStore,
+    owner: String,
+    ttl: Duration,
+    is_leader: bool,
+}
+
+impl LeaderLease {
+    /// Create a lease contender identified by `owner`, holding the lease for
+    /// `ttl` after each renewal.
+    pub fn new(store: StatusStore, owner: String, ttl: Duration) -> Self {
+        Self {
+            store,
+            owner,
+            ttl,
+            is_leader: false,
+        }
+    }
+
+    /// The identifier of this replica in the lease item.
+    pub fn owner(&self) -> &str {
+        &self.owner
+    }
+
+    /// Acquire or renew the lease at `now`, returning whether this replica
+    /// is the leader.
+    ///
+    /// If DynamoDB can't be reached, this replica stops acting as leader: it
+    /// can't tell whether another one took over.
+    pub async fn acquire(&mut self, now: i64) -> bool {
+        let is_leader = match self
+            .store
+            .acquire_lease(CHECKER_LEASE, &self.owner, now, self.ttl.as_secs() as i64)
+            .await
+        {
+            Ok(acquired) => acquired,
+            Err(e) => {
+                warn!(error = %e, "failed to acquire checker lease");
+                false
+            }
+        };
+
+        match (self.is_leader, is_leader) {
+            (false, true) => info!(owner = %self.owner, "became checker leader"),
+            (true, false) => warn!(owner = %self.owner, "lost checker leadership"),
+            _ => {}
+        }
+        self.is_leader = is_leader;
+        is_leader
+    }
+
+    /// Release the lease if held, so another replica can take over without
+    /// waiting for it to expire.
+    pub async fn release(&mut self) {
+        if !self.is_leader {
+            return;
+        }
+        match self.store.release_lease(CHECKER_LEASE, &self.owner).await {
+            Ok(()) => info!(owner = %self.owner, "released checker lease"),
+            Err(e) => warn!(error = %e, "failed to release checker lease"),
+        }
+        self.is_leader = false;
+    }
+}
+
+/// An identifier for this process: `hostname:pid`.
+///
+/// The hostname is the pod name under Kubernetes, so replicas are told apart
+/// in the lease item and in the logs.
+pub fn owner_id(hostname: Option<String>) -> String {
+    let hostname = hostname
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| "checker".to_string());
+    format!("{hostname}:{}", std::process::id())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn owner_id_includes_hostname_and_pid() {
+        let pid = std::process::id();
+        assert_eq!(
+            owner_id(Some("checker-7d9f".into())),
+            format!("checker-7d9f:{pid}")
+        );
+        assert_eq!(owner_id(Some(String::new())), format!("checker:{pid}"));
+        assert_eq!(owner_id(None), format!("checker:{pid}"));
+    }
+}
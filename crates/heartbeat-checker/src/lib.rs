@@ -0,0 +1,9 @@
+pub mod alerts;
+pub mod checker;
+pub mod errors;
+pub mod leader;
+pub mod report;
+pub mod runner;
+pub mod scheduler;
+pub mod telegram;
+pub mod template;
@@ -1,18 +1,15 @@
-mod alerts;
-mod checker;
-mod errors;
-mod telegram;
-
+use std::collections::HashMap;
 use std::env;
 
 use aws_config::BehaviorVersion;
-use heartbeat_core::DynamoStore;
+use heartbeat_checker::alerts::{self, AlertTemplates};
+use heartbeat_checker::checker::Checker;
+use heartbeat_checker::telegram::{self, TelegramClient};
+use heartbeat_core::{DynamoTables, StorageConfig, Stores};
 use lambda_runtime::{service_fn, Error, LambdaEvent};
 use tracing::info;
 use tracing_subscriber::EnvFilter;
 
-use telegram::TelegramClient;
-
 #[tokio::main]
 async fn main() -> Result<(), Error> {
     // Initialize structured JSON logging for CloudWatch
@@ -26,10 +23,22 @@ async fn main() -> Result<(), Error> {
     let table_name =
         env::var("HEARTBEAT_TABLE_NAME").unwrap_or_else(|_| "heartbeat-monitors".to_string());
 
-    info!(table_name = %table_name, "initializing heartbeat checker");
+    let status_table_name = env::var("HEARTBEAT_STATUS_TABLE_NAME")
+        .unwrap_or_else(|_| "heartbeat-status".to_string());
+
+    info!(
+        table_name = %table_name,
+        status_table_name = %status_table_name,
+        "initializing heartbeat checker"
+    );
 
-    // Create DynamoDB store
-    let store = DynamoStore::new(&table_name).await;
+    // The Lambda always uses DynamoDB; DATABASE_URL is for the daemon
+    let stores = Stores::connect(&StorageConfig::DynamoDb(DynamoTables {
+        monitors: table_name,
+        status: status_table_name,
+        ..DynamoTables::default()
+    }))
+    .await?;
 
     // Read Telegram credentials from SSM Parameter Store
     let config = aws_config::defaults(BehaviorVersion::latest()).load().await;
@@ -60,19 +69,73 @@ async fn main() -> Result<(), Error> {
         .and_then(|p| p.value().map(String::from))
         .ok_or_else(|| Error::from("missing SSM parameter for chat id"))?;
 
-    let telegram = TelegramClient::new(bot_token, chat_id);
+    // Optional per-project chats: a JSON object of project name -> chat IDs
+    let project_chat_ids = match env::var("TELEGRAM_PROJECT_CHAT_IDS_PARAM") {
+        Ok(param) => {
+            let json = ssm
+                .get_parameter()
+                .name(&param)
+                .with_decryption(true)
+                .send()
+                .await?
+                .parameter()
+                .and_then(|p| p.value().map(String::from))
+                .ok_or_else(|| Error::from("missing SSM parameter for project chat ids"))?;
+            telegram::parse_project_chat_ids(&json)?
+        }
+        Err(_) => HashMap::new(),
+    };
+
+    info!(
+        projects = project_chat_ids.len(),
+        "loaded per-project Telegram chats"
+    );
+
+    // Optional alert templates: a JSON object of alert kind -> template
+    let templates = match env::var("ALERT_TEMPLATES_PARAM") {
+        Ok(param) => {
+            let json = ssm
+                .get_parameter()
+                .name(&param)
+                .send()
+                .await?
+                .parameter()
+                .and_then(|p| p.value().map(String::from))
+                .ok_or_else(|| Error::from("missing SSM parameter for alert templates"))?;
+            AlertTemplates::from_json(&json)?
+        }
+        Err(_) => AlertTemplates::default(),
+    };
+    let templates = match env::var("ALERT_TIMEZONE") {
+        Ok(name) if !name.is_empty() => templates.with_timezone(alerts::parse_timezone(&name)?),
+        _ => templates,
+    };
+
+    let telegram = TelegramClient::new(bot_token, chat_id, project_chat_ids);
+    let checker =
+        Checker::new(stores.monitors, stores.status, telegram).with_templates(templates);
 
     info!("cold start complete, starting Lambda runtime");
 
     // Run the Lambda runtime
-    lambda_runtime::run(service_fn(|_event: LambdaEvent<serde_json::Value>| {
-        let store = store.clone();
-        let telegram = telegram.clone();
+    lambda_runtime::run(service_fn(|event: LambdaEvent<serde_json::Value>| {
+        let checker = checker.clone();
         async move {
-            checker::check_monitors(&store, &telegram)
+            // Identifies this invocation in the cycle lease
+            let owner = format!("lambda:{}", event.context.request_id);
+            let report = checker
+                .run_cycle(&owner)
                 .await
                 .map_err(|e| Error::from(e.to_string()))?;
-            Ok::<serde_json::Value, Error>(serde_json::json!({"status": "ok"}))
+
+            // Embedded Metric Format lines must be printed as-is, not as log messages
+            if !report.skipped {
+                for document in report.emf_documents(checker.clock.now_millis()) {
+                    println!("{document}");
+                }
+            }
+
+            Ok::<serde_json::Value, Error>(serde_json::to_value(&report)?)
         }
     }))
     .await
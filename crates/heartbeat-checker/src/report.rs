@@ -0,0 +1,262 @@
+use heartbeat_core::CheckerHeartbeat;
+use serde::Serialize;
+use serde_json::{Value, json};
+
+/// CloudWatch namespace of the cycle metrics.
+const METRICS_NAMESPACE: &str = "Heartbeat";
+
+/// Statistics of a check cycle, returned as the Lambda output.
+#[derive(Debug, Default, Serialize)]
+pub struct CycleReport {
+    /// Whether the cycle was skipped because another checker was running one.
+    pub skipped: bool,
+    /// Monitors returned as overdue or failed.
+    pub overdue: u64,
+    /// Monitors with an active alert, checked for recovery.
+    pub alerted: u64,
+    /// Distinct monitors looked at in the cycle.
+    pub checked: u64,
+    /// Overdue or alerted monitors skipped because they are paused.
+    pub skipped_paused: u64,
+    /// Monitors found recovered, whether or not the notification was sent.
+    pub recoveries: u64,
+    pub first_alerts: AlertCounts,
+    pub repeat_alerts: AlertCounts,
+    pub recovery_alerts: AlertCounts,
+    /// Alerts not sent because an earlier cycle had already sent them.
+    pub duplicates: u64,
+    /// Per-monitor errors. The cycle continues past them; the affected
+    /// monitors are retried next cycle.
+    pub errors: Vec<MonitorError>,
+    pub duration_ms: u64,
+}
+
+/// Notifications of one kind sent and failed in a cycle.
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub struct AlertCounts {
+    pub sent: u64,
+    pub failed: u64,
+}
+
+/// An error while handling one monitor.
+#[derive(Debug, Serialize)]
+pub struct MonitorError {
+    /// Monitor name, `project/slug` outside the default project.
+    pub monitor: String,
+    pub error: String,
+}
+
+/// Kind of notification, for [`CycleReport::sent`] and [`CycleReport::failed`].
+#[derive(Debug, Clone, Copy)]
+pub enum AlertKind {
+    First,
+    Repeat,
+    Recovery,
+}
+
+impl AlertKind {
+    /// Name of the kind, as the `AlertType` metric dimension.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AlertKind::First => "first",
+            AlertKind::Repeat => "repeat",
+            AlertKind::Recovery => "recovery",
+        }
+    }
+}
+
+impl CycleReport {
+    fn counts_mut(&mut self, kind: AlertKind) -> &mut AlertCounts {
+        match kind {
+            AlertKind::First => &mut self.first_alerts,
+            AlertKind::Repeat => &mut self.repeat_alerts,
+            AlertKind::Recovery => &mut self.recovery_alerts,
+        }
+    }
+
+    /// Count a notification of `kind` as sent.
+    pub fn sent(&mut self, kind: AlertKind) {
+        self.counts_mut(kind).sent += 1;
+    }
+
+    /// Count a notification of `kind` that could not be sent for `monitor`.
+    pub fn failed(&mut self, kind: AlertKind, monitor: &str, error: impl ToString) {
+        self.counts_mut(kind).failed += 1;
+        self.error(monitor, error);
+    }
+
+    /// Record an error while handling `monitor`.
+    pub fn error(&mut self, monitor: &str, error: impl ToString) {
+        self.errors.push(MonitorError {
+            monitor: monitor.to_string(),
+            error: error.to_string(),
+        });
+    }
+
+    /// Notifications of all kinds sent.
+    pub fn alerts_sent(&self) -> u64 {
+        self.first_alerts.sent + self.repeat_alerts.sent + self.recovery_alerts.sent
+    }
+
+    /// Notifications of all kinds that could not be sent.
+    pub fn alerts_failed(&self) -> u64 {
+        self.first_alerts.failed + self.repeat_alerts.failed + self.recovery_alerts.failed
+    }
+
+    /// The checker heartbeat recording this cycle as completed at `at`.
+    pub fn heartbeat(&self, at: i64) -> CheckerHeartbeat {
+        CheckerHeartbeat {
+            at,
+            overdue: self.overdue,
+            alerted: self.alerted,
+            alerts_sent: self.alerts_sent(),
+            errors: self.errors.len() as u64,
+            last_error: self
+                .errors
+                .last()
+                .map(|e| format!("{}: {}", e.monitor, e.error)),
+        }
+    }
+
+    /// CloudWatch Embedded Metric Format documents for this cycle, one per
+    /// log line: cycle totals, then notifications by `AlertType`.
+    pub fn emf_documents(&self, timestamp_ms: i64) -> Vec<Value> {
+        let totals = emf_document(
+            timestamp_ms,
+            &[],
+            &[
+                ("MonitorsChecked", "Count", self.checked),
+                ("Overdue", "Count", self.overdue),
+                ("SkippedPaused", "Count", self.skipped_paused),
+                ("Recoveries", "Count", self.recoveries),
+                ("AlertsSent", "Count", self.alerts_sent()),
+                ("AlertsFailed", "Count", self.alerts_failed()),
+                ("AlertsDeduplicated", "Count", self.duplicates),
+                ("Errors", "Count", self.errors.len() as u64),
+                ("Duration", "Milliseconds", self.duration_ms),
+            ],
+        );
+
+        let by_type = [
+            (AlertKind::First, self.first_alerts),
+            (AlertKind::Repeat, self.repeat_alerts),
+            (AlertKind::Recovery, self.recovery_alerts),
+        ]
+        .into_iter()
+        .map(|(kind, counts)| {
+            emf_document(
+                timestamp_ms,
+                &[("AlertType", kind.as_str())],
+                &[
+                    ("AlertsSent", "Count", counts.sent),
+                    ("AlertsFailed", "Count", counts.failed),
+                ],
+            )
+        });
+
+        std::iter::once(totals).chain(by_type).collect()
+    }
+}
+
+/// An EMF document with `dimensions` and `metrics` as (name, unit, value).
+fn emf_document(
+    timestamp_ms: i64,
+    dimensions: &[(&str, &str)],
+    metrics: &[(&str, &str, u64)],
+) -> Value {
+    let dimension_names: Vec<&str> = dimensions.iter().map(|(name, _)| *name).collect();
+    let definitions: Vec<Value> = metrics
+        .iter()
+        .map(|(name, unit, _)| json!({ "Name": name, "Unit": unit }))
+        .collect();
+
+    let mut document = json!({
+        "_aws": {
+            "Timestamp": timestamp_ms,
+            "CloudWatchMetrics": [{
+                "Namespace": METRICS_NAMESPACE,
+                "Dimensions": [dimension_names],
+                "Metrics": definitions,
+            }],
+        },
+    });
+    for (name, value) in dimensions {
+        document[*name] = json!(value);
+    }
+    for (name, _, value) in metrics {
+        document[*name] = json!(value);
+    }
+    document
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report() -> CycleReport {
+        let mut report = CycleReport {
+            overdue: 3,
+            alerted: 2,
+            checked: 4,
+            duration_ms: 120,
+            ..CycleReport::default()
+        };
+        report.sent(AlertKind::First);
+        report.sent(AlertKind::Recovery);
+        report.failed(AlertKind::Repeat, "team-a/backup", "timeout");
+        report.error("db-dump", "throttled");
+        report
+    }
+
+    #[test]
+    fn counts_alerts_and_errors() {
+        let report = report();
+        assert_eq!(report.alerts_sent(), 2);
+        assert_eq!(report.alerts_failed(), 1);
+        assert_eq!(report.errors.len(), 2);
+        assert_eq!(report.errors[0].monitor, "team-a/backup");
+    }
+
+    #[test]
+    fn heartbeat_summarizes_report() {
+        let heartbeat = report().heartbeat(1000);
+        assert_eq!(heartbeat.at, 1000);
+        assert_eq!(heartbeat.alerts_sent, 2);
+        assert_eq!(heartbeat.errors, 2);
+        assert_eq!(heartbeat.last_error.as_deref(), Some("db-dump: throttled"));
+    }
+
+    #[test]
+    fn emf_totals_document() {
+        let documents = report().emf_documents(1_700_000_000_000);
+        assert_eq!(documents.len(), 4);
+
+        let totals = &documents[0];
+        let directive = &totals["_aws"]["CloudWatchMetrics"][0];
+        assert_eq!(totals["_aws"]["Timestamp"], 1_700_000_000_000i64);
+        assert_eq!(directive["Namespace"], "Heartbeat");
+        assert_eq!(directive["Dimensions"], json!([[]]));
+        assert_eq!(totals["MonitorsChecked"], 4);
+        assert_eq!(totals["AlertsFailed"], 1);
+        assert_eq!(totals["Duration"], 120);
+        assert!(
+            directive["Metrics"]
+                .as_array()
+                .unwrap()
+                .contains(&json!({ "Name": "Duration", "Unit": "Milliseconds" }))
+        );
+    }
+
+    #[test]
+    fn emf_documents_by_alert_type() {
+        let documents = report().emf_documents(0);
+        let repeat = &documents[2];
+        assert_eq!(
+            repeat["_aws"]["CloudWatchMetrics"][0]["Dimensions"],
+            json!([["AlertType"]])
+        );
+        assert_eq!(repeat["AlertType"], "repeat");
+        assert_eq!(repeat["AlertsSent"], 0);
+        assert_eq!(repeat["AlertsFailed"], 1);
+    }
+}
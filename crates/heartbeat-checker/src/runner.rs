@@ -0,0 +1,337 @@
+use std::future::Future;
+use std::time::Duration;
+
+use heartbeat_core::{Clock, MonitorId, MonitorStore};
+use tokio::sync::mpsc;
+use tokio::time::{Instant, MissedTickBehavior};
+use tracing::{debug, error, info, warn};
+
+use crate::checker::Checker;
+use crate::leader::LeaderLease;
+use crate::scheduler::{DueQueue, DueUpdate};
+
+/// Default time between check cycles: 2 minutes, as the Lambda schedule.
+pub const DEFAULT_CHECK_INTERVAL: Duration = Duration::from_secs(120);
+
+/// Minimum time between cycles run because monitors became due, so that
+/// monitors due a few seconds apart are checked together: 5 seconds.
+const MIN_DUE_CYCLE_GAP: Duration = Duration::from_secs(5);
+
+/// When a long-running checker runs its cycles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Schedule {
+    /// Time between check cycles.
+    pub interval: Duration,
+    /// How long the leader lease is held after each renewal.
+    pub lease_ttl: Duration,
+    pub mode: ScheduleMode,
+}
+
+/// How a long-running checker decides when to run a cycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScheduleMode {
+    /// Every interval, as the Lambda.
+    Interval,
+    /// Every interval, and as soon as a monitor becomes due. Due times are
+    /// read from all monitors every interval, read again for the monitors a
+    /// cycle was run for, and updated by the API's pings when running inside
+    /// it.
+    ///
+    /// Pings only reach a checker in the API process that received them. A
+    /// standalone daemon, or a leader in another API replica, only sees a
+    /// ping at the next refresh, or when the monitor's previous due time is
+    /// reached and it is read again.
+    Due,
+}
+
+impl Schedule {
+    /// Read the schedule from `CHECK_INTERVAL`, `LEADER_LEASE_TTL` and
+    /// `CHECK_SCHEDULE` (`interval` or `due`).
+    pub fn from_env() -> Result<Self, String> {
+        let mut schedule = Self::parse(
+            std::env::var("CHECK_INTERVAL").ok().as_deref(),
+            std::env::var("LEADER_LEASE_TTL").ok().as_deref(),
+        )?;
+        schedule.mode = match std::env::var("CHECK_SCHEDULE").as_deref() {
+            Ok("interval") | Err(_) => ScheduleMode::Interval,
+            Ok("due") => ScheduleMode::Due,
+            Ok(other) => return Err(format!("invalid CHECK_SCHEDULE: {other}")),
+        };
+        Ok(schedule)
+    }
+
+    /// A schedule from humantime durations, e.g. `2m`.
+    ///
+    /// The lease TTL defaults to three intervals and must be longer than one:
+    /// the leader renews the lease every cycle, and a replica that stops doing
+    /// so is replaced after the TTL.
+    fn parse(interval: Option<&str>, lease_ttl: Option<&str>) -> Result<Self, String> {
+        let interval = match interval {
+            Some(v) => {
+                humantime::parse_duration(v).map_err(|e| format!("invalid CHECK_INTERVAL: {e}"))?
+            }
+            None => DEFAULT_CHECK_INTERVAL,
+        };
+        if interval.is_zero() {
+            return Err("CHECK_INTERVAL must not be zero".to_string());
+        }
+        let lease_ttl = match lease_ttl {
+            Some(v) => humantime::parse_duration(v)
+                .map_err(|e| format!("invalid LEADER_LEASE_TTL: {e}"))?,
+            None => interval * 3,
+        };
+        if lease_ttl <= interval {
+            return Err("LEADER_LEASE_TTL must be longer than CHECK_INTERVAL".to_string());
+        }
+
+        Ok(Self {
+            interval,
+            lease_ttl,
+            mode: ScheduleMode::Interval,
+        })
+    }
+}
+
+/// Run `checker` cycles on `schedule` until `shutdown` completes, whenever
+/// this replica holds the leader lease as `owner`.
+///
+/// In [`ScheduleMode::Due`], `due_updates` receives the monitors written by
+/// the API in the same process, if any: a new due time is scheduled, and a
+/// ping to an alerted monitor runs a cycle to send its recovery, at most one
+/// every [`MIN_DUE_CYCLE_GAP`] as for due monitors.
+///
+/// A cycle in progress is completed before returning, so alert state isn't
+/// left behind the notifications already sent, and the lease is released
+/// for another replica to take over.
+pub async fn run(
+    checker: Checker,
+    schedule: Schedule,
+    owner: String,
+    mut due_updates: Option<mpsc::Receiver<DueUpdate>>,
+    shutdown: impl Future<Output = ()>,
+) {
+    let mut lease = LeaderLease::new(checker.status_store.clone(), owner, schedule.lease_ttl);
+    let mut queue = DueQueue::new();
+    let mut last_cycle = Instant::now();
+    // An alerted monitor was written, and may have recovered
+    let mut recovery_pending = false;
+
+    info!(
+        check_interval = %humantime::format_duration(schedule.interval),
+        lease_ttl = %humantime::format_duration(schedule.lease_ttl),
+        mode = ?schedule.mode,
+        owner = %lease.owner(),
+        "starting checker loop"
+    );
+
+    let mut ticker = tokio::time::interval(schedule.interval);
+    // After a slow cycle, wait a full interval rather than running back to back
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    tokio::pin!(shutdown);
+
+    loop {
+        // Monitors the cycle is run for because they became due
+        let mut popped = Vec::new();
+        let wake = match schedule.mode {
+            ScheduleMode::Due => {
+                let due = queue
+                    .next_due()
+                    .map(|due| due_instant(due, &checker.clock, last_cycle));
+                let recovery = recovery_pending.then(|| last_cycle + MIN_DUE_CYCLE_GAP);
+                due.into_iter().chain(recovery).min()
+            }
+            ScheduleMode::Interval => None,
+        };
+
+        tokio::select! {
+            _ = &mut shutdown => break,
+            _ = ticker.tick() => {
+                if !lease.acquire(checker.clock.now()).await {
+                    continue;
+                }
+                if schedule.mode == ScheduleMode::Due {
+                    refresh(&checker.store, &checker.clock, &mut queue).await;
+                }
+            }
+            _ = sleep_until(wake), if wake.is_some() => {
+                // Popped even if not the leader, which checks them instead
+                popped = queue.pop_due(checker.clock.now());
+                debug!(due = popped.len(), recovery_pending, "monitors became due");
+                recovery_pending = false;
+                if !lease.acquire(checker.clock.now()).await {
+                    continue;
+                }
+            }
+            update = recv(&mut due_updates) => {
+                let Some(update) = update else {
+                    due_updates = None;
+                    continue;
+                };
+                queue.set(update.key, update.next_due);
+                recovery_pending |= update.alerted;
+                continue;
+            }
+        }
+
+        if let Err(e) = checker.run_cycle(lease.owner()).await {
+            error!(error = %e, "check cycle failed");
+        }
+        last_cycle = Instant::now();
+        recovery_pending = false;
+        reschedule(&checker.store, &checker.clock, &mut queue, &popped).await;
+    }
+
+    lease.release().await;
+    info!("checker loop stopped");
+}
+
+/// Replace the due times in `queue` by those of all monitors not yet due.
+///
+/// Monitors already overdue are left to the cycle following the refresh.
+async fn refresh(store: &MonitorStore, clock: &Clock, queue: &mut DueQueue) {
+    let now = clock.now();
+    match store.list_monitors().await {
+        Ok(monitors) => {
+            queue.replace(
+                monitors
+                    .iter()
+                    .filter(|m| m.paused != Some(true) && m.next_due >= now)
+                    .map(|m| (m.key(), m.next_due)),
+            );
+            debug!(monitors = queue.len(), "refreshed due times");
+        }
+        Err(e) => warn!(error = %e, "failed to refresh due times"),
+    }
+}
+
+/// Queue the monitors in `keys` again with their current due times, as pings
+/// since they were queued may have moved them later.
+///
+/// Monitors still overdue are left to the next interval, as in [`refresh`].
+async fn reschedule(store: &MonitorStore, clock: &Clock, queue: &mut DueQueue, keys: &[String]) {
+    let now = clock.now();
+    for key in keys {
+        let id = match MonitorId::from_key(key) {
+            Ok(id) => id,
+            Err(e) => {
+                warn!(key, error = %e, "invalid monitor key in due queue");
+                continue;
+            }
+        };
+        match store.get_monitor(&id).await {
+            Ok(Some(m)) if m.paused != Some(true) && m.next_due >= now => {
+                queue.set(key.clone(), m.next_due);
+            }
+            Ok(_) => {}
+            Err(e) => warn!(key, error = %e, "failed to reschedule monitor"),
+        }
+    }
+}
+
+/// When to run a cycle for a monitor due at `due`: once it is overdue on
+/// `clock` (the overdue query excludes the due second itself), and not sooner
+/// than [`MIN_DUE_CYCLE_GAP`] after the `last_cycle`.
+fn due_instant(due: i64, clock: &Clock, last_cycle: Instant) -> Instant {
+    let wait = (due + 1 - clock.now()).max(0) as u64;
+    (Instant::now() + Duration::from_secs(wait)).max(last_cycle + MIN_DUE_CYCLE_GAP)
+}
+
+async fn sleep_until(wake: Option<Instant>) {
+    match wake {
+        Some(wake) => tokio::time::sleep_until(wake).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// The next update, or never without a receiver.
+async fn recv(updates: &mut Option<mpsc::Receiver<DueUpdate>>) -> Option<DueUpdate> {
+    match updates {
+        Some(updates) => updates.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use heartbeat_core::{ManualClock, Monitor, StorageConfig, Stores};
+
+    use super::*;
+
+    #[test]
+    fn defaults_to_two_minutes_and_three_intervals() {
+        let schedule = Schedule::parse(None, None).unwrap();
+        assert_eq!(schedule.interval, Duration::from_secs(120));
+        assert_eq!(schedule.lease_ttl, Duration::from_secs(360));
+
+        let schedule = Schedule::parse(Some("30s"), None).unwrap();
+        assert_eq!(schedule.lease_ttl, Duration::from_secs(90));
+    }
+
+    #[test]
+    fn lease_must_outlive_interval() {
+        assert!(Schedule::parse(Some("1m"), Some("1m")).is_err());
+        assert!(Schedule::parse(Some("1m"), Some("90s")).is_ok());
+        assert!(Schedule::parse(Some("0s"), None).is_err());
+        assert!(Schedule::parse(Some("soon"), None).is_err());
+    }
+
+    #[test]
+    fn due_cycles_wait_for_overdue_and_gap() {
+        let clock = Clock::from(ManualClock::new(1000));
+        let start = Instant::now();
+
+        // Overdue one second after the due time
+        let wake = due_instant(1030, &clock, start - MIN_DUE_CYCLE_GAP);
+        assert!(wake >= start + Duration::from_secs(31));
+        assert!(wake <= start + Duration::from_secs(32));
+
+        // Already due, but a cycle just ran
+        assert_eq!(due_instant(990, &clock, start), start + MIN_DUE_CYCLE_GAP);
+    }
+
+    #[tokio::test]
+    async fn reschedule_reads_due_times_again() {
+        let stores = Stores::connect(&StorageConfig::Sql {
+            url: "sqlite::memory:".to_string(),
+        })
+        .await
+        .unwrap();
+        let clock = Clock::from(ManualClock::new(2000));
+        let mut monitor = Monitor {
+            slug: "backup".into(),
+            project: "default".into(),
+            interval_secs: 300,
+            last_ping: 1000,
+            next_due: 1300,
+            check_partition: "CHECK".into(),
+            first_alerted_at: None,
+            last_alerted_at: None,
+            alert_count: None,
+            alert_status: None,
+            created_at: 0,
+            paused: None,
+            expires_at: 0,
+            last_failure: None,
+            last_ping_meta: None,
+            ping_token: None,
+            timezone: None,
+        };
+        stores.monitors.upsert_monitor(&monitor).await.unwrap();
+
+        // Queued with the due time of the ping before last, and popped
+        let mut queue = DueQueue::new();
+        queue.set(monitor.key(), 1300);
+        let popped = queue.pop_due(clock.now());
+
+        // Still overdue: left to the interval
+        reschedule(&stores.monitors, &clock, &mut queue, &popped).await;
+        assert!(queue.is_empty());
+
+        // Pinged elsewhere since: queued again at its new due time
+        monitor.last_ping = 1990;
+        monitor.next_due = 2290;
+        stores.monitors.upsert_monitor(&monitor).await.unwrap();
+        reschedule(&stores.monitors, &clock, &mut queue, &popped).await;
+        assert_eq!(queue.next_due(), Some(2290));
+    }
+}
@@ -0,0 +1,168 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+use heartbeat_core::Monitor;
+use tokio::sync::mpsc;
+use tracing::debug;
+
+/// Pending due-time updates the API may queue before they are dropped; the
+/// periodic refresh catches up on dropped ones.
+pub const DUE_UPDATES_CAPACITY: usize = 1024;
+
+/// A monitor whose due time changed, e.g. on a ping.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DueUpdate {
+    /// Partition key of the monitor, see [`Monitor::key`].
+    pub key: String,
+    /// Unix epoch seconds when the monitor becomes overdue.
+    pub next_due: i64,
+    /// Whether the monitor has an active alert, which may now be recovered.
+    pub alerted: bool,
+}
+
+/// Sends due-time updates from the API to a checker running in the same
+/// process.
+#[derive(Clone)]
+pub struct DueUpdates {
+    sender: mpsc::Sender<DueUpdate>,
+}
+
+impl DueUpdates {
+    /// Create the sender and the receiver to pass to the checker loop.
+    pub fn channel() -> (Self, mpsc::Receiver<DueUpdate>) {
+        let (sender, receiver) = mpsc::channel(DUE_UPDATES_CAPACITY);
+        (Self { sender }, receiver)
+    }
+
+    /// Notify the checker that `monitor` was written, without waiting.
+    pub fn monitor_changed(&self, monitor: &Monitor) {
+        let update = DueUpdate {
+            key: monitor.key(),
+            next_due: monitor.next_due,
+            alerted: monitor.last_alerted_at.is_some(),
+        };
+        if let Err(e) = self.sender.try_send(update) {
+            debug!("dropped due update: {e}");
+        }
+    }
+}
+
+/// Due times of monitors, earliest first.
+///
+/// Updating a monitor leaves its previous entry in the heap; entries that no
+/// longer match the monitor's due time are skipped when reached.
+#[derive(Debug, Default)]
+pub struct DueQueue {
+    heap: BinaryHeap<Reverse<(i64, String)>>,
+    due: HashMap<String, i64>,
+}
+
+impl DueQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of monitors in the queue.
+    pub fn len(&self) -> usize {
+        self.due.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.due.is_empty()
+    }
+
+    /// Set the due time of the monitor `key`, replacing any previous one.
+    pub fn set(&mut self, key: String, next_due: i64) {
+        if self.due.insert(key.clone(), next_due) != Some(next_due) {
+            self.heap.push(Reverse((next_due, key)));
+        }
+    }
+
+    /// Replace the whole queue, e.g. after reading all monitors.
+    pub fn replace(&mut self, entries: impl IntoIterator<Item = (String, i64)>) {
+        self.due = entries.into_iter().collect();
+        self.heap = self
+            .due
+            .iter()
+            .map(|(key, due)| Reverse((*due, key.clone())))
+            .collect();
+    }
+
+    /// The earliest due time, if any.
+    pub fn next_due(&mut self) -> Option<i64> {
+        while let Some(Reverse((due, key))) = self.heap.peek() {
+            if self.due.get(key) == Some(due) {
+                return Some(*due);
+            }
+            self.heap.pop();
+        }
+        None
+    }
+
+    /// Remove the monitors due before `now`, returning their keys.
+    pub fn pop_due(&mut self, now: i64) -> Vec<String> {
+        let mut popped = Vec::new();
+        while let Some(due) = self.next_due() {
+            if due >= now {
+                break;
+            }
+            let Some(Reverse((_, key))) = self.heap.pop() else {
+                break;
+            };
+            self.due.remove(&key);
+            popped.push(key);
+        }
+        popped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn yields_earliest_due_time() {
+        let mut queue = DueQueue::new();
+        queue.set("b".into(), 300);
+        queue.set("a".into(), 100);
+        queue.set("c".into(), 200);
+        assert_eq!(queue.next_due(), Some(100));
+        assert_eq!(queue.len(), 3);
+    }
+
+    #[test]
+    fn update_replaces_previous_due_time() {
+        let mut queue = DueQueue::new();
+        queue.set("a".into(), 100);
+        queue.set("b".into(), 200);
+        // Pinged: due later than b now
+        queue.set("a".into(), 400);
+        assert_eq!(queue.next_due(), Some(200));
+
+        assert_eq!(queue.pop_due(201), ["b"]);
+        assert_eq!(queue.next_due(), Some(400));
+        assert_eq!(queue.len(), 1);
+    }
+
+    #[test]
+    fn pops_only_monitors_due_before_now() {
+        let mut queue = DueQueue::new();
+        queue.set("a".into(), 100);
+        queue.set("b".into(), 100);
+        queue.set("c".into(), 150);
+        assert!(queue.pop_due(100).is_empty());
+        assert_eq!(queue.pop_due(101).len(), 2);
+        assert_eq!(queue.next_due(), Some(150));
+    }
+
+    #[test]
+    fn replace_drops_previous_entries() {
+        let mut queue = DueQueue::new();
+        queue.set("a".into(), 100);
+        queue.replace([("b".to_string(), 300)]);
+        assert_eq!(queue.next_due(), Some(300));
+        assert_eq!(queue.pop_due(1000), ["b"]);
+        assert!(queue.is_empty());
+        assert_eq!(queue.next_due(), None);
+    }
+}
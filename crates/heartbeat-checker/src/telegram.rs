@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::time::Duration;
 
 use serde::Serialize;
@@ -5,12 +6,20 @@ use tracing::{info, warn};
 
 use crate::errors::TelegramError;
 
+/// Base URL of the Telegram Bot API.
+pub const DEFAULT_API_URL: &str = "https://api.telegram.org";
+
 /// Telegram Bot API client for sending alert messages.
+///
+/// Alerts go to the chats configured for the monitor's project, or to the
+/// default chat for projects without their own.
 #[derive(Clone)]
 pub struct TelegramClient {
     http: reqwest::Client,
+    api_url: String,
     bot_token: String,
     chat_id: String,
+    project_chat_ids: HashMap<String, Vec<String>>,
 }
 
 #[derive(Serialize)]
@@ -28,24 +37,66 @@ struct TelegramResponse {
 }
 
 impl TelegramClient {
-    /// Create a new Telegram client.
-    pub fn new(bot_token: String, chat_id: String) -> Self {
+    /// Create a new Telegram client sending to `chat_id` by default and to
+    /// `project_chat_ids` for the listed projects.
+    pub fn new(
+        bot_token: String,
+        chat_id: String,
+        project_chat_ids: HashMap<String, Vec<String>>,
+    ) -> Self {
         Self {
             http: reqwest::Client::new(),
+            api_url: DEFAULT_API_URL.to_string(),
             bot_token,
             chat_id,
+            project_chat_ids,
+        }
+    }
+
+    /// Send messages to the Bot API at `api_url` instead of Telegram's, e.g.
+    /// a mock server during local development.
+    pub fn with_api_url(mut self, api_url: impl Into<String>) -> Self {
+        self.api_url = api_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Create a client from the environment, for running outside Lambda.
+    ///
+    /// Reads `TELEGRAM_BOT_TOKEN`, `TELEGRAM_CHAT_ID` and the optional
+    /// `TELEGRAM_PROJECT_CHAT_IDS`, each either from the variable itself or
+    /// from the file named by the same variable with a `_FILE` suffix, and
+    /// the optional `TELEGRAM_API_URL`.
+    pub fn from_env() -> Result<Self, String> {
+        let bot_token = env_secret("TELEGRAM_BOT_TOKEN")?
+            .ok_or("TELEGRAM_BOT_TOKEN or TELEGRAM_BOT_TOKEN_FILE must be set")?;
+        let chat_id = env_secret("TELEGRAM_CHAT_ID")?
+            .ok_or("TELEGRAM_CHAT_ID or TELEGRAM_CHAT_ID_FILE must be set")?;
+        let project_chat_ids = match env_secret("TELEGRAM_PROJECT_CHAT_IDS")? {
+            Some(json) => parse_project_chat_ids(&json)?,
+            None => HashMap::new(),
+        };
+
+        let client = Self::new(bot_token, chat_id, project_chat_ids);
+        Ok(match std::env::var("TELEGRAM_API_URL") {
+            Ok(api_url) if !api_url.is_empty() => client.with_api_url(api_url),
+            _ => client,
+        })
+    }
+
+    /// The chats receiving alerts for `project`.
+    fn chat_ids(&self, project: &str) -> &[String] {
+        match self.project_chat_ids.get(project) {
+            Some(chat_ids) if !chat_ids.is_empty() => chat_ids,
+            _ => std::slice::from_ref(&self.chat_id),
         }
     }
 
     /// Send a message using MarkdownV2 parse mode.
-    async fn send_message(&self, text: &str) -> Result<(), TelegramError> {
-        let url = format!(
-            "https://api.telegram.org/bot{}/sendMessage",
-            self.bot_token
-        );
+    async fn send_message(&self, chat_id: &str, text: &str) -> Result<(), TelegramError> {
+        let url = format!("{}/bot{}/sendMessage", self.api_url, self.bot_token);
 
         let body = SendMessageRequest {
-            chat_id: &self.chat_id,
+            chat_id,
             text,
             parse_mode: "MarkdownV2",
         };
@@ -65,10 +116,38 @@ impl TelegramClient {
         Ok(())
     }
 
-    /// Send a message with retry (3 attempts with exponential backoff).
+    /// Send an alert for a monitor of `project` to each of the project's chats.
+    ///
+    /// Succeeds if at least one chat received it, so a single broken chat
+    /// doesn't cause the alert to be resent to the others every cycle.
+    pub async fn send_with_retry(&self, project: &str, text: &str) -> Result<(), TelegramError> {
+        let mut delivered = false;
+        let mut last_err = None;
+
+        for chat_id in self.chat_ids(project) {
+            match self.send_to_chat_with_retry(chat_id, text).await {
+                Ok(()) => delivered = true,
+                Err(e) => {
+                    warn!(project, chat_id, error = %e, "failed to send alert to chat");
+                    last_err = Some(e);
+                }
+            }
+        }
+
+        match last_err {
+            Some(e) if !delivered => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Send a message to one chat with retry (3 attempts with exponential backoff).
     ///
     /// Delays: 500ms, 2s, 5s between retries.
-    pub async fn send_with_retry(&self, text: &str) -> Result<(), TelegramError> {
+    async fn send_to_chat_with_retry(
+        &self,
+        chat_id: &str,
+        text: &str,
+    ) -> Result<(), TelegramError> {
         let delays = [
             Duration::from_millis(500),
             Duration::from_secs(2),
@@ -86,7 +165,7 @@ impl TelegramClient {
                 tokio::time::sleep(*delay).await;
             }
 
-            match self.send_message(text).await {
+            match self.send_message(chat_id, text).await {
                 Ok(()) => {
                     if attempt > 0 {
                         info!(attempt, "Telegram send succeeded after retry");
@@ -102,3 +181,95 @@ impl TelegramClient {
         Err(last_err.expect("at least one attempt was made"))
     }
 }
+
+/// Read the secret `name` from the environment variable `name`, or from the
+/// file named by `{name}_FILE` (e.g. a mounted Kubernetes or Docker secret).
+pub(crate) fn env_secret(name: &str) -> Result<Option<String>, String> {
+    let file_var = format!("{name}_FILE");
+    secret_value(
+        name,
+        std::env::var(name).ok(),
+        std::env::var(&file_var).ok(),
+    )
+}
+
+/// The secret `name` given as a `value` or a `file` path, but not both.
+///
+/// Surrounding whitespace, such as a trailing newline in a file, is removed.
+fn secret_value(
+    name: &str,
+    value: Option<String>,
+    file: Option<String>,
+) -> Result<Option<String>, String> {
+    let value = match (value, file) {
+        (Some(_), Some(_)) => return Err(format!("set only one of {name} and {name}_FILE")),
+        (Some(value), None) => value,
+        (None, Some(path)) => std::fs::read_to_string(&path)
+            .map_err(|e| format!("failed to read {name}_FILE {path}: {e}"))?,
+        (None, None) => return Ok(None),
+    };
+
+    let value = value.trim();
+    if value.is_empty() {
+        return Err(format!("{name} is empty"));
+    }
+    Ok(Some(value.to_string()))
+}
+
+/// Parse the per-project chat configuration: a JSON object mapping project
+/// names to lists of chat IDs, e.g. `{"team-a": ["-1001234567890"]}`.
+pub fn parse_project_chat_ids(json: &str) -> Result<HashMap<String, Vec<String>>, String> {
+    serde_json::from_str(json).map_err(|e| format!("invalid project chat IDs: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client() -> TelegramClient {
+        let project_chat_ids =
+            parse_project_chat_ids(r#"{"team-a": ["-1", "-2"], "team-b": []}"#).unwrap();
+        TelegramClient::new("token".into(), "-100".into(), project_chat_ids)
+    }
+
+    #[test]
+    fn routes_to_project_chats() {
+        assert_eq!(client().chat_ids("team-a"), ["-1", "-2"]);
+    }
+
+    #[test]
+    fn falls_back_to_default_chat() {
+        assert_eq!(client().chat_ids("default"), ["-100"]);
+        assert_eq!(client().chat_ids("team-b"), ["-100"]);
+    }
+
+    #[test]
+    fn reads_secret_from_value_or_file() {
+        assert_eq!(
+            secret_value("TOKEN", Some("abc\n".into()), None),
+            Ok(Some("abc".into()))
+        );
+        assert_eq!(secret_value("TOKEN", None, None), Ok(None));
+
+        let path = std::env::temp_dir().join(format!("heartbeat-secret-{}", std::process::id()));
+        std::fs::write(&path, "from-file\n").unwrap();
+        let file = Some(path.to_string_lossy().into_owned());
+        assert_eq!(
+            secret_value("TOKEN", None, file.clone()),
+            Ok(Some("from-file".into()))
+        );
+        std::fs::remove_file(&path).unwrap();
+        assert!(secret_value("TOKEN", None, file).is_err());
+    }
+
+    #[test]
+    fn rejects_ambiguous_or_empty_secret() {
+        assert!(secret_value("TOKEN", Some("a".into()), Some("/path".into())).is_err());
+        assert!(secret_value("TOKEN", Some(" \n".into()), None).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_config() {
+        assert!(parse_project_chat_ids(r#"{"team-a": "-1"}"#).is_err());
+    }
+}
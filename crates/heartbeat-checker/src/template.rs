@@ -0,0 +1,244 @@
+//! Handlebars-style message templates, rendered as Telegram MarkdownV2.
+//!
+//! A template is plain text with `{{name}}` placeholders and
+//! `{{#if name}}...{{/if}}` sections, shown only when the variable is set.
+//! Text between backticks is a code span, and text between triple backticks a
+//! code block, as in MarkdownV2.
+//!
+//! Both the template text and the substituted values are escaped on
+//! rendering, so the message stays valid whatever the values contain:
+//! special characters are escaped outside code, backticks and backslashes
+//! inside. Text already escaped in the template, such as `\(`, is kept.
+
+use std::collections::BTreeMap;
+
+/// Characters that must be escaped in MarkdownV2 (outside of code spans).
+const MARKDOWN_V2_SPECIAL: &[char] = &[
+    '_', '*', '[', ']', '(', ')', '~', '`', '>', '#', '+', '-', '=', '|', '{', '}', '.', '!',
+];
+
+/// Values of the variables of a template. Variables without a value render
+/// as empty and hide their `{{#if}}` sections.
+pub type Vars = BTreeMap<&'static str, String>;
+
+/// A parsed message template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Text(String),
+    Var(String),
+    If(String, Vec<Node>),
+}
+
+impl Template {
+    /// Parse `source`, which may only use the variables in `allowed`.
+    pub fn parse(source: &str, allowed: &[&str]) -> Result<Self, String> {
+        let check = |name: &str| {
+            if allowed.contains(&name) {
+                Ok(name.to_string())
+            } else {
+                Err(format!(
+                    "unknown variable `{name}`, expected one of: {}",
+                    allowed.join(", ")
+                ))
+            }
+        };
+
+        // Sections being parsed, innermost last, below the top level
+        let mut stack: Vec<(String, Vec<Node>)> = Vec::new();
+        let mut nodes = Vec::new();
+        let mut rest = source;
+
+        while let Some(start) = rest.find("{{") {
+            if start > 0 {
+                nodes.push(Node::Text(rest[..start].to_string()));
+            }
+            let after = &rest[start + 2..];
+            let end = after
+                .find("}}")
+                .ok_or_else(|| format!("unclosed `{{{{` in `{}`", &rest[start..]))?;
+            let tag = after[..end].trim();
+            rest = &after[end + 2..];
+
+            if let Some(name) = tag.strip_prefix("#if ") {
+                stack.push((check(name.trim())?, std::mem::take(&mut nodes)));
+            } else if tag == "/if" {
+                let (name, outer) = stack.pop().ok_or("`{{/if}}` without `{{#if}}`")?;
+                let section = std::mem::replace(&mut nodes, outer);
+                nodes.push(Node::If(name, section));
+            } else {
+                nodes.push(Node::Var(check(tag)?));
+            }
+        }
+        if !rest.is_empty() {
+            nodes.push(Node::Text(rest.to_string()));
+        }
+
+        match stack.last() {
+            Some((name, _)) => Err(format!("`{{{{#if {name}}}}}` without `{{{{/if}}}}`")),
+            None => Ok(Self { nodes }),
+        }
+    }
+
+    /// Render the template with `vars` as MarkdownV2.
+    pub fn render(&self, vars: &Vars) -> String {
+        let mut renderer = Renderer::default();
+        renderer.nodes(&self.nodes, vars);
+        renderer.out
+    }
+}
+
+/// MarkdownV2 output, tracking whether it is inside code.
+#[derive(Default)]
+struct Renderer {
+    out: String,
+    in_code: bool,
+}
+
+impl Renderer {
+    fn nodes(&mut self, nodes: &[Node], vars: &Vars) {
+        for node in nodes {
+            match node {
+                Node::Text(text) => self.text(text),
+                Node::Var(name) => {
+                    if let Some(value) = vars.get(name.as_str()) {
+                        let escaped = if self.in_code {
+                            escape_code(value)
+                        } else {
+                            escape_text(value)
+                        };
+                        self.out.push_str(&escaped);
+                    }
+                }
+                Node::If(name, section) => {
+                    if vars.get(name.as_str()).is_some_and(|v| !v.is_empty()) {
+                        self.nodes(section, vars);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Template text: backticks open and close code, and special characters
+    /// outside of it are escaped unless already escaped with a backslash.
+    /// Backslashes inside code, and at the end of the text (where they would
+    /// escape the next value), are escaped.
+    fn text(&mut self, text: &str) {
+        let mut chars = text.chars();
+        while let Some(ch) = chars.next() {
+            if ch == '`' {
+                self.in_code = !self.in_code;
+            } else if ch == '\\'
+                && !self.in_code
+                && let Some(next) = chars.next()
+            {
+                // Already escaped -- pass through as-is
+                self.out.push(ch);
+                self.out.push(next);
+                continue;
+            } else if ch == '\\' || (!self.in_code && MARKDOWN_V2_SPECIAL.contains(&ch)) {
+                self.out.push('\\');
+            }
+            self.out.push(ch);
+        }
+    }
+}
+
+/// Escape arbitrary user-supplied text for use outside code spans.
+///
+/// Backticks and backslashes are escaped too, since the text is not trusted
+/// to contain valid MarkdownV2.
+pub fn escape_text(text: &str) -> String {
+    let mut result = String::with_capacity(text.len() * 2);
+    for ch in text.chars() {
+        if ch == '\\' || MARKDOWN_V2_SPECIAL.contains(&ch) {
+            result.push('\\');
+        }
+        result.push(ch);
+    }
+    result
+}
+
+/// Escape arbitrary user-supplied text for use inside a code span or block.
+///
+/// In MarkdownV2 only backticks and backslashes need escaping there.
+pub fn escape_code(text: &str) -> String {
+    let mut result = String::with_capacity(text.len() * 2);
+    for ch in text.chars() {
+        if ch == '`' || ch == '\\' {
+            result.push('\\');
+        }
+        result.push(ch);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(source: &str, vars: &[(&'static str, &str)]) -> String {
+        let names: Vec<&str> = vars.iter().map(|(name, _)| *name).collect();
+        let vars = vars.iter().map(|(k, v)| (*k, v.to_string())).collect();
+        Template::parse(source, &names).unwrap().render(&vars)
+    }
+
+    #[test]
+    fn escapes_text_outside_code_spans() {
+        // Inside backticks: my-slug should NOT be escaped
+        // Outside backticks: the dot should be escaped
+        assert_eq!(
+            render("hello `my-slug` world.end", &[]),
+            "hello `my-slug` world\\.end"
+        );
+        // Escapes in the template are kept
+        assert_eq!(render("\\(was down\\)", &[]), "\\(was down\\)");
+        // A backslash before a value doesn't escape the value's first character
+        assert_eq!(
+            render("path\\{{slug}}", &[("slug", "a.b")]),
+            "path\\\\a\\.b"
+        );
+        // Backslashes in code are escaped
+        assert_eq!(render("`C:\\dir` ok", &[]), "`C:\\\\dir` ok");
+    }
+
+    #[test]
+    fn escapes_values_by_context() {
+        let vars = [("slug", "my_job`x"), ("message", "disk (98%) full")];
+        assert_eq!(
+            render("`{{slug}}` {{ message }}", &vars),
+            "`my_job\\`x` disk \\(98%\\) full"
+        );
+        assert_eq!(
+            render("```\n{{message}}\n``` {{slug}}", &vars),
+            "```\ndisk (98%) full\n``` my\\_job\\`x"
+        );
+    }
+
+    #[test]
+    fn if_sections_need_a_value() {
+        let source = "FAILED{{#if code}} | exit code: {{code}}{{/if}}.";
+        assert_eq!(
+            render(source, &[("code", "2")]),
+            "FAILED \\| exit code: 2\\."
+        );
+        assert_eq!(render(source, &[("code", "")]), "FAILED\\.");
+
+        let template = Template::parse(source, &["code"]).unwrap();
+        assert_eq!(template.render(&Vars::new()), "FAILED\\.");
+    }
+
+    #[test]
+    fn rejects_invalid_templates() {
+        assert!(Template::parse("{{slug}", &["slug"]).is_err());
+        assert!(Template::parse("{{nope}}", &["slug"]).is_err());
+        assert!(Template::parse("{{#if slug}}x", &["slug"]).is_err());
+        assert!(Template::parse("x{{/if}}", &["slug"]).is_err());
+        assert!(Template::parse("{{#if nope}}x{{/if}}", &["slug"]).is_err());
+        assert!(Template::parse("{{#if slug}}{{slug}}{{/if}}", &["slug"]).is_ok());
+    }
+}
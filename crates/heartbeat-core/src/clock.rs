@@ -0,0 +1,104 @@
+//! The time source of the API handlers and the checker.
+//!
+//! Time-dependent behaviour (overdue detection, repeat alerts, downtime, key
+//! expiry) reads the time from a [`Clock`] rather than from `Utc::now()`, so
+//! tests can move time forward instead of sleeping.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI64, Ordering};
+
+use chrono::Utc;
+
+/// Source of the current time, in seconds since the Unix epoch.
+#[derive(Debug, Clone, Default)]
+pub enum Clock {
+    /// The system clock.
+    #[default]
+    System,
+    /// A clock that only moves when told to.
+    Manual(ManualClock),
+}
+
+impl Clock {
+    /// The current time in seconds since the Unix epoch.
+    pub fn now(&self) -> i64 {
+        match self {
+            Clock::System => Utc::now().timestamp(),
+            Clock::Manual(clock) => clock.now(),
+        }
+    }
+
+    /// The current time in milliseconds since the Unix epoch.
+    pub fn now_millis(&self) -> i64 {
+        match self {
+            Clock::System => Utc::now().timestamp_millis(),
+            Clock::Manual(clock) => clock.now() * 1000,
+        }
+    }
+}
+
+impl From<ManualClock> for Clock {
+    fn from(clock: ManualClock) -> Self {
+        Clock::Manual(clock)
+    }
+}
+
+/// A clock set and advanced by hand, e.g. by a test simulating hours of
+/// checker cycles.
+///
+/// Clones share the same time, so the test keeps one to move the time seen by
+/// the code under test.
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    now: Arc<AtomicI64>,
+}
+
+impl ManualClock {
+    /// A clock stopped at `now_epoch`.
+    pub fn new(now_epoch: i64) -> Self {
+        Self {
+            now: Arc::new(AtomicI64::new(now_epoch)),
+        }
+    }
+
+    /// The time the clock is at.
+    pub fn now(&self) -> i64 {
+        self.now.load(Ordering::SeqCst)
+    }
+
+    /// Set the clock to `now_epoch`.
+    pub fn set(&self, now_epoch: i64) {
+        self.now.store(now_epoch, Ordering::SeqCst);
+    }
+
+    /// Move the clock forward by `secs` seconds, returning the new time.
+    pub fn advance(&self, secs: i64) -> i64 {
+        self.now.fetch_add(secs, Ordering::SeqCst) + secs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manual_clock_is_shared_by_clones() {
+        let manual = ManualClock::new(1000);
+        let clock = Clock::from(manual.clone());
+        assert_eq!(clock.now(), 1000);
+        assert_eq!(clock.now_millis(), 1_000_000);
+
+        assert_eq!(manual.advance(300), 1300);
+        assert_eq!(clock.now(), 1300);
+
+        manual.set(50);
+        assert_eq!(clock.now(), 50);
+    }
+
+    #[test]
+    fn system_clock_follows_utc() {
+        let before = Utc::now().timestamp();
+        let now = Clock::System.now();
+        assert!(now >= before && now <= Utc::now().timestamp());
+    }
+}
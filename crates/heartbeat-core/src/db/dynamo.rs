@@ -0,0 +1,1107 @@
+use std::collections::HashMap;
+
+use aws_config::BehaviorVersion;
+use aws_sdk_dynamodb::Client;
+use aws_sdk_dynamodb::error::BuildError;
+use aws_sdk_dynamodb::types::{
+    AttributeDefinition, AttributeValue, BillingMode, GlobalSecondaryIndex, KeySchemaElement,
+    KeyType, Projection, ProjectionType, ReturnValue, ScalarAttributeType, TimeToLiveSpecification,
+};
+
+use super::DynamoTables;
+use crate::error::CoreError;
+use crate::metrics::DynamoMetrics;
+use crate::model::{
+    ApiKeyRecord, AuditEntry, CHECKER_COMPONENT, CheckerHeartbeat, Monitor, MonitorId,
+    MonitorStatus, alert_sent_key, audit_event_prefix, split_monitor_key,
+};
+
+/// DynamoDB client wrapper for heartbeat monitor storage.
+#[derive(Clone)]
+pub struct DynamoStore {
+    client: Client,
+    table_name: String,
+}
+
+impl DynamoStore {
+    /// Create a new `DynamoStore` by loading AWS configuration from the
+    /// environment and constructing a DynamoDB client. Calls made with the
+    /// client are recorded in the [`metrics`](crate::metrics).
+    ///
+    /// `endpoint_url` overrides the DynamoDB endpoint, e.g. to use DynamoDB
+    /// Local at `http://localhost:8000`.
+    pub async fn new(table_name: impl Into<String>, endpoint_url: Option<&str>) -> Self {
+        let config = aws_config::defaults(BehaviorVersion::latest()).load().await;
+        let mut builder =
+            aws_sdk_dynamodb::config::Builder::from(&config).interceptor(DynamoMetrics);
+        if let Some(endpoint_url) = endpoint_url {
+            builder = builder.endpoint_url(endpoint_url);
+        }
+        let client = Client::from_conf(builder.build());
+        Self {
+            client,
+            table_name: table_name.into(),
+        }
+    }
+
+    /// The DynamoDB table name.
+    pub fn table_name(&self) -> &str {
+        &self.table_name
+    }
+
+    /// The underlying DynamoDB client, for direct use in later phases.
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Check that the table exists and is reachable.
+    pub async fn check_table(&self) -> Result<(), CoreError> {
+        describe_table(&self.client, &self.table_name).await
+    }
+
+    /// Create the `tables` that don't exist yet, with the keys, indexes and
+    /// TTL attributes of `terraform/dynamodb.tf`, for local development
+    /// against DynamoDB Local.
+    ///
+    /// Returns the names of the tables created.
+    pub async fn create_tables(&self, tables: &DynamoTables) -> Result<Vec<String>, CoreError> {
+        let specs = table_specs(tables).map_err(|e| CoreError::DynamoSdk(Box::new(e)))?;
+
+        let mut created = Vec::new();
+        for spec in specs {
+            let result = self
+                .client
+                .create_table()
+                .table_name(&spec.name)
+                .billing_mode(BillingMode::PayPerRequest)
+                .set_key_schema(Some(spec.keys))
+                .set_attribute_definitions(Some(spec.attributes))
+                .set_global_secondary_indexes((!spec.indexes.is_empty()).then_some(spec.indexes))
+                .send()
+                .await;
+
+            if let Err(e) = result {
+                let service_err = e.into_service_error();
+                // Already created
+                if service_err.is_resource_in_use_exception() {
+                    continue;
+                }
+                return Err(CoreError::DynamoSdk(Box::new(service_err)));
+            }
+
+            if spec.ttl {
+                let ttl = TimeToLiveSpecification::builder()
+                    .attribute_name("expires_at")
+                    .enabled(true)
+                    .build()
+                    .map_err(|e| CoreError::DynamoSdk(Box::new(e)))?;
+                self.client
+                    .update_time_to_live()
+                    .table_name(&spec.name)
+                    .time_to_live_specification(ttl)
+                    .send()
+                    .await
+                    .map_err(|e| CoreError::DynamoSdk(Box::new(e)))?;
+            }
+            created.push(spec.name);
+        }
+
+        Ok(created)
+    }
+
+    /// Upsert a monitor into DynamoDB using `update_item`.
+    ///
+    /// Uses `if_not_exists` for `created_at` and `ping_token` so the original
+    /// creation timestamp and ping token are preserved on subsequent pings,
+    /// and a ping token is generated for new monitors. `last_ping_meta` is
+    /// replaced when given and removed otherwise, and `last_failure` is
+    /// removed: the ping resolves it. Returns the monitor as stored after
+    /// the update.
+    pub async fn upsert_monitor(&self, monitor: &Monitor) -> Result<Monitor, CoreError> {
+        let mut set_optional = String::new();
+        let mut remove = vec!["last_failure"];
+        match monitor.last_ping_meta {
+            Some(_) => set_optional.push_str(", last_ping_meta = :meta"),
+            None => remove.push("last_ping_meta"),
+        }
+        match monitor.timezone {
+            Some(_) => set_optional.push_str(", #tz = :tz"),
+            None => remove.push("#tz"),
+        }
+        let remove_expression = format!(" REMOVE {}", remove.join(", "));
+
+        let mut request = self
+            .client
+            .update_item()
+            .table_name(&self.table_name)
+            .key("slug", AttributeValue::S(monitor.key()))
+            .update_expression(format!(
+                "SET interval_secs = :interval, \
+                 last_ping = :last_ping, \
+                 next_due = :next_due, \
+                 check_partition = :cp, \
+                 expires_at = :expires, \
+                 created_at = if_not_exists(created_at, :created_at), \
+                 ping_token = if_not_exists(ping_token, :token)\
+                 {set_optional}{remove_expression}"
+            ))
+            .expression_attribute_names("#tz", "timezone")
+            .expression_attribute_values(
+                ":interval",
+                AttributeValue::N(monitor.interval_secs.to_string()),
+            )
+            .expression_attribute_values(
+                ":last_ping",
+                AttributeValue::N(monitor.last_ping.to_string()),
+            )
+            .expression_attribute_values(
+                ":next_due",
+                AttributeValue::N(monitor.next_due.to_string()),
+            )
+            .expression_attribute_values(":cp", AttributeValue::S(monitor.check_partition.clone()))
+            .expression_attribute_values(
+                ":expires",
+                AttributeValue::N(monitor.expires_at.to_string()),
+            )
+            .expression_attribute_values(
+                ":created_at",
+                AttributeValue::N(monitor.created_at.to_string()),
+            )
+            .expression_attribute_values(":token", AttributeValue::S(new_ping_token()));
+
+        if let Some(meta) = &monitor.last_ping_meta {
+            request = request
+                .expression_attribute_values(":meta", serde_dynamo::to_attribute_value(meta)?);
+        }
+        if let Some(timezone) = &monitor.timezone {
+            request =
+                request.expression_attribute_values(":tz", AttributeValue::S(timezone.clone()));
+        }
+
+        let result = request
+            .return_values(ReturnValue::AllNew)
+            .send()
+            .await
+            .map_err(|e| CoreError::DynamoSdk(Box::new(e)))?;
+
+        Ok(monitor_from_item(result.attributes.unwrap_or_default())?)
+    }
+
+    /// Record an explicit failure for a monitor using `update_item`.
+    ///
+    /// Stores `monitor.last_failure` and the new `next_due`, but uses
+    /// `if_not_exists` for `last_ping`, `created_at` and `ping_token` so the
+    /// last successful ping is preserved when the monitor already exists.
+    /// Returns the monitor as stored after the update.
+    pub async fn record_failure(&self, monitor: &Monitor) -> Result<Monitor, CoreError> {
+        let failure: AttributeValue = serde_dynamo::to_attribute_value(&monitor.last_failure)?;
+
+        let result = self
+            .client
+            .update_item()
+            .table_name(&self.table_name)
+            .key("slug", AttributeValue::S(monitor.key()))
+            .update_expression(
+                "SET interval_secs = :interval, \
+                 last_ping = if_not_exists(last_ping, :last_ping), \
+                 next_due = :next_due, \
+                 check_partition = :cp, \
+                 expires_at = :expires, \
+                 last_failure = :failure, \
+                 created_at = if_not_exists(created_at, :created_at), \
+                 ping_token = if_not_exists(ping_token, :token)",
+            )
+            .expression_attribute_values(
+                ":interval",
+                AttributeValue::N(monitor.interval_secs.to_string()),
+            )
+            .expression_attribute_values(
+                ":last_ping",
+                AttributeValue::N(monitor.last_ping.to_string()),
+            )
+            .expression_attribute_values(
+                ":next_due",
+                AttributeValue::N(monitor.next_due.to_string()),
+            )
+            .expression_attribute_values(":cp", AttributeValue::S(monitor.check_partition.clone()))
+            .expression_attribute_values(
+                ":expires",
+                AttributeValue::N(monitor.expires_at.to_string()),
+            )
+            .expression_attribute_values(":failure", failure)
+            .expression_attribute_values(
+                ":created_at",
+                AttributeValue::N(monitor.created_at.to_string()),
+            )
+            .expression_attribute_values(":token", AttributeValue::S(new_ping_token()))
+            .return_values(ReturnValue::AllNew)
+            .send()
+            .await
+            .map_err(|e| CoreError::DynamoSdk(Box::new(e)))?;
+
+        Ok(monitor_from_item(result.attributes.unwrap_or_default())?)
+    }
+
+    /// Get a monitor by project and slug.
+    ///
+    /// Returns `None` if the monitor does not exist.
+    pub async fn get_monitor(&self, id: &MonitorId) -> Result<Option<Monitor>, CoreError> {
+        let result = self
+            .client
+            .get_item()
+            .table_name(&self.table_name)
+            .key("slug", AttributeValue::S(id.key()))
+            .send()
+            .await
+            .map_err(|e| CoreError::DynamoSdk(Box::new(e)))?;
+
+        match result.item {
+            Some(item) => Ok(Some(monitor_from_item(item)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Find the monitor owning a ping token.
+    ///
+    /// Uses the `ping-token-index` GSI (keys only). Since GSIs are eventually
+    /// consistent, a rotated token may keep resolving for a brief moment.
+    pub async fn find_monitor_by_ping_token(
+        &self,
+        token: &str,
+    ) -> Result<Option<MonitorId>, CoreError> {
+        let result = self
+            .client
+            .query()
+            .table_name(&self.table_name)
+            .index_name("ping-token-index")
+            .key_condition_expression("ping_token = :token")
+            .expression_attribute_values(":token", AttributeValue::S(token.to_string()))
+            .limit(1)
+            .send()
+            .await
+            .map_err(|e| CoreError::DynamoSdk(Box::new(e)))?;
+
+        match result.items().first().and_then(|item| item.get("slug")) {
+            Some(AttributeValue::S(key)) => Ok(Some(MonitorId::from_key(key)?)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Replace the ping token of a monitor with a newly generated one.
+    ///
+    /// Returns the new token, or `CoreError::NotFound` if the monitor does not exist.
+    pub async fn rotate_ping_token(&self, id: &MonitorId) -> Result<String, CoreError> {
+        let token = new_ping_token();
+
+        let result = self
+            .client
+            .update_item()
+            .table_name(&self.table_name)
+            .key("slug", AttributeValue::S(id.key()))
+            .update_expression("SET ping_token = :token")
+            .expression_attribute_values(":token", AttributeValue::S(token.clone()))
+            .condition_expression("attribute_exists(slug)")
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => Ok(token),
+            Err(e) => {
+                let service_err = e.into_service_error();
+                if service_err.is_conditional_check_failed_exception() {
+                    Err(CoreError::NotFound(id.slug.to_string()))
+                } else {
+                    Err(CoreError::DynamoSdk(Box::new(service_err)))
+                }
+            }
+        }
+    }
+
+    /// Query all monitors that are overdue as of `now_epoch`.
+    ///
+    /// Uses the `overdue-check-index` GSI with partition key `check_partition = "CHECK"`
+    /// and sort key `next_due < now_epoch`.
+    pub async fn query_overdue(&self, now_epoch: i64) -> Result<Vec<Monitor>, CoreError> {
+        let result = self
+            .client
+            .query()
+            .table_name(&self.table_name)
+            .index_name("overdue-check-index")
+            .key_condition_expression("check_partition = :cp AND next_due < :now")
+            .expression_attribute_values(":cp", AttributeValue::S("CHECK".to_string()))
+            .expression_attribute_values(":now", AttributeValue::N(now_epoch.to_string()))
+            .send()
+            .await
+            .map_err(|e| CoreError::DynamoSdk(Box::new(e)))?;
+
+        Ok(monitors_from_items(result.items)?)
+    }
+
+    /// Query all monitors that currently have an active alert (last_alerted_at exists).
+    ///
+    /// Uses a table scan with a filter expression since there is no GSI for this.
+    pub async fn query_alerted(&self) -> Result<Vec<Monitor>, CoreError> {
+        let result = self
+            .client
+            .scan()
+            .table_name(&self.table_name)
+            .filter_expression("attribute_exists(last_alerted_at)")
+            .send()
+            .await
+            .map_err(|e| CoreError::DynamoSdk(Box::new(e)))?;
+
+        Ok(monitors_from_items(result.items)?)
+    }
+
+    /// Update the alert state for a monitor after sending an alert.
+    ///
+    /// Sets `first_alerted_at`, `last_alerted_at`, `alert_count` and
+    /// `alert_status` on the monitor with partition key `key` (see [`Monitor::key`]).
+    pub async fn update_alert_state(
+        &self,
+        key: &str,
+        first_alert: i64,
+        now_epoch: i64,
+        alert_count: u32,
+        status: MonitorStatus,
+    ) -> Result<(), CoreError> {
+        let status: AttributeValue = serde_dynamo::to_attribute_value(status)?;
+
+        self.client
+            .update_item()
+            .table_name(&self.table_name)
+            .key("slug", AttributeValue::S(key.to_string()))
+            .update_expression("SET first_alerted_at = :first, last_alerted_at = :now, alert_count = :count, alert_status = :status")
+            .expression_attribute_values(":first", AttributeValue::N(first_alert.to_string()))
+            .expression_attribute_values(":now", AttributeValue::N(now_epoch.to_string()))
+            .expression_attribute_values(":count", AttributeValue::N(alert_count.to_string()))
+            .expression_attribute_values(":status", status)
+            .send()
+            .await
+            .map_err(|e| CoreError::DynamoSdk(Box::new(e)))?;
+
+        Ok(())
+    }
+
+    /// Clear the alert state for a monitor after it recovers.
+    ///
+    /// Removes `last_alerted_at`, `alert_count` and `alert_status` from the monitor
+    /// with partition key `key` (see [`Monitor::key`]).
+    pub async fn clear_alert_state(&self, key: &str) -> Result<(), CoreError> {
+        self.client
+            .update_item()
+            .table_name(&self.table_name)
+            .key("slug", AttributeValue::S(key.to_string()))
+            .update_expression("REMOVE last_alerted_at, alert_count, alert_status")
+            .send()
+            .await
+            .map_err(|e| CoreError::DynamoSdk(Box::new(e)))?;
+
+        Ok(())
+    }
+
+    /// List all monitors in the table, across all projects.
+    ///
+    /// Uses a full table scan, reading every page.
+    pub async fn list_monitors(&self) -> Result<Vec<Monitor>, CoreError> {
+        let mut monitors = Vec::new();
+        let mut start_key = None;
+
+        // Scan all pages: metrics and due scheduling need every monitor
+        loop {
+            let result = self
+                .client
+                .scan()
+                .table_name(&self.table_name)
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await
+                .map_err(|e| CoreError::DynamoSdk(Box::new(e)))?;
+
+            monitors.extend(monitors_from_items(result.items)?);
+
+            start_key = result.last_evaluated_key;
+            if start_key.is_none() {
+                return Ok(monitors);
+            }
+        }
+    }
+
+    /// Delete a monitor by project and slug.
+    ///
+    /// Returns `CoreError::NotFound` if the monitor does not exist.
+    pub async fn delete_monitor(&self, id: &MonitorId) -> Result<(), CoreError> {
+        let result = self
+            .client
+            .delete_item()
+            .table_name(&self.table_name)
+            .key("slug", AttributeValue::S(id.key()))
+            .condition_expression("attribute_exists(slug)")
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                let service_err = e.into_service_error();
+                if service_err.is_conditional_check_failed_exception() {
+                    Err(CoreError::NotFound(id.slug.to_string()))
+                } else {
+                    Err(CoreError::DynamoSdk(Box::new(service_err)))
+                }
+            }
+        }
+    }
+
+    /// Set or clear the paused state for a monitor.
+    ///
+    /// When pausing (`paused = true`), also clears `last_alerted_at`, `alert_count`
+    /// and `alert_status`.
+    /// Returns `CoreError::NotFound` if the monitor does not exist.
+    pub async fn set_paused(&self, id: &MonitorId, paused: bool) -> Result<(), CoreError> {
+        let result = if paused {
+            self.client
+                .update_item()
+                .table_name(&self.table_name)
+                .key("slug", AttributeValue::S(id.key()))
+                .update_expression(
+                    "SET paused = :val REMOVE last_alerted_at, alert_count, alert_status",
+                )
+                .expression_attribute_values(":val", AttributeValue::Bool(true))
+                .condition_expression("attribute_exists(slug)")
+                .send()
+                .await
+        } else {
+            self.client
+                .update_item()
+                .table_name(&self.table_name)
+                .key("slug", AttributeValue::S(id.key()))
+                .update_expression("REMOVE paused")
+                .condition_expression("attribute_exists(slug)")
+                .send()
+                .await
+        };
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                let service_err = e.into_service_error();
+                if service_err.is_conditional_check_failed_exception() {
+                    Err(CoreError::NotFound(id.slug.to_string()))
+                } else {
+                    Err(CoreError::DynamoSdk(Box::new(service_err)))
+                }
+            }
+        }
+    }
+}
+
+/// DynamoDB client wrapper for API key storage.
+#[derive(Clone)]
+pub struct DynamoApiKeyStore {
+    client: Client,
+    table_name: String,
+}
+
+impl DynamoApiKeyStore {
+    /// Create a new `DynamoApiKeyStore` sharing an existing DynamoDB client.
+    pub fn new(client: Client, table_name: impl Into<String>) -> Self {
+        Self {
+            client,
+            table_name: table_name.into(),
+        }
+    }
+
+    /// The DynamoDB table name.
+    pub fn table_name(&self) -> &str {
+        &self.table_name
+    }
+
+    /// Check that the table exists and is reachable.
+    pub async fn check_table(&self) -> Result<(), CoreError> {
+        describe_table(&self.client, &self.table_name).await
+    }
+
+    /// Get an API key by its hash (or plaintext token, for unmigrated keys).
+    ///
+    /// Returns `None` if the key does not exist.
+    pub async fn get_key(&self, key_hash: &str) -> Result<Option<ApiKeyRecord>, CoreError> {
+        let result = self
+            .client
+            .get_item()
+            .table_name(&self.table_name)
+            .key("api_key", AttributeValue::S(key_hash.to_string()))
+            .send()
+            .await
+            .map_err(|e| CoreError::DynamoSdk(Box::new(e)))?;
+
+        match result.item {
+            Some(item) => {
+                let record: ApiKeyRecord = serde_dynamo::from_item(item)?;
+                Ok(Some(record))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Store a new API key.
+    pub async fn put_key(&self, record: &ApiKeyRecord) -> Result<(), CoreError> {
+        let item = serde_dynamo::to_item(record)?;
+
+        self.client
+            .put_item()
+            .table_name(&self.table_name)
+            .set_item(Some(item))
+            .send()
+            .await
+            .map_err(|e| CoreError::DynamoSdk(Box::new(e)))?;
+
+        Ok(())
+    }
+
+    /// Delete an API key by its hash (or plaintext token, for unmigrated keys).
+    pub async fn delete_key(&self, key_hash: &str) -> Result<(), CoreError> {
+        self.client
+            .delete_item()
+            .table_name(&self.table_name)
+            .key("api_key", AttributeValue::S(key_hash.to_string()))
+            .send()
+            .await
+            .map_err(|e| CoreError::DynamoSdk(Box::new(e)))?;
+
+        Ok(())
+    }
+
+    /// List all API keys in the table.
+    ///
+    /// Uses a full table scan, reading every page.
+    pub async fn list_keys(&self) -> Result<Vec<ApiKeyRecord>, CoreError> {
+        let mut keys = Vec::new();
+        let mut start_key = None;
+
+        // Scan all pages: keys are looked up here to be revoked or rotated
+        loop {
+            let result = self
+                .client
+                .scan()
+                .table_name(&self.table_name)
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await
+                .map_err(|e| CoreError::DynamoSdk(Box::new(e)))?;
+
+            let page: Vec<ApiKeyRecord> =
+                serde_dynamo::from_items(result.items.unwrap_or_default())?;
+            keys.extend(page);
+
+            start_key = result.last_evaluated_key;
+            if start_key.is_none() {
+                return Ok(keys);
+            }
+        }
+    }
+
+    /// Record that a key was used at `now_epoch`.
+    pub async fn touch_key(&self, key_hash: &str, now_epoch: i64) -> Result<(), CoreError> {
+        self.update_key(
+            key_hash,
+            "SET last_used_at = :value",
+            AttributeValue::N(now_epoch.to_string()),
+        )
+        .await
+    }
+
+    /// Revoke a key as of `now_epoch`. Revoked keys are rejected immediately.
+    ///
+    /// Returns `CoreError::NotFound` if the key does not exist.
+    pub async fn revoke_key(&self, key_hash: &str, now_epoch: i64) -> Result<(), CoreError> {
+        self.update_key(
+            key_hash,
+            "SET revoked_at = :value",
+            AttributeValue::N(now_epoch.to_string()),
+        )
+        .await
+    }
+
+    /// Set the expiry of a key to `expires_at` (Unix epoch seconds).
+    ///
+    /// Returns `CoreError::NotFound` if the key does not exist.
+    pub async fn set_key_expiry(&self, key_hash: &str, expires_at: i64) -> Result<(), CoreError> {
+        self.update_key(
+            key_hash,
+            "SET expires_at = :value",
+            AttributeValue::N(expires_at.to_string()),
+        )
+        .await
+    }
+
+    /// Apply `update_expression` with a single `:value` to an existing key.
+    async fn update_key(
+        &self,
+        key_hash: &str,
+        update_expression: &str,
+        value: AttributeValue,
+    ) -> Result<(), CoreError> {
+        let result = self
+            .client
+            .update_item()
+            .table_name(&self.table_name)
+            .key("api_key", AttributeValue::S(key_hash.to_string()))
+            .update_expression(update_expression)
+            .expression_attribute_values(":value", value)
+            .condition_expression("attribute_exists(api_key)")
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                let service_err = e.into_service_error();
+                if service_err.is_conditional_check_failed_exception() {
+                    Err(CoreError::NotFound("API key".to_string()))
+                } else {
+                    Err(CoreError::DynamoSdk(Box::new(service_err)))
+                }
+            }
+        }
+    }
+}
+
+/// DynamoDB client wrapper for the audit log.
+#[derive(Clone)]
+pub struct DynamoAuditStore {
+    client: Client,
+    table_name: String,
+}
+
+impl DynamoAuditStore {
+    /// Create a new `DynamoAuditStore` sharing an existing DynamoDB client.
+    pub fn new(client: Client, table_name: impl Into<String>) -> Self {
+        Self {
+            client,
+            table_name: table_name.into(),
+        }
+    }
+
+    /// The DynamoDB table name.
+    pub fn table_name(&self) -> &str {
+        &self.table_name
+    }
+
+    /// Append an entry to the audit log.
+    pub async fn record(&self, entry: &AuditEntry) -> Result<(), CoreError> {
+        let item = serde_dynamo::to_item(entry)?;
+
+        self.client
+            .put_item()
+            .table_name(&self.table_name)
+            .set_item(Some(item))
+            .send()
+            .await
+            .map_err(|e| CoreError::DynamoSdk(Box::new(e)))?;
+
+        Ok(())
+    }
+
+    /// List up to `limit` entries of `project` made at or after `since_epoch`,
+    /// newest first. If `before` is the `event_id` of an entry, the list
+    /// starts with the entry preceding it.
+    pub async fn list(
+        &self,
+        project: &str,
+        since_epoch: i64,
+        before: Option<&str>,
+        limit: i32,
+    ) -> Result<Vec<AuditEntry>, CoreError> {
+        let start_key = before.map(|event_id| {
+            HashMap::from([
+                (
+                    "project".to_string(),
+                    AttributeValue::S(project.to_string()),
+                ),
+                (
+                    "event_id".to_string(),
+                    AttributeValue::S(event_id.to_string()),
+                ),
+            ])
+        });
+
+        let result = self
+            .client
+            .query()
+            .table_name(&self.table_name)
+            .key_condition_expression("project = :project AND event_id >= :since")
+            .expression_attribute_values(":project", AttributeValue::S(project.to_string()))
+            .expression_attribute_values(
+                ":since",
+                AttributeValue::S(audit_event_prefix(since_epoch)),
+            )
+            .scan_index_forward(false)
+            .set_exclusive_start_key(start_key)
+            .limit(limit)
+            .send()
+            .await
+            .map_err(|e| CoreError::DynamoSdk(Box::new(e)))?;
+
+        let entries: Vec<AuditEntry> = serde_dynamo::from_items(result.items().to_vec())?;
+        Ok(entries)
+    }
+}
+
+/// DynamoDB client wrapper for the status of heartbeat's own components.
+#[derive(Clone)]
+pub struct DynamoStatusStore {
+    client: Client,
+    table_name: String,
+}
+
+impl DynamoStatusStore {
+    /// Create a new `DynamoStatusStore` sharing an existing DynamoDB client.
+    pub fn new(client: Client, table_name: impl Into<String>) -> Self {
+        Self {
+            client,
+            table_name: table_name.into(),
+        }
+    }
+
+    /// The DynamoDB table name.
+    pub fn table_name(&self) -> &str {
+        &self.table_name
+    }
+
+    /// Record a completed checker cycle, replacing the previous one.
+    pub async fn put_checker_heartbeat(
+        &self,
+        heartbeat: &CheckerHeartbeat,
+    ) -> Result<(), CoreError> {
+        let mut item: HashMap<String, AttributeValue> = serde_dynamo::to_item(heartbeat)?;
+        item.insert(
+            "component".to_string(),
+            AttributeValue::S(CHECKER_COMPONENT.to_string()),
+        );
+
+        self.client
+            .put_item()
+            .table_name(&self.table_name)
+            .set_item(Some(item))
+            .send()
+            .await
+            .map_err(|e| CoreError::DynamoSdk(Box::new(e)))?;
+
+        Ok(())
+    }
+
+    /// Get the last completed checker cycle.
+    ///
+    /// Returns `None` if the checker has never completed a cycle.
+    pub async fn get_checker_heartbeat(&self) -> Result<Option<CheckerHeartbeat>, CoreError> {
+        let result = self
+            .client
+            .get_item()
+            .table_name(&self.table_name)
+            .key(
+                "component",
+                AttributeValue::S(CHECKER_COMPONENT.to_string()),
+            )
+            .send()
+            .await
+            .map_err(|e| CoreError::DynamoSdk(Box::new(e)))?;
+
+        match result.item {
+            Some(item) => Ok(Some(serde_dynamo::from_item(item)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Acquire the lease `name` for `owner` until `now_epoch + ttl_secs`.
+    ///
+    /// Succeeds if the lease is free, expired, or already held by `owner`, in
+    /// which case it is renewed. Returns `false` if another owner holds it.
+    pub async fn acquire_lease(
+        &self,
+        name: &str,
+        owner: &str,
+        now_epoch: i64,
+        ttl_secs: i64,
+    ) -> Result<bool, CoreError> {
+        let result = self
+            .client
+            .put_item()
+            .table_name(&self.table_name)
+            .item("component", AttributeValue::S(name.to_string()))
+            .item("owner", AttributeValue::S(owner.to_string()))
+            .item(
+                "expires_at",
+                AttributeValue::N((now_epoch + ttl_secs).to_string()),
+            )
+            .condition_expression(
+                "attribute_not_exists(component) OR expires_at < :now OR #owner = :owner",
+            )
+            .expression_attribute_names("#owner", "owner")
+            .expression_attribute_values(":now", AttributeValue::N(now_epoch.to_string()))
+            .expression_attribute_values(":owner", AttributeValue::S(owner.to_string()))
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                let service_err = e.into_service_error();
+                if service_err.is_conditional_check_failed_exception() {
+                    Ok(false)
+                } else {
+                    Err(CoreError::DynamoSdk(Box::new(service_err)))
+                }
+            }
+        }
+    }
+
+    /// Extend the lease `name` to `now_epoch + ttl_secs` if `owner` still
+    /// holds it and it hasn't expired.
+    ///
+    /// Unlike [`DynamoStatusStore::acquire_lease`], this never takes over the
+    /// lease: `false` means it was lost and may be held by another owner.
+    pub async fn renew_lease(
+        &self,
+        name: &str,
+        owner: &str,
+        now_epoch: i64,
+        ttl_secs: i64,
+    ) -> Result<bool, CoreError> {
+        let result = self
+            .client
+            .update_item()
+            .table_name(&self.table_name)
+            .key("component", AttributeValue::S(name.to_string()))
+            .update_expression("SET expires_at = :expires")
+            .condition_expression("#owner = :owner AND expires_at >= :now")
+            .expression_attribute_names("#owner", "owner")
+            .expression_attribute_values(
+                ":expires",
+                AttributeValue::N((now_epoch + ttl_secs).to_string()),
+            )
+            .expression_attribute_values(":now", AttributeValue::N(now_epoch.to_string()))
+            .expression_attribute_values(":owner", AttributeValue::S(owner.to_string()))
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                let service_err = e.into_service_error();
+                if service_err.is_conditional_check_failed_exception() {
+                    Ok(false)
+                } else {
+                    Err(CoreError::DynamoSdk(Box::new(service_err)))
+                }
+            }
+        }
+    }
+
+    /// Release the lease `name` if `owner` still holds it.
+    pub async fn release_lease(&self, name: &str, owner: &str) -> Result<(), CoreError> {
+        let result = self
+            .client
+            .delete_item()
+            .table_name(&self.table_name)
+            .key("component", AttributeValue::S(name.to_string()))
+            .condition_expression("#owner = :owner")
+            .expression_attribute_names("#owner", "owner")
+            .expression_attribute_values(":owner", AttributeValue::S(owner.to_string()))
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                let service_err = e.into_service_error();
+                // Expired and taken over, or already released
+                if service_err.is_conditional_check_failed_exception() {
+                    Ok(())
+                } else {
+                    Err(CoreError::DynamoSdk(Box::new(service_err)))
+                }
+            }
+        }
+    }
+
+    /// Record that the alert with `idempotency_key` is being sent, until
+    /// `expires_at` (epoch seconds).
+    ///
+    /// Returns `false` if it was already recorded, by this or another
+    /// checker, and hasn't expired: the alert must not be sent again.
+    pub async fn claim_alert(
+        &self,
+        idempotency_key: &str,
+        now_epoch: i64,
+        expires_at: i64,
+    ) -> Result<bool, CoreError> {
+        let result = self
+            .client
+            .put_item()
+            .table_name(&self.table_name)
+            .item(
+                "component",
+                AttributeValue::S(alert_sent_key(idempotency_key)),
+            )
+            .item("claimed_at", AttributeValue::N(now_epoch.to_string()))
+            .item("expires_at", AttributeValue::N(expires_at.to_string()))
+            // TTL deletion is lazy, so expired items may still be present
+            .condition_expression("attribute_not_exists(component) OR expires_at < :now")
+            .expression_attribute_values(":now", AttributeValue::N(now_epoch.to_string()))
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                let service_err = e.into_service_error();
+                if service_err.is_conditional_check_failed_exception() {
+                    Ok(false)
+                } else {
+                    Err(CoreError::DynamoSdk(Box::new(service_err)))
+                }
+            }
+        }
+    }
+
+    /// Remove the claim on `idempotency_key`, so the alert can be retried
+    /// after failing to send it.
+    pub async fn release_alert(&self, idempotency_key: &str) -> Result<(), CoreError> {
+        self.client
+            .delete_item()
+            .table_name(&self.table_name)
+            .key(
+                "component",
+                AttributeValue::S(alert_sent_key(idempotency_key)),
+            )
+            .send()
+            .await
+            .map_err(|e| CoreError::DynamoSdk(Box::new(e)))?;
+
+        Ok(())
+    }
+}
+
+/// The schema of a table created by [`DynamoStore::create_tables`].
+struct TableSpec {
+    name: String,
+    keys: Vec<KeySchemaElement>,
+    attributes: Vec<AttributeDefinition>,
+    indexes: Vec<GlobalSecondaryIndex>,
+    /// Whether items expire on their `expires_at` attribute.
+    ttl: bool,
+}
+
+/// The schemas of all heartbeat tables, as in `terraform/dynamodb.tf`.
+fn table_specs(tables: &DynamoTables) -> Result<Vec<TableSpec>, BuildError> {
+    use ScalarAttributeType::{N, S};
+
+    Ok(vec![
+        TableSpec {
+            name: tables.monitors.clone(),
+            keys: vec![key("slug", KeyType::Hash)?],
+            attributes: vec![
+                attribute("slug", S)?,
+                attribute("check_partition", S)?,
+                attribute("next_due", N)?,
+                attribute("ping_token", S)?,
+            ],
+            indexes: vec![
+                GlobalSecondaryIndex::builder()
+                    .index_name("overdue-check-index")
+                    .key_schema(key("check_partition", KeyType::Hash)?)
+                    .key_schema(key("next_due", KeyType::Range)?)
+                    .projection(
+                        Projection::builder()
+                            .projection_type(ProjectionType::All)
+                            .build(),
+                    )
+                    .build()?,
+                GlobalSecondaryIndex::builder()
+                    .index_name("ping-token-index")
+                    .key_schema(key("ping_token", KeyType::Hash)?)
+                    .projection(
+                        Projection::builder()
+                            .projection_type(ProjectionType::KeysOnly)
+                            .build(),
+                    )
+                    .build()?,
+            ],
+            ttl: true,
+        },
+        TableSpec {
+            name: tables.api_keys.clone(),
+            keys: vec![key("api_key", KeyType::Hash)?],
+            attributes: vec![attribute("api_key", S)?],
+            indexes: Vec::new(),
+            ttl: false,
+        },
+        TableSpec {
+            name: tables.audit.clone(),
+            keys: vec![
+                key("project", KeyType::Hash)?,
+                key("event_id", KeyType::Range)?,
+            ],
+            attributes: vec![attribute("project", S)?, attribute("event_id", S)?],
+            indexes: Vec::new(),
+            ttl: true,
+        },
+        TableSpec {
+            name: tables.status.clone(),
+            keys: vec![key("component", KeyType::Hash)?],
+            attributes: vec![attribute("component", S)?],
+            indexes: Vec::new(),
+            ttl: true,
+        },
+    ])
+}
+
+fn key(name: &str, key_type: KeyType) -> Result<KeySchemaElement, BuildError> {
+    KeySchemaElement::builder()
+        .attribute_name(name)
+        .key_type(key_type)
+        .build()
+}
+
+fn attribute(name: &str, kind: ScalarAttributeType) -> Result<AttributeDefinition, BuildError> {
+    AttributeDefinition::builder()
+        .attribute_name(name)
+        .attribute_type(kind)
+        .build()
+}
+
+/// Describe a table, failing if it doesn't exist or DynamoDB can't be reached.
+async fn describe_table(client: &Client, table_name: &str) -> Result<(), CoreError> {
+    client
+        .describe_table()
+        .table_name(table_name)
+        .send()
+        .await
+        .map_err(|e| CoreError::DynamoSdk(Box::new(e)))?;
+    Ok(())
+}
+
+/// Deserialize a monitor item, splitting its partition key into project and slug.
+fn monitor_from_item(item: HashMap<String, AttributeValue>) -> serde_dynamo::Result<Monitor> {
+    let mut monitor: Monitor = serde_dynamo::from_item(item)?;
+    let (project, slug) = split_monitor_key(&monitor.slug);
+    (monitor.project, monitor.slug) = (project.to_string(), slug.to_string());
+    Ok(monitor)
+}
+
+/// Deserialize monitor items returned by a query or scan.
+fn monitors_from_items(
+    items: Option<Vec<HashMap<String, AttributeValue>>>,
+) -> serde_dynamo::Result<Vec<Monitor>> {
+    items
+        .unwrap_or_default()
+        .into_iter()
+        .map(monitor_from_item)
+        .collect()
+}
+
+/// Generate a new unguessable ping token (random UUIDv4).
+fn new_ping_token() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
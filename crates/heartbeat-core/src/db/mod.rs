@@ -0,0 +1,465 @@
+//! Storage backends: DynamoDB, or an SQL database (SQLite or PostgreSQL)
+//! for running without AWS, with the `sql` feature.
+//!
+//! The binaries use the store enums below, which dispatch to the backend
+//! selected by [`StorageConfig`].
+
+mod dynamo;
+#[cfg(feature = "sql")]
+mod sql;
+
+pub use dynamo::{DynamoApiKeyStore, DynamoAuditStore, DynamoStatusStore, DynamoStore};
+#[cfg(feature = "sql")]
+pub use sql::SqlStore;
+
+use crate::error::CoreError;
+use crate::model::{ApiKeyRecord, AuditEntry, CheckerHeartbeat, Monitor, MonitorId, MonitorStatus};
+
+/// Names of the DynamoDB tables, and the endpoint serving them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DynamoTables {
+    pub monitors: String,
+    pub api_keys: String,
+    pub audit: String,
+    pub status: String,
+    /// DynamoDB endpoint replacing the AWS one, e.g. DynamoDB Local.
+    pub endpoint_url: Option<String>,
+}
+
+impl Default for DynamoTables {
+    /// The table names created by the Terraform configuration.
+    fn default() -> Self {
+        Self {
+            monitors: "heartbeat-monitors".to_string(),
+            api_keys: "heartbeat-api-keys".to_string(),
+            audit: "heartbeat-audit".to_string(),
+            status: "heartbeat-status".to_string(),
+            endpoint_url: None,
+        }
+    }
+}
+
+/// Where heartbeat stores its data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StorageConfig {
+    /// DynamoDB tables, with AWS configuration from the environment.
+    DynamoDb(DynamoTables),
+    /// An SQL database at `url`: `sqlite://path?mode=rwc` or `postgres://...`.
+    #[cfg(feature = "sql")]
+    Sql { url: String },
+}
+
+impl StorageConfig {
+    /// The SQL database in `DATABASE_URL` if set, and the DynamoDB `tables`
+    /// otherwise, at the endpoint in `DYNAMODB_ENDPOINT_URL` if set.
+    ///
+    /// Without the `sql` feature, `DATABASE_URL` is ignored with a warning.
+    pub fn from_env(mut tables: DynamoTables) -> Self {
+        match std::env::var("DATABASE_URL") {
+            #[cfg(feature = "sql")]
+            Ok(url) if !url.is_empty() => return Self::Sql { url },
+            #[cfg(not(feature = "sql"))]
+            Ok(url) if !url.is_empty() => {
+                tracing::warn!("DATABASE_URL is set, but SQL storage is not built in")
+            }
+            _ => {}
+        }
+        if let Ok(url) = std::env::var("DYNAMODB_ENDPOINT_URL")
+            && !url.is_empty()
+        {
+            tables.endpoint_url = Some(url);
+        }
+        Self::DynamoDb(tables)
+    }
+
+    /// The backend name, for logging.
+    pub fn backend(&self) -> &'static str {
+        match self {
+            Self::DynamoDb(_) => "dynamodb",
+            #[cfg(feature = "sql")]
+            Self::Sql { url } if url.starts_with("sqlite:") => "sqlite",
+            #[cfg(feature = "sql")]
+            Self::Sql { .. } => "postgres",
+        }
+    }
+}
+
+/// The stores of all heartbeat data, on one backend.
+#[derive(Clone)]
+pub struct Stores {
+    pub monitors: MonitorStore,
+    pub api_keys: ApiKeyStore,
+    pub audit: AuditStore,
+    pub status: StatusStore,
+}
+
+impl Stores {
+    /// Connect to the backend in `config`.
+    ///
+    /// DynamoDB stores share one client, whose calls are recorded in the
+    /// [`metrics`](crate::metrics). An SQL database is migrated to the
+    /// current schema.
+    pub async fn connect(config: &StorageConfig) -> Result<Self, CoreError> {
+        match config {
+            StorageConfig::DynamoDb(tables) => {
+                let monitors =
+                    DynamoStore::new(&tables.monitors, tables.endpoint_url.as_deref()).await;
+                let client = monitors.client().clone();
+                Ok(Self {
+                    api_keys: ApiKeyStore::Dynamo(DynamoApiKeyStore::new(
+                        client.clone(),
+                        &tables.api_keys,
+                    )),
+                    audit: AuditStore::Dynamo(DynamoAuditStore::new(client.clone(), &tables.audit)),
+                    status: StatusStore::Dynamo(DynamoStatusStore::new(client, &tables.status)),
+                    monitors: MonitorStore::Dynamo(monitors),
+                })
+            }
+            #[cfg(feature = "sql")]
+            StorageConfig::Sql { url } => {
+                let store = SqlStore::connect(url).await?;
+                Ok(Self {
+                    monitors: MonitorStore::Sql(store.clone()),
+                    api_keys: ApiKeyStore::Sql(store.clone()),
+                    audit: AuditStore::Sql(store.clone()),
+                    status: StatusStore::Sql(store),
+                })
+            }
+        }
+    }
+}
+
+/// Monitor storage. See [`DynamoStore`] for the semantics of each method.
+#[derive(Clone)]
+pub enum MonitorStore {
+    Dynamo(DynamoStore),
+    #[cfg(feature = "sql")]
+    Sql(SqlStore),
+}
+
+impl MonitorStore {
+    /// Check that the backend is reachable.
+    pub async fn check_table(&self) -> Result<(), CoreError> {
+        match self {
+            Self::Dynamo(store) => store.check_table().await,
+            #[cfg(feature = "sql")]
+            Self::Sql(store) => store.check_table().await,
+        }
+    }
+
+    pub async fn upsert_monitor(&self, monitor: &Monitor) -> Result<Monitor, CoreError> {
+        match self {
+            Self::Dynamo(store) => store.upsert_monitor(monitor).await,
+            #[cfg(feature = "sql")]
+            Self::Sql(store) => store.upsert_monitor(monitor).await,
+        }
+    }
+
+    pub async fn record_failure(&self, monitor: &Monitor) -> Result<Monitor, CoreError> {
+        match self {
+            Self::Dynamo(store) => store.record_failure(monitor).await,
+            #[cfg(feature = "sql")]
+            Self::Sql(store) => store.record_failure(monitor).await,
+        }
+    }
+
+    pub async fn get_monitor(&self, id: &MonitorId) -> Result<Option<Monitor>, CoreError> {
+        match self {
+            Self::Dynamo(store) => store.get_monitor(id).await,
+            #[cfg(feature = "sql")]
+            Self::Sql(store) => store.get_monitor(id).await,
+        }
+    }
+
+    pub async fn find_monitor_by_ping_token(
+        &self,
+        token: &str,
+    ) -> Result<Option<MonitorId>, CoreError> {
+        match self {
+            Self::Dynamo(store) => store.find_monitor_by_ping_token(token).await,
+            #[cfg(feature = "sql")]
+            Self::Sql(store) => store.find_monitor_by_ping_token(token).await,
+        }
+    }
+
+    pub async fn rotate_ping_token(&self, id: &MonitorId) -> Result<String, CoreError> {
+        match self {
+            Self::Dynamo(store) => store.rotate_ping_token(id).await,
+            #[cfg(feature = "sql")]
+            Self::Sql(store) => store.rotate_ping_token(id).await,
+        }
+    }
+
+    pub async fn query_overdue(&self, now_epoch: i64) -> Result<Vec<Monitor>, CoreError> {
+        match self {
+            Self::Dynamo(store) => store.query_overdue(now_epoch).await,
+            #[cfg(feature = "sql")]
+            Self::Sql(store) => store.query_overdue(now_epoch).await,
+        }
+    }
+
+    pub async fn query_alerted(&self) -> Result<Vec<Monitor>, CoreError> {
+        match self {
+            Self::Dynamo(store) => store.query_alerted().await,
+            #[cfg(feature = "sql")]
+            Self::Sql(store) => store.query_alerted().await,
+        }
+    }
+
+    pub async fn update_alert_state(
+        &self,
+        key: &str,
+        first_alert: i64,
+        now_epoch: i64,
+        alert_count: u32,
+        status: MonitorStatus,
+    ) -> Result<(), CoreError> {
+        match self {
+            Self::Dynamo(store) => {
+                store
+                    .update_alert_state(key, first_alert, now_epoch, alert_count, status)
+                    .await
+            }
+            #[cfg(feature = "sql")]
+            Self::Sql(store) => {
+                store
+                    .update_alert_state(key, first_alert, now_epoch, alert_count, status)
+                    .await
+            }
+        }
+    }
+
+    pub async fn clear_alert_state(&self, key: &str) -> Result<(), CoreError> {
+        match self {
+            Self::Dynamo(store) => store.clear_alert_state(key).await,
+            #[cfg(feature = "sql")]
+            Self::Sql(store) => store.clear_alert_state(key).await,
+        }
+    }
+
+    pub async fn list_monitors(&self) -> Result<Vec<Monitor>, CoreError> {
+        match self {
+            Self::Dynamo(store) => store.list_monitors().await,
+            #[cfg(feature = "sql")]
+            Self::Sql(store) => store.list_monitors().await,
+        }
+    }
+
+    pub async fn delete_monitor(&self, id: &MonitorId) -> Result<(), CoreError> {
+        match self {
+            Self::Dynamo(store) => store.delete_monitor(id).await,
+            #[cfg(feature = "sql")]
+            Self::Sql(store) => store.delete_monitor(id).await,
+        }
+    }
+
+    pub async fn set_paused(&self, id: &MonitorId, paused: bool) -> Result<(), CoreError> {
+        match self {
+            Self::Dynamo(store) => store.set_paused(id, paused).await,
+            #[cfg(feature = "sql")]
+            Self::Sql(store) => store.set_paused(id, paused).await,
+        }
+    }
+}
+
+/// API key storage. See [`DynamoApiKeyStore`] for the semantics of each method.
+#[derive(Clone)]
+pub enum ApiKeyStore {
+    Dynamo(DynamoApiKeyStore),
+    #[cfg(feature = "sql")]
+    Sql(SqlStore),
+}
+
+impl ApiKeyStore {
+    /// Check that the backend is reachable.
+    pub async fn check_table(&self) -> Result<(), CoreError> {
+        match self {
+            Self::Dynamo(store) => store.check_table().await,
+            #[cfg(feature = "sql")]
+            Self::Sql(store) => store.check_table().await,
+        }
+    }
+
+    pub async fn get_key(&self, key_hash: &str) -> Result<Option<ApiKeyRecord>, CoreError> {
+        match self {
+            Self::Dynamo(store) => store.get_key(key_hash).await,
+            #[cfg(feature = "sql")]
+            Self::Sql(store) => store.get_key(key_hash).await,
+        }
+    }
+
+    pub async fn put_key(&self, record: &ApiKeyRecord) -> Result<(), CoreError> {
+        match self {
+            Self::Dynamo(store) => store.put_key(record).await,
+            #[cfg(feature = "sql")]
+            Self::Sql(store) => store.put_key(record).await,
+        }
+    }
+
+    pub async fn delete_key(&self, key_hash: &str) -> Result<(), CoreError> {
+        match self {
+            Self::Dynamo(store) => store.delete_key(key_hash).await,
+            #[cfg(feature = "sql")]
+            Self::Sql(store) => store.delete_key(key_hash).await,
+        }
+    }
+
+    pub async fn list_keys(&self) -> Result<Vec<ApiKeyRecord>, CoreError> {
+        match self {
+            Self::Dynamo(store) => store.list_keys().await,
+            #[cfg(feature = "sql")]
+            Self::Sql(store) => store.list_keys().await,
+        }
+    }
+
+    pub async fn touch_key(&self, key_hash: &str, now_epoch: i64) -> Result<(), CoreError> {
+        match self {
+            Self::Dynamo(store) => store.touch_key(key_hash, now_epoch).await,
+            #[cfg(feature = "sql")]
+            Self::Sql(store) => store.touch_key(key_hash, now_epoch).await,
+        }
+    }
+
+    pub async fn revoke_key(&self, key_hash: &str, now_epoch: i64) -> Result<(), CoreError> {
+        match self {
+            Self::Dynamo(store) => store.revoke_key(key_hash, now_epoch).await,
+            #[cfg(feature = "sql")]
+            Self::Sql(store) => store.revoke_key(key_hash, now_epoch).await,
+        }
+    }
+
+    pub async fn set_key_expiry(&self, key_hash: &str, expires_at: i64) -> Result<(), CoreError> {
+        match self {
+            Self::Dynamo(store) => store.set_key_expiry(key_hash, expires_at).await,
+            #[cfg(feature = "sql")]
+            Self::Sql(store) => store.set_key_expiry(key_hash, expires_at).await,
+        }
+    }
+}
+
+/// Audit log storage. See [`DynamoAuditStore`] for the semantics of each method.
+#[derive(Clone)]
+pub enum AuditStore {
+    Dynamo(DynamoAuditStore),
+    #[cfg(feature = "sql")]
+    Sql(SqlStore),
+}
+
+impl AuditStore {
+    pub async fn record(&self, entry: &AuditEntry) -> Result<(), CoreError> {
+        match self {
+            Self::Dynamo(store) => store.record(entry).await,
+            #[cfg(feature = "sql")]
+            Self::Sql(store) => store.record_audit(entry).await,
+        }
+    }
+
+    pub async fn list(
+        &self,
+        project: &str,
+        since_epoch: i64,
+        before: Option<&str>,
+        limit: i32,
+    ) -> Result<Vec<AuditEntry>, CoreError> {
+        match self {
+            Self::Dynamo(store) => store.list(project, since_epoch, before, limit).await,
+            #[cfg(feature = "sql")]
+            Self::Sql(store) => store.list_audit(project, since_epoch, before, limit).await,
+        }
+    }
+}
+
+/// Status of heartbeat's own components, leases and alert claims. See
+/// [`DynamoStatusStore`] for the semantics of each method.
+#[derive(Clone)]
+pub enum StatusStore {
+    Dynamo(DynamoStatusStore),
+    #[cfg(feature = "sql")]
+    Sql(SqlStore),
+}
+
+impl StatusStore {
+    pub async fn put_checker_heartbeat(
+        &self,
+        heartbeat: &CheckerHeartbeat,
+    ) -> Result<(), CoreError> {
+        match self {
+            Self::Dynamo(store) => store.put_checker_heartbeat(heartbeat).await,
+            #[cfg(feature = "sql")]
+            Self::Sql(store) => store.put_checker_heartbeat(heartbeat).await,
+        }
+    }
+
+    pub async fn get_checker_heartbeat(&self) -> Result<Option<CheckerHeartbeat>, CoreError> {
+        match self {
+            Self::Dynamo(store) => store.get_checker_heartbeat().await,
+            #[cfg(feature = "sql")]
+            Self::Sql(store) => store.get_checker_heartbeat().await,
+        }
+    }
+
+    pub async fn acquire_lease(
+        &self,
+        name: &str,
+        owner: &str,
+        now_epoch: i64,
+        ttl_secs: i64,
+    ) -> Result<bool, CoreError> {
+        match self {
+            Self::Dynamo(store) => store.acquire_lease(name, owner, now_epoch, ttl_secs).await,
+            #[cfg(feature = "sql")]
+            Self::Sql(store) => store.acquire_lease(name, owner, now_epoch, ttl_secs).await,
+        }
+    }
+
+    pub async fn renew_lease(
+        &self,
+        name: &str,
+        owner: &str,
+        now_epoch: i64,
+        ttl_secs: i64,
+    ) -> Result<bool, CoreError> {
+        match self {
+            Self::Dynamo(store) => store.renew_lease(name, owner, now_epoch, ttl_secs).await,
+            #[cfg(feature = "sql")]
+            Self::Sql(store) => store.renew_lease(name, owner, now_epoch, ttl_secs).await,
+        }
+    }
+
+    pub async fn release_lease(&self, name: &str, owner: &str) -> Result<(), CoreError> {
+        match self {
+            Self::Dynamo(store) => store.release_lease(name, owner).await,
+            #[cfg(feature = "sql")]
+            Self::Sql(store) => store.release_lease(name, owner).await,
+        }
+    }
+
+    pub async fn claim_alert(
+        &self,
+        idempotency_key: &str,
+        now_epoch: i64,
+        expires_at: i64,
+    ) -> Result<bool, CoreError> {
+        match self {
+            Self::Dynamo(store) => {
+                store
+                    .claim_alert(idempotency_key, now_epoch, expires_at)
+                    .await
+            }
+            #[cfg(feature = "sql")]
+            Self::Sql(store) => {
+                store
+                    .claim_alert(idempotency_key, now_epoch, expires_at)
+                    .await
+            }
+        }
+    }
+
+    pub async fn release_alert(&self, idempotency_key: &str) -> Result<(), CoreError> {
+        match self {
+            Self::Dynamo(store) => store.release_alert(idempotency_key).await,
+            #[cfg(feature = "sql")]
+            Self::Sql(store) => store.release_alert(idempotency_key).await,
+        }
+    }
+}
@@ -0,0 +1,982 @@
+//! Storage in an SQL database, SQLite or PostgreSQL, for running without
+//! DynamoDB.
+//!
+//! All tables live in one database. Nested values (failures, ping metadata,
+//! scopes, audit entries) are stored as JSON text, and items DynamoDB would
+//! expire through its TTL are deleted when writing or querying.
+
+use sqlx::any::{AnyPoolOptions, AnyRow};
+use sqlx::{AnyPool, Row};
+
+use crate::error::CoreError;
+use crate::model::{
+    ApiKeyRecord, AuditEntry, CHECKER_COMPONENT, CheckerHeartbeat, Monitor, MonitorId,
+    MonitorStatus, alert_sent_key, audit_event_prefix, split_monitor_key,
+};
+
+/// Schema migrations, applied in order and recorded in `schema_migrations`.
+///
+/// Statements are written for both SQLite and PostgreSQL. Never edit a
+/// released migration: append a new one instead.
+const MIGRATIONS: &[(i64, &[&str])] = &[
+    (
+        1,
+        &[
+            "CREATE TABLE IF NOT EXISTS monitors (
+            monitor_key TEXT PRIMARY KEY,
+            interval_secs BIGINT NOT NULL,
+            last_ping BIGINT NOT NULL,
+            next_due BIGINT NOT NULL,
+            first_alerted_at BIGINT,
+            last_alerted_at BIGINT,
+            alert_count BIGINT,
+            alert_status TEXT,
+            created_at BIGINT NOT NULL,
+            paused BIGINT,
+            expires_at BIGINT NOT NULL,
+            last_failure TEXT,
+            last_ping_meta TEXT,
+            ping_token TEXT
+        )",
+            "CREATE INDEX IF NOT EXISTS monitors_next_due ON monitors (next_due)",
+            "CREATE UNIQUE INDEX IF NOT EXISTS monitors_ping_token ON monitors (ping_token)",
+            "CREATE TABLE IF NOT EXISTS api_keys (
+            key_hash TEXT PRIMARY KEY,
+            project TEXT NOT NULL,
+            key_prefix TEXT,
+            description TEXT NOT NULL,
+            created_at BIGINT NOT NULL,
+            scopes TEXT,
+            slug_prefix TEXT,
+            expires_at BIGINT,
+            revoked_at BIGINT,
+            last_used_at BIGINT
+        )",
+            "CREATE TABLE IF NOT EXISTS audit_log (
+            project TEXT NOT NULL,
+            event_id TEXT NOT NULL,
+            expires_at BIGINT NOT NULL,
+            entry TEXT NOT NULL,
+            PRIMARY KEY (project, event_id)
+        )",
+            "CREATE TABLE IF NOT EXISTS component_status (
+            component TEXT PRIMARY KEY,
+            owner TEXT,
+            data TEXT,
+            expires_at BIGINT
+        )",
+        ],
+    ),
+    (2, &["ALTER TABLE monitors ADD COLUMN timezone TEXT"]),
+];
+
+const MONITOR_COLUMNS: &str = "monitor_key, interval_secs, last_ping, next_due, \
+    first_alerted_at, last_alerted_at, alert_count, alert_status, created_at, paused, \
+    expires_at, last_failure, last_ping_meta, ping_token, timezone";
+
+const API_KEY_COLUMNS: &str = "key_hash, project, key_prefix, description, created_at, \
+    scopes, slug_prefix, expires_at, revoked_at, last_used_at";
+
+/// SQLite or PostgreSQL storage for monitors, API keys, the audit log and
+/// component status.
+#[derive(Clone)]
+pub struct SqlStore {
+    pool: AnyPool,
+}
+
+impl SqlStore {
+    /// Connect to the database at `url` (`sqlite://...` or `postgres://...`)
+    /// and apply pending migrations.
+    ///
+    /// SQLite databases are used through a single connection, since SQLite
+    /// allows one writer at a time.
+    pub async fn connect(url: &str) -> Result<Self, CoreError> {
+        sqlx::any::install_default_drivers();
+        let max_connections = if url.starts_with("sqlite:") { 1 } else { 10 };
+        let pool = AnyPoolOptions::new()
+            .max_connections(max_connections)
+            .connect(url)
+            .await?;
+
+        let store = Self { pool };
+        store.migrate().await?;
+        Ok(store)
+    }
+
+    /// Apply the migrations not yet recorded, each in its own transaction.
+    async fn migrate(&self) -> Result<(), CoreError> {
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS schema_migrations (
+                version BIGINT PRIMARY KEY,
+                applied_at BIGINT NOT NULL
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        let applied = self.applied_migrations().await?;
+        for (version, statements) in MIGRATIONS {
+            if applied.contains(version) {
+                continue;
+            }
+            if let Err(e) = self.apply_migration(*version, statements).await {
+                // Another replica may have applied it concurrently, failing
+                // statements such as ALTER TABLE here once it committed
+                if self.applied_migrations().await?.contains(version) {
+                    continue;
+                }
+                return Err(e);
+            }
+            tracing::info!(version, "applied schema migration");
+        }
+
+        Ok(())
+    }
+
+    async fn applied_migrations(&self) -> Result<Vec<i64>, CoreError> {
+        Ok(sqlx::query("SELECT version FROM schema_migrations")
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(|row| row.try_get("version"))
+            .collect::<Result<_, _>>()?)
+    }
+
+    async fn apply_migration(&self, version: i64, statements: &[&str]) -> Result<(), CoreError> {
+        let mut tx = self.pool.begin().await?;
+        for statement in statements {
+            sqlx::query(statement).execute(&mut *tx).await?;
+        }
+        // Statements creating tables are idempotent, so a concurrent replica
+        // may have applied the migration too
+        sqlx::query(
+            "INSERT INTO schema_migrations (version, applied_at) VALUES ($1, $2) \
+             ON CONFLICT (version) DO NOTHING",
+        )
+        .bind(version)
+        .bind(chrono::Utc::now().timestamp())
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Check that the schema exists and the database is reachable.
+    pub async fn check_table(&self) -> Result<(), CoreError> {
+        sqlx::query("SELECT 1 FROM monitors LIMIT 1")
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    // -- Monitors ----------------------------------------------------------
+
+    /// Insert or update a monitor on a ping, see [`DynamoStore::upsert_monitor`].
+    ///
+    /// [`DynamoStore::upsert_monitor`]: super::DynamoStore::upsert_monitor
+    pub async fn upsert_monitor(&self, monitor: &Monitor) -> Result<Monitor, CoreError> {
+        let meta = to_json(&monitor.last_ping_meta)?;
+        let sql = format!(
+            "INSERT INTO monitors (monitor_key, interval_secs, last_ping, next_due, created_at, \
+                expires_at, last_ping_meta, ping_token, timezone) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) \
+             ON CONFLICT (monitor_key) DO UPDATE SET \
+                interval_secs = excluded.interval_secs, \
+                last_ping = excluded.last_ping, \
+                next_due = excluded.next_due, \
+                expires_at = excluded.expires_at, \
+                last_ping_meta = excluded.last_ping_meta, \
+                last_failure = NULL, \
+                ping_token = COALESCE(monitors.ping_token, excluded.ping_token), \
+                timezone = excluded.timezone \
+             RETURNING {MONITOR_COLUMNS}"
+        );
+        let row = sqlx::query(&sql)
+            .bind(monitor.key())
+            .bind(monitor.interval_secs as i64)
+            .bind(monitor.last_ping)
+            .bind(monitor.next_due)
+            .bind(monitor.created_at)
+            .bind(monitor.expires_at)
+            .bind(meta)
+            .bind(new_ping_token())
+            .bind(monitor.timezone.clone())
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(monitor_from_row(&row)?)
+    }
+
+    /// Record an explicit failure, keeping the last successful ping of an
+    /// existing monitor, see [`DynamoStore::record_failure`].
+    ///
+    /// [`DynamoStore::record_failure`]: super::DynamoStore::record_failure
+    pub async fn record_failure(&self, monitor: &Monitor) -> Result<Monitor, CoreError> {
+        let failure = to_json(&monitor.last_failure)?;
+        let sql = format!(
+            "INSERT INTO monitors (monitor_key, interval_secs, last_ping, next_due, created_at, \
+                expires_at, last_failure, ping_token) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8) \
+             ON CONFLICT (monitor_key) DO UPDATE SET \
+                interval_secs = excluded.interval_secs, \
+                next_due = excluded.next_due, \
+                expires_at = excluded.expires_at, \
+                last_failure = excluded.last_failure, \
+                ping_token = COALESCE(monitors.ping_token, excluded.ping_token) \
+             RETURNING {MONITOR_COLUMNS}"
+        );
+        let row = sqlx::query(&sql)
+            .bind(monitor.key())
+            .bind(monitor.interval_secs as i64)
+            .bind(monitor.last_ping)
+            .bind(monitor.next_due)
+            .bind(monitor.created_at)
+            .bind(monitor.expires_at)
+            .bind(failure)
+            .bind(new_ping_token())
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(monitor_from_row(&row)?)
+    }
+
+    /// Get a monitor by project and slug.
+    ///
+    /// Returns `None` if the monitor does not exist.
+    pub async fn get_monitor(&self, id: &MonitorId) -> Result<Option<Monitor>, CoreError> {
+        let sql = format!("SELECT {MONITOR_COLUMNS} FROM monitors WHERE monitor_key = $1");
+        let row = sqlx::query(&sql)
+            .bind(id.key())
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(row.as_ref().map(monitor_from_row).transpose()?)
+    }
+
+    /// Find the monitor owning a ping token.
+    pub async fn find_monitor_by_ping_token(
+        &self,
+        token: &str,
+    ) -> Result<Option<MonitorId>, CoreError> {
+        let row = sqlx::query("SELECT monitor_key FROM monitors WHERE ping_token = $1")
+            .bind(token)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        match row {
+            Some(row) => Ok(Some(MonitorId::from_key(row.try_get("monitor_key")?)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Replace the ping token of a monitor with a newly generated one.
+    ///
+    /// Returns the new token, or `CoreError::NotFound` if the monitor does not exist.
+    pub async fn rotate_ping_token(&self, id: &MonitorId) -> Result<String, CoreError> {
+        let token = new_ping_token();
+        let result = sqlx::query("UPDATE monitors SET ping_token = $1 WHERE monitor_key = $2")
+            .bind(&token)
+            .bind(id.key())
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(CoreError::NotFound(id.slug.to_string()));
+        }
+        Ok(token)
+    }
+
+    /// Query all monitors that are overdue as of `now_epoch`.
+    ///
+    /// Monitors past their `expires_at` are deleted first, as DynamoDB's TTL
+    /// would.
+    pub async fn query_overdue(&self, now_epoch: i64) -> Result<Vec<Monitor>, CoreError> {
+        sqlx::query("DELETE FROM monitors WHERE expires_at < $1")
+            .bind(now_epoch)
+            .execute(&self.pool)
+            .await?;
+
+        let sql = format!("SELECT {MONITOR_COLUMNS} FROM monitors WHERE next_due < $1");
+        let rows = sqlx::query(&sql)
+            .bind(now_epoch)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(monitor_from_row)
+            .collect::<sqlx::Result<_>>()?)
+    }
+
+    /// Query all monitors that currently have an active alert.
+    pub async fn query_alerted(&self) -> Result<Vec<Monitor>, CoreError> {
+        let sql =
+            format!("SELECT {MONITOR_COLUMNS} FROM monitors WHERE last_alerted_at IS NOT NULL");
+        let rows = sqlx::query(&sql).fetch_all(&self.pool).await?;
+
+        Ok(rows
+            .iter()
+            .map(monitor_from_row)
+            .collect::<sqlx::Result<_>>()?)
+    }
+
+    /// Update the alert state for a monitor after sending an alert.
+    pub async fn update_alert_state(
+        &self,
+        key: &str,
+        first_alert: i64,
+        now_epoch: i64,
+        alert_count: u32,
+        status: MonitorStatus,
+    ) -> Result<(), CoreError> {
+        sqlx::query(
+            "UPDATE monitors SET first_alerted_at = $1, last_alerted_at = $2, \
+             alert_count = $3, alert_status = $4 WHERE monitor_key = $5",
+        )
+        .bind(first_alert)
+        .bind(now_epoch)
+        .bind(i64::from(alert_count))
+        .bind(status.as_str())
+        .bind(key)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Clear the alert state for a monitor after it recovers.
+    pub async fn clear_alert_state(&self, key: &str) -> Result<(), CoreError> {
+        sqlx::query(
+            "UPDATE monitors SET last_alerted_at = NULL, alert_count = NULL, \
+             alert_status = NULL WHERE monitor_key = $1",
+        )
+        .bind(key)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// List all monitors, across all projects.
+    pub async fn list_monitors(&self) -> Result<Vec<Monitor>, CoreError> {
+        let sql = format!("SELECT {MONITOR_COLUMNS} FROM monitors ORDER BY monitor_key");
+        let rows = sqlx::query(&sql).fetch_all(&self.pool).await?;
+
+        Ok(rows
+            .iter()
+            .map(monitor_from_row)
+            .collect::<sqlx::Result<_>>()?)
+    }
+
+    /// Delete a monitor by project and slug.
+    ///
+    /// Returns `CoreError::NotFound` if the monitor does not exist.
+    pub async fn delete_monitor(&self, id: &MonitorId) -> Result<(), CoreError> {
+        let result = sqlx::query("DELETE FROM monitors WHERE monitor_key = $1")
+            .bind(id.key())
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(CoreError::NotFound(id.slug.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Set or clear the paused state for a monitor. Pausing also clears its
+    /// alert state.
+    ///
+    /// Returns `CoreError::NotFound` if the monitor does not exist.
+    pub async fn set_paused(&self, id: &MonitorId, paused: bool) -> Result<(), CoreError> {
+        let sql = if paused {
+            "UPDATE monitors SET paused = 1, last_alerted_at = NULL, alert_count = NULL, \
+             alert_status = NULL WHERE monitor_key = $1"
+        } else {
+            "UPDATE monitors SET paused = NULL WHERE monitor_key = $1"
+        };
+        let result = sqlx::query(sql).bind(id.key()).execute(&self.pool).await?;
+
+        if result.rows_affected() == 0 {
+            return Err(CoreError::NotFound(id.slug.to_string()));
+        }
+        Ok(())
+    }
+
+    // -- API keys ----------------------------------------------------------
+
+    /// Get an API key by its hash (or plaintext token, for unmigrated keys).
+    pub async fn get_key(&self, key_hash: &str) -> Result<Option<ApiKeyRecord>, CoreError> {
+        let sql = format!("SELECT {API_KEY_COLUMNS} FROM api_keys WHERE key_hash = $1");
+        let row = sqlx::query(&sql)
+            .bind(key_hash)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(row.as_ref().map(api_key_from_row).transpose()?)
+    }
+
+    /// Store an API key, replacing any key with the same hash.
+    pub async fn put_key(&self, record: &ApiKeyRecord) -> Result<(), CoreError> {
+        let sql = format!(
+            "INSERT INTO api_keys ({API_KEY_COLUMNS}) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10) \
+             ON CONFLICT (key_hash) DO UPDATE SET \
+                project = excluded.project, \
+                key_prefix = excluded.key_prefix, \
+                description = excluded.description, \
+                created_at = excluded.created_at, \
+                scopes = excluded.scopes, \
+                slug_prefix = excluded.slug_prefix, \
+                expires_at = excluded.expires_at, \
+                revoked_at = excluded.revoked_at, \
+                last_used_at = excluded.last_used_at"
+        );
+        sqlx::query(&sql)
+            .bind(&record.key_hash)
+            .bind(&record.project)
+            .bind(&record.key_prefix)
+            .bind(&record.description)
+            .bind(record.created_at)
+            .bind(to_json(&record.scopes)?)
+            .bind(&record.slug_prefix)
+            .bind(record.expires_at)
+            .bind(record.revoked_at)
+            .bind(record.last_used_at)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Delete an API key by its hash (or plaintext token, for unmigrated keys).
+    pub async fn delete_key(&self, key_hash: &str) -> Result<(), CoreError> {
+        sqlx::query("DELETE FROM api_keys WHERE key_hash = $1")
+            .bind(key_hash)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// List all API keys.
+    pub async fn list_keys(&self) -> Result<Vec<ApiKeyRecord>, CoreError> {
+        let sql = format!("SELECT {API_KEY_COLUMNS} FROM api_keys ORDER BY created_at");
+        let rows = sqlx::query(&sql).fetch_all(&self.pool).await?;
+
+        Ok(rows
+            .iter()
+            .map(api_key_from_row)
+            .collect::<sqlx::Result<_>>()?)
+    }
+
+    /// Record that a key was used at `now_epoch`.
+    pub async fn touch_key(&self, key_hash: &str, now_epoch: i64) -> Result<(), CoreError> {
+        self.update_key(key_hash, "last_used_at", now_epoch).await
+    }
+
+    /// Revoke a key as of `now_epoch`.
+    ///
+    /// Returns `CoreError::NotFound` if the key does not exist.
+    pub async fn revoke_key(&self, key_hash: &str, now_epoch: i64) -> Result<(), CoreError> {
+        self.update_key(key_hash, "revoked_at", now_epoch).await
+    }
+
+    /// Set the expiry of a key to `expires_at` (Unix epoch seconds).
+    ///
+    /// Returns `CoreError::NotFound` if the key does not exist.
+    pub async fn set_key_expiry(&self, key_hash: &str, expires_at: i64) -> Result<(), CoreError> {
+        self.update_key(key_hash, "expires_at", expires_at).await
+    }
+
+    /// Set `column` to `value` on an existing key.
+    async fn update_key(&self, key_hash: &str, column: &str, value: i64) -> Result<(), CoreError> {
+        let sql = format!("UPDATE api_keys SET {column} = $1 WHERE key_hash = $2");
+        let result = sqlx::query(&sql)
+            .bind(value)
+            .bind(key_hash)
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(CoreError::NotFound("API key".to_string()));
+        }
+        Ok(())
+    }
+
+    // -- Audit log ---------------------------------------------------------
+
+    /// Append an entry to the audit log, deleting entries past their
+    /// `expires_at`.
+    pub async fn record_audit(&self, entry: &AuditEntry) -> Result<(), CoreError> {
+        sqlx::query("DELETE FROM audit_log WHERE expires_at < $1")
+            .bind(entry.at)
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query(
+            "INSERT INTO audit_log (project, event_id, expires_at, entry) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(&entry.project)
+        .bind(&entry.event_id)
+        .bind(entry.expires_at)
+        .bind(serde_json::to_string(entry)?)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// List up to `limit` entries of `project` made at or after `since_epoch`,
+    /// newest first, starting before the entry `before` if given.
+    pub async fn list_audit(
+        &self,
+        project: &str,
+        since_epoch: i64,
+        before: Option<&str>,
+        limit: i32,
+    ) -> Result<Vec<AuditEntry>, CoreError> {
+        let query = match before {
+            Some(_) => {
+                "SELECT entry FROM audit_log WHERE project = $1 AND event_id >= $2 \
+                 AND event_id < $3 ORDER BY event_id DESC LIMIT $4"
+            }
+            None => {
+                "SELECT entry FROM audit_log WHERE project = $1 AND event_id >= $2 \
+                 ORDER BY event_id DESC LIMIT $3"
+            }
+        };
+        let mut query = sqlx::query(query)
+            .bind(project)
+            .bind(audit_event_prefix(since_epoch));
+        if let Some(before) = before {
+            query = query.bind(before);
+        }
+        let rows = query.bind(i64::from(limit)).fetch_all(&self.pool).await?;
+
+        Ok(rows
+            .iter()
+            .filter_map(|row| from_json(row, "entry").transpose())
+            .collect::<sqlx::Result<_>>()?)
+    }
+
+    // -- Component status --------------------------------------------------
+
+    /// Record a completed checker cycle, replacing the previous one.
+    pub async fn put_checker_heartbeat(
+        &self,
+        heartbeat: &CheckerHeartbeat,
+    ) -> Result<(), CoreError> {
+        sqlx::query(
+            "INSERT INTO component_status (component, data) VALUES ($1, $2) \
+             ON CONFLICT (component) DO UPDATE SET data = excluded.data",
+        )
+        .bind(CHECKER_COMPONENT)
+        .bind(serde_json::to_string(heartbeat)?)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Get the last completed checker cycle.
+    pub async fn get_checker_heartbeat(&self) -> Result<Option<CheckerHeartbeat>, CoreError> {
+        let row = sqlx::query("SELECT data FROM component_status WHERE component = $1")
+            .bind(CHECKER_COMPONENT)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        match row {
+            Some(row) => Ok(from_json(&row, "data")?),
+            None => Ok(None),
+        }
+    }
+
+    /// Acquire or renew the lease `name` for `owner`, see
+    /// [`DynamoStatusStore::acquire_lease`].
+    ///
+    /// [`DynamoStatusStore::acquire_lease`]: super::DynamoStatusStore::acquire_lease
+    pub async fn acquire_lease(
+        &self,
+        name: &str,
+        owner: &str,
+        now_epoch: i64,
+        ttl_secs: i64,
+    ) -> Result<bool, CoreError> {
+        let result = sqlx::query(
+            "INSERT INTO component_status (component, owner, expires_at) VALUES ($1, $2, $3) \
+             ON CONFLICT (component) DO UPDATE SET \
+                owner = excluded.owner, expires_at = excluded.expires_at \
+             WHERE component_status.expires_at < $4 OR component_status.owner = excluded.owner",
+        )
+        .bind(name)
+        .bind(owner)
+        .bind(now_epoch + ttl_secs)
+        .bind(now_epoch)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Extend the lease `name` if `owner` still holds it and it hasn't
+    /// expired; `false` means it was lost.
+    pub async fn renew_lease(
+        &self,
+        name: &str,
+        owner: &str,
+        now_epoch: i64,
+        ttl_secs: i64,
+    ) -> Result<bool, CoreError> {
+        let result = sqlx::query(
+            "UPDATE component_status SET expires_at = $1 \
+             WHERE component = $2 AND owner = $3 AND expires_at >= $4",
+        )
+        .bind(now_epoch + ttl_secs)
+        .bind(name)
+        .bind(owner)
+        .bind(now_epoch)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Release the lease `name` if `owner` still holds it.
+    pub async fn release_lease(&self, name: &str, owner: &str) -> Result<(), CoreError> {
+        sqlx::query("DELETE FROM component_status WHERE component = $1 AND owner = $2")
+            .bind(name)
+            .bind(owner)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Record that the alert with `idempotency_key` is being sent, until
+    /// `expires_at`. Returns `false` if it was already recorded and hasn't
+    /// expired.
+    ///
+    /// Expired claims and leases are deleted first, as DynamoDB's TTL would.
+    pub async fn claim_alert(
+        &self,
+        idempotency_key: &str,
+        now_epoch: i64,
+        expires_at: i64,
+    ) -> Result<bool, CoreError> {
+        sqlx::query("DELETE FROM component_status WHERE expires_at < $1")
+            .bind(now_epoch)
+            .execute(&self.pool)
+            .await?;
+
+        let result = sqlx::query(
+            "INSERT INTO component_status (component, expires_at) VALUES ($1, $2) \
+             ON CONFLICT (component) DO NOTHING",
+        )
+        .bind(alert_sent_key(idempotency_key))
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Remove the claim on `idempotency_key`, so the alert can be retried.
+    pub async fn release_alert(&self, idempotency_key: &str) -> Result<(), CoreError> {
+        sqlx::query("DELETE FROM component_status WHERE component = $1")
+            .bind(alert_sent_key(idempotency_key))
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// Read a monitor row, splitting its key into project and slug.
+fn monitor_from_row(row: &AnyRow) -> sqlx::Result<Monitor> {
+    let key: String = row.try_get("monitor_key")?;
+    let (project, slug) = split_monitor_key(&key);
+    let alert_status = row
+        .try_get::<Option<String>, _>("alert_status")?
+        .map(|status| serde_json::from_value(serde_json::Value::String(status)))
+        .transpose()
+        .map_err(|e| sqlx::Error::Decode(Box::new(e)))?;
+
+    Ok(Monitor {
+        slug: slug.to_string(),
+        project: project.to_string(),
+        interval_secs: row.try_get::<i64, _>("interval_secs")? as u64,
+        last_ping: row.try_get("last_ping")?,
+        next_due: row.try_get("next_due")?,
+        check_partition: "CHECK".to_string(),
+        first_alerted_at: row.try_get("first_alerted_at")?,
+        last_alerted_at: row.try_get("last_alerted_at")?,
+        alert_count: row
+            .try_get::<Option<i64>, _>("alert_count")?
+            .map(|count| count as u32),
+        alert_status,
+        created_at: row.try_get("created_at")?,
+        paused: row
+            .try_get::<Option<i64>, _>("paused")?
+            .map(|paused| paused != 0),
+        expires_at: row.try_get("expires_at")?,
+        last_failure: from_json(row, "last_failure")?,
+        last_ping_meta: from_json(row, "last_ping_meta")?,
+        ping_token: row.try_get("ping_token")?,
+        timezone: row.try_get("timezone")?,
+    })
+}
+
+fn api_key_from_row(row: &AnyRow) -> sqlx::Result<ApiKeyRecord> {
+    Ok(ApiKeyRecord {
+        key_hash: row.try_get("key_hash")?,
+        project: row.try_get("project")?,
+        key_prefix: row.try_get("key_prefix")?,
+        description: row.try_get("description")?,
+        created_at: row.try_get("created_at")?,
+        scopes: from_json(row, "scopes")?,
+        slug_prefix: row.try_get("slug_prefix")?,
+        expires_at: row.try_get("expires_at")?,
+        revoked_at: row.try_get("revoked_at")?,
+        last_used_at: row.try_get("last_used_at")?,
+    })
+}
+
+/// Serialize an optional value to a nullable JSON text column.
+fn to_json<T: serde::Serialize>(value: &Option<T>) -> serde_json::Result<Option<String>> {
+    value.as_ref().map(serde_json::to_string).transpose()
+}
+
+/// Deserialize the nullable JSON text `column` of `row`.
+fn from_json<T: serde::de::DeserializeOwned>(
+    row: &AnyRow,
+    column: &str,
+) -> sqlx::Result<Option<T>> {
+    row.try_get::<Option<String>, _>(column)?
+        .as_deref()
+        .map(serde_json::from_str)
+        .transpose()
+        .map_err(|e| sqlx::Error::Decode(Box::new(e)))
+}
+
+/// Generate a new unguessable ping token (random UUIDv4).
+fn new_ping_token() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Failure, Slug};
+
+    // Each test below runs on SQLite, and on PostgreSQL in `postgres` when
+    // enabled.
+
+    /// A store on a fresh SQLite database in a temporary directory.
+    async fn sqlite() -> (SqlStore, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!(
+            "sqlite://{}?mode=rwc",
+            dir.path().join("heartbeat.db").display()
+        );
+        (SqlStore::connect(&url).await.unwrap(), dir)
+    }
+
+    fn monitor(slug: &str, now: i64) -> Monitor {
+        Monitor {
+            slug: slug.to_string(),
+            project: "team-a".to_string(),
+            interval_secs: 60,
+            last_ping: now,
+            next_due: now + 60,
+            check_partition: "CHECK".to_string(),
+            first_alerted_at: None,
+            last_alerted_at: None,
+            alert_count: None,
+            alert_status: None,
+            created_at: now,
+            paused: None,
+            expires_at: now + 86_400,
+            last_failure: None,
+            last_ping_meta: None,
+            ping_token: None,
+            timezone: None,
+        }
+    }
+
+    fn id(slug: &str) -> MonitorId {
+        MonitorId {
+            project: "team-a".to_string(),
+            slug: Slug::new(slug).unwrap(),
+        }
+    }
+
+    async fn upsert_keeps_creation_and_token(store: &SqlStore) {
+        let created = store
+            .upsert_monitor(&monitor("backup", 1000))
+            .await
+            .unwrap();
+        assert_eq!(created.project, "team-a");
+        let token = created.ping_token.clone().unwrap();
+
+        let mut ping = monitor("backup", 2000);
+        ping.created_at = 2000;
+        let updated = store.upsert_monitor(&ping).await.unwrap();
+        assert_eq!(updated.created_at, 1000);
+        assert_eq!(updated.last_ping, 2000);
+        assert_eq!(updated.ping_token.as_deref(), Some(token.as_str()));
+
+        let found = store.find_monitor_by_ping_token(&token).await.unwrap();
+        assert_eq!(found, Some(id("backup")));
+    }
+
+    async fn failure_keeps_last_ping_and_alert_state_round_trips(store: &SqlStore) {
+        store
+            .upsert_monitor(&monitor("backup", 1000))
+            .await
+            .unwrap();
+
+        let mut failed = monitor("backup", 3000);
+        failed.last_failure = Some(Failure {
+            at: 3000,
+            exit_code: Some(2),
+            message: Some("disk full".into()),
+            log: None,
+        });
+        let stored = store.record_failure(&failed).await.unwrap();
+        assert_eq!(stored.last_ping, 1000);
+        assert!(stored.is_failed());
+
+        let key = stored.key();
+        store
+            .update_alert_state(&key, 3000, 3100, 2, MonitorStatus::Failed)
+            .await
+            .unwrap();
+        let alerted = store.query_alerted().await.unwrap();
+        assert_eq!(alerted.len(), 1);
+        assert_eq!(alerted[0].alert_status, Some(MonitorStatus::Failed));
+        assert_eq!(alerted[0].alert_count, Some(2));
+
+        store.set_paused(&id("backup"), true).await.unwrap();
+        let paused = store.get_monitor(&id("backup")).await.unwrap().unwrap();
+        assert_eq!(paused.paused, Some(true));
+        assert_eq!(paused.last_alerted_at, None);
+
+        let pinged = store
+            .upsert_monitor(&monitor("backup", 3000))
+            .await
+            .unwrap();
+        assert_eq!(pinged.last_failure, None);
+        assert!(!pinged.is_failed());
+
+        assert!(matches!(
+            store.delete_monitor(&id("missing")).await,
+            Err(CoreError::NotFound(_))
+        ));
+    }
+
+    async fn overdue_query_deletes_expired_monitors(store: &SqlStore) {
+        store.upsert_monitor(&monitor("due", 1000)).await.unwrap();
+        let mut expired = monitor("expired", 1000);
+        expired.expires_at = 1500;
+        store.upsert_monitor(&expired).await.unwrap();
+
+        let overdue = store.query_overdue(2000).await.unwrap();
+        assert_eq!(overdue.len(), 1);
+        assert_eq!(overdue[0].slug, "due");
+        assert_eq!(store.list_monitors().await.unwrap().len(), 1);
+    }
+
+    async fn leases_and_alert_claims(store: &SqlStore) {
+        assert!(store.acquire_lease("lease", "a", 100, 60).await.unwrap());
+        assert!(!store.acquire_lease("lease", "b", 120, 60).await.unwrap());
+        assert!(store.renew_lease("lease", "a", 150, 60).await.unwrap());
+        assert!(!store.renew_lease("lease", "b", 150, 60).await.unwrap());
+        // Expired at 210
+        assert!(store.acquire_lease("lease", "b", 211, 60).await.unwrap());
+        store.release_lease("lease", "a").await.unwrap();
+        assert!(!store.renew_lease("lease", "a", 212, 60).await.unwrap());
+
+        assert!(store.claim_alert("k", 100, 200).await.unwrap());
+        assert!(!store.claim_alert("k", 150, 250).await.unwrap());
+        assert!(store.claim_alert("k", 201, 300).await.unwrap());
+        store.release_alert("k").await.unwrap();
+        assert!(store.claim_alert("k", 202, 300).await.unwrap());
+    }
+
+    async fn migrations_are_applied_once(store: &SqlStore) {
+        store.migrate().await.unwrap();
+        let count: i64 = sqlx::query("SELECT COUNT(*) AS n FROM schema_migrations")
+            .fetch_one(&store.pool)
+            .await
+            .unwrap()
+            .try_get("n")
+            .unwrap();
+        assert_eq!(count, MIGRATIONS.len() as i64);
+        store.check_table().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn sqlite_upsert_keeps_creation_and_token() {
+        let (store, _dir) = sqlite().await;
+        upsert_keeps_creation_and_token(&store).await;
+    }
+
+    #[tokio::test]
+    async fn sqlite_failure_keeps_last_ping_and_alert_state_round_trips() {
+        let (store, _dir) = sqlite().await;
+        failure_keeps_last_ping_and_alert_state_round_trips(&store).await;
+    }
+
+    #[tokio::test]
+    async fn sqlite_overdue_query_deletes_expired_monitors() {
+        let (store, _dir) = sqlite().await;
+        overdue_query_deletes_expired_monitors(&store).await;
+    }
+
+    #[tokio::test]
+    async fn sqlite_leases_and_alert_claims() {
+        let (store, _dir) = sqlite().await;
+        leases_and_alert_claims(&store).await;
+    }
+
+    #[tokio::test]
+    async fn sqlite_migrations_are_applied_once() {
+        let (store, _dir) = sqlite().await;
+        migrations_are_applied_once(&store).await;
+    }
+
+    /// A store on the PostgreSQL database in `TEST_POSTGRES_URL`, with its
+    /// heartbeat tables dropped first.
+    async fn postgres_store(url: &str) -> SqlStore {
+        sqlx::any::install_default_drivers();
+        let pool = AnyPool::connect(url).await.unwrap();
+        for table in [
+            "monitors",
+            "api_keys",
+            "audit_log",
+            "component_status",
+            "schema_migrations",
+        ] {
+            sqlx::query(&format!("DROP TABLE IF EXISTS {table}"))
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+        SqlStore::connect(url).await.unwrap()
+    }
+
+    /// Runs the tests above on PostgreSQL. The heartbeat tables of the
+    /// database are dropped, so use a database for testing only:
+    ///
+    /// `TEST_POSTGRES_URL=postgres://... cargo test -p heartbeat-core --features sql -- --ignored`
+    #[tokio::test]
+    #[ignore = "needs a PostgreSQL database in TEST_POSTGRES_URL"]
+    async fn postgres() {
+        let url = std::env::var("TEST_POSTGRES_URL").expect("TEST_POSTGRES_URL is not set");
+        upsert_keeps_creation_and_token(&postgres_store(&url).await).await;
+        failure_keeps_last_ping_and_alert_state_round_trips(&postgres_store(&url).await).await;
+        overdue_query_deletes_expired_monitors(&postgres_store(&url).await).await;
+        leases_and_alert_claims(&postgres_store(&url).await).await;
+        migrations_are_applied_once(&postgres_store(&url).await).await;
+    }
+}
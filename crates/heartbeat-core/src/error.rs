@@ -12,6 +12,13 @@ pub enum CoreError {
     #[error("Serialization error: {0}")]
     Serde(#[from] serde_dynamo::Error),
 
+    #[cfg(feature = "sql")]
+    #[error("SQL error: {0}")]
+    Sql(#[from] sqlx::Error),
+
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
     #[error("Slug validation error: {0}")]
     Slug(#[from] crate::model::SlugError),
 
@@ -1,7 +1,19 @@
+pub mod clock;
 pub mod db;
 pub mod error;
+pub mod metrics;
 pub mod model;
 
-pub use db::DynamoStore;
+pub use clock::{Clock, ManualClock};
+#[cfg(feature = "sql")]
+pub use db::SqlStore;
+pub use db::{
+    ApiKeyStore, AuditStore, DynamoStore, DynamoTables, MonitorStore, StatusStore, StorageConfig,
+    Stores,
+};
 pub use error::CoreError;
-pub use model::{Monitor, MonitorStatus, Slug, SlugError};
+pub use model::{
+    API_KEY_PREFIX_LEN, ApiKeyRecord, AuditAction, AuditEntry, CHECK_CYCLE_LEASE, CHECKER_LEASE,
+    CheckerHeartbeat, DEFAULT_PROJECT, Failure, Monitor, MonitorId, MonitorStatus, PingMetadata,
+    Scope, Slug, SlugError, alert_sent_key, hash_api_key, monitor_key, split_monitor_key,
+};
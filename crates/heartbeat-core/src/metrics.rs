@@ -0,0 +1,89 @@
+//! DynamoDB call metrics, recorded through the [`metrics`] facade.
+//!
+//! They are no-ops unless the binary installs a recorder, as `heartbeat-api`
+//! does for its `/metrics` endpoint.
+
+use std::time::Instant;
+
+use aws_sdk_dynamodb::config::interceptors::{
+    BeforeSerializationInterceptorContextRef, FinalizerInterceptorContextRef,
+};
+use aws_sdk_dynamodb::config::{ConfigBag, Intercept, RuntimeComponents};
+use aws_smithy_runtime_api::box_error::BoxError;
+use aws_smithy_runtime_api::client::orchestrator::{Metadata, OrchestratorError};
+use aws_smithy_types::config_bag::{Storable, StoreReplace};
+
+/// Histogram of DynamoDB call durations, labelled by `operation`.
+pub const DYNAMODB_DURATION: &str = "heartbeat_dynamodb_request_duration_seconds";
+
+/// Counter of failed DynamoDB calls, labelled by `operation` and `kind`.
+pub const DYNAMODB_ERRORS: &str = "heartbeat_dynamodb_errors_total";
+
+/// SDK interceptor timing every DynamoDB call, including retries.
+#[derive(Debug)]
+pub(crate) struct DynamoMetrics;
+
+/// Start of the current call, kept in the call's config bag.
+#[derive(Debug, Clone)]
+struct CallStart(Instant);
+
+impl Storable for CallStart {
+    type Storer = StoreReplace<Self>;
+}
+
+impl Intercept for DynamoMetrics {
+    fn name(&self) -> &'static str {
+        "DynamoMetrics"
+    }
+
+    fn read_before_execution(
+        &self,
+        _context: &BeforeSerializationInterceptorContextRef<'_>,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        cfg.interceptor_state().store_put(CallStart(Instant::now()));
+        Ok(())
+    }
+
+    fn read_after_execution(
+        &self,
+        context: &FinalizerInterceptorContextRef<'_>,
+        _runtime_components: &RuntimeComponents,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        let operation = cfg
+            .load::<Metadata>()
+            .map_or("unknown", Metadata::name)
+            .to_string();
+
+        if let Some(CallStart(start)) = cfg.load::<CallStart>() {
+            metrics::histogram!(DYNAMODB_DURATION, "operation" => operation.clone())
+                .record(start.elapsed());
+        }
+
+        if let Some(Err(error)) = context.inner().output_or_error() {
+            metrics::counter!(
+                DYNAMODB_ERRORS,
+                "operation" => operation,
+                "kind" => error_kind(error)
+            )
+            .increment(1);
+        }
+
+        Ok(())
+    }
+}
+
+/// Classify a failed call. `operation` errors are responses from DynamoDB,
+/// including conditional check failures and throttling.
+fn error_kind<E>(error: &OrchestratorError<E>) -> &'static str {
+    if error.is_operation_error() {
+        "operation"
+    } else if error.is_timeout_error() {
+        "timeout"
+    } else if error.is_connector_error() {
+        "connector"
+    } else {
+        "other"
+    }
+}
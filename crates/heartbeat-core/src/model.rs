@@ -1,6 +1,8 @@
 use std::fmt;
+use std::str::FromStr;
 
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use thiserror::Error;
 
 // ---------------------------------------------------------------------------
@@ -82,6 +84,58 @@ pub enum SlugError {
     InvalidHyphenPosition,
 }
 
+// ---------------------------------------------------------------------------
+// Projects
+// ---------------------------------------------------------------------------
+
+/// Project of API keys created before projects existed, and of their monitors.
+pub const DEFAULT_PROJECT: &str = "default";
+
+fn default_project() -> String {
+    DEFAULT_PROJECT.to_string()
+}
+
+/// The partition key of a monitor in the monitors table: `project#slug`.
+///
+/// Monitors of the default project keep their bare slug as key, so monitors
+/// created before projects existed need no migration. Neither projects nor
+/// slugs can contain `#`, so keys are unambiguous.
+pub fn monitor_key(project: &str, slug: &str) -> String {
+    if project == DEFAULT_PROJECT {
+        slug.to_string()
+    } else {
+        format!("{project}#{slug}")
+    }
+}
+
+/// Split a monitor partition key into project and slug; the inverse of [`monitor_key`].
+pub fn split_monitor_key(key: &str) -> (&str, &str) {
+    key.split_once('#').unwrap_or((DEFAULT_PROJECT, key))
+}
+
+/// Identifies a monitor: a slug within a project.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MonitorId {
+    pub project: String,
+    pub slug: Slug,
+}
+
+impl MonitorId {
+    /// Parse a monitor partition key.
+    pub fn from_key(key: &str) -> Result<Self, SlugError> {
+        let (project, slug) = split_monitor_key(key);
+        Ok(Self {
+            project: project.to_string(),
+            slug: Slug::new(slug)?,
+        })
+    }
+
+    /// The partition key of this monitor, see [`monitor_key`].
+    pub fn key(&self) -> String {
+        monitor_key(&self.project, self.slug.as_ref())
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Monitor
 // ---------------------------------------------------------------------------
@@ -89,9 +143,17 @@ pub enum SlugError {
 /// A heartbeat monitor stored in DynamoDB.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Monitor {
-    /// Unique identifier (validated as a Slug at API boundaries).
+    /// Identifier within the project (validated as a Slug at API boundaries).
+    ///
+    /// Stored under `slug` as the partition key `project#slug` (see
+    /// [`monitor_key`]), which the store splits into `project` and `slug`.
     pub slug: String,
 
+    /// Project the monitor belongs to. Part of the partition key, not stored
+    /// separately.
+    #[serde(skip, default = "default_project")]
+    pub project: String,
+
     /// Expected check-in interval in seconds.
     pub interval_secs: u64,
 
@@ -116,6 +178,10 @@ pub struct Monitor {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub alert_count: Option<u32>,
 
+    /// Status that triggered the current alert episode (if any).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alert_status: Option<MonitorStatus>,
+
     /// Unix epoch seconds when this monitor was created.
     pub created_at: i64,
 
@@ -125,32 +191,489 @@ pub struct Monitor {
 
     /// TTL: last_ping + 90 days (in seconds). DynamoDB auto-deletes after this.
     pub expires_at: i64,
+
+    /// Details of the most recent explicitly reported failure (if any),
+    /// cleared by the next successful ping.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_failure: Option<Failure>,
+
+    /// Request metadata captured with the most recent successful ping (if any).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_ping_meta: Option<PingMetadata>,
+
+    /// Secret token for the monitor's `/ping/{token}` URL. Generated by the
+    /// store on creation; missing on monitors created before tokens existed
+    /// until their next ping.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ping_token: Option<String>,
+
+    /// IANA time zone the monitor's alerts show times in, instead of the
+    /// checker's (if set).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+}
+
+impl Monitor {
+    /// The partition key of this monitor, see [`monitor_key`].
+    pub fn key(&self) -> String {
+        monitor_key(&self.project, &self.slug)
+    }
+
+    /// Name for messages: the slug, prefixed by the project outside the
+    /// default project.
+    pub fn display_name(&self) -> String {
+        if self.project == DEFAULT_PROJECT {
+            self.slug.clone()
+        } else {
+            format!("{}/{}", self.project, self.slug)
+        }
+    }
+
+    /// Whether the most recent event for this monitor is a reported failure,
+    /// i.e. no successful ping has been received since.
+    ///
+    /// Pings clear `last_failure`, so a failure in the same second as the
+    /// last ping was reported after it.
+    pub fn is_failed(&self) -> bool {
+        self.last_failure
+            .as_ref()
+            .is_some_and(|failure| failure.at >= self.last_ping)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Failure
+// ---------------------------------------------------------------------------
+
+/// An explicit failure reported via `/fail` or a non-zero exit code ping.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Failure {
+    /// Unix epoch seconds when the failure was reported.
+    pub at: i64,
+
+    /// Exit code of the failed job (if reported).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+
+    /// Short human-readable failure reason (if reported).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+
+    /// Tail of the job output, truncated at the API boundary (if reported).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log: Option<String>,
+}
+
+// ---------------------------------------------------------------------------
+// PingMetadata
+// ---------------------------------------------------------------------------
+
+/// Request metadata captured with a successful ping.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PingMetadata {
+    /// HTTP method of the ping request.
+    pub method: String,
+
+    /// Client IP address (if known).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_ip: Option<String>,
+
+    /// Client `User-Agent` header (if sent).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+
+    /// Request body, truncated at the API boundary (if sent).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
 }
 
 // ---------------------------------------------------------------------------
-// MonitorStatus (derived, not stored)
+// API keys
 // ---------------------------------------------------------------------------
 
-/// Derived monitor status -- not stored in DynamoDB.
+/// A permission granted to an API key.
+///
+/// `Admin` grants every scope and `Manage` also grants `Read`; `Ping` and
+/// `Read` grant only themselves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    /// Record pings and failures.
+    Ping,
+    /// List and inspect monitors.
+    Read,
+    /// Delete, pause, unpause and rotate ping tokens of monitors.
+    Manage,
+    /// Everything, including administrative endpoints.
+    Admin,
+}
+
+impl Scope {
+    /// All scopes, in increasing order of privilege.
+    pub const ALL: [Scope; 4] = [Scope::Ping, Scope::Read, Scope::Manage, Scope::Admin];
+
+    /// Whether holding this scope grants `required`.
+    pub fn grants(self, required: Scope) -> bool {
+        match self {
+            Scope::Admin => true,
+            Scope::Manage => matches!(required, Scope::Manage | Scope::Read),
+            other => other == required,
+        }
+    }
+
+    /// The lowercase name used in storage and on the command line.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Scope::Ping => "ping",
+            Scope::Read => "read",
+            Scope::Manage => "manage",
+            Scope::Admin => "admin",
+        }
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Scope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Scope::ALL
+            .into_iter()
+            .find(|scope| scope.as_str() == s)
+            .ok_or_else(|| format!("unknown scope '{s}' (expected ping, read, manage or admin)"))
+    }
+}
+
+/// Length of the non-secret key prefix stored to identify a key.
+pub const API_KEY_PREFIX_LEN: usize = 8;
+
+/// Hash a bearer token for storage and lookup: hex-encoded SHA-256.
+///
+/// Keys are 256-bit random values, so a plain (unsalted) hash is enough to
+/// make a leaked table useless without enabling brute force.
+pub fn hash_api_key(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// An API key item stored in the keys table.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiKeyRecord {
+    /// SHA-256 of the bearer token, see [`hash_api_key`] (partition key).
+    ///
+    /// Stored under the `api_key` attribute, which holds the plaintext token
+    /// for keys created before hashing that have not been migrated yet.
+    #[serde(rename = "api_key")]
+    pub key_hash: String,
+
+    /// Project the key belongs to. It only gives access to that project's
+    /// monitors. Keys created before projects existed belong to [`DEFAULT_PROJECT`].
+    #[serde(default = "default_project")]
+    pub project: String,
+
+    /// The first [`API_KEY_PREFIX_LEN`] characters of the token, to identify
+    /// the key. `None` for unmigrated plaintext keys.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_prefix: Option<String>,
+
+    /// Free-form description of the key's owner or purpose.
+    #[serde(default)]
+    pub description: String,
+
+    /// Unix epoch seconds when this key was created.
+    pub created_at: i64,
+
+    /// Scopes granted to this key. Keys created before scopes existed have
+    /// none stored and keep full access.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scopes: Option<Vec<Scope>>,
+
+    /// If set, the key may only access monitors whose slug starts with this prefix.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slug_prefix: Option<String>,
+
+    /// Unix epoch seconds after which the key is no longer accepted (if any).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+
+    /// Unix epoch seconds when the key was revoked (if it was).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revoked_at: Option<i64>,
+
+    /// Unix epoch seconds when the key was last used, at coarse resolution.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_used_at: Option<i64>,
+}
+
+impl ApiKeyRecord {
+    /// Build a record for a new bearer token, storing only its hash and prefix.
+    ///
+    /// The key belongs to the default project and scopes, slug prefix and
+    /// expiry are unset; callers fill them in.
+    pub fn new(token: &str, description: impl Into<String>, created_at: i64) -> Self {
+        Self {
+            key_hash: hash_api_key(token),
+            key_prefix: Some(token.chars().take(API_KEY_PREFIX_LEN).collect()),
+            project: default_project(),
+            description: description.into(),
+            created_at,
+            scopes: None,
+            slug_prefix: None,
+            expires_at: None,
+            revoked_at: None,
+            last_used_at: None,
+        }
+    }
+
+    /// The non-secret identifier of the key: its prefix (taken from the
+    /// token itself for unmigrated plaintext keys).
+    pub fn prefix(&self) -> String {
+        match &self.key_prefix {
+            Some(prefix) => prefix.clone(),
+            None => self.key_hash.chars().take(API_KEY_PREFIX_LEN).collect(),
+        }
+    }
+
+    /// Whether this is a key created before hashing, still stored in plaintext.
+    pub fn is_plaintext(&self) -> bool {
+        self.key_prefix.is_none()
+    }
+
+    /// Whether the key is accepted at `now_epoch`: neither revoked nor expired.
+    pub fn is_active(&self, now_epoch: i64) -> bool {
+        self.revoked_at.is_none() && self.expires_at.is_none_or(|expires| expires > now_epoch)
+    }
+
+    /// Whether this key grants `required`.
+    pub fn grants(&self, required: Scope) -> bool {
+        match &self.scopes {
+            Some(scopes) => scopes.iter().any(|scope| scope.grants(required)),
+            None => true,
+        }
+    }
+
+    /// Whether this key may access the monitor identified by `slug`.
+    pub fn allows_slug(&self, slug: &str) -> bool {
+        self.slug_prefix
+            .as_deref()
+            .is_none_or(|prefix| slug.starts_with(prefix))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Audit log
+// ---------------------------------------------------------------------------
+
+/// How long audit entries are kept: 1 year in seconds.
+pub const AUDIT_TTL_SECS: i64 = 365 * 24 * 60 * 60;
+
+/// A management action recorded in the audit log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    /// A monitor was created by its first ping or failure.
+    Create,
+    /// A monitor's interval was changed by a ping.
+    Update,
+    Delete,
+    Pause,
+    Unpause,
+    RotatePingToken,
+    CreateKey,
+    RevokeKey,
+    RotateKey,
+    /// A plaintext key was replaced by its hash.
+    MigrateKey,
+}
+
+/// An audit log item stored in the audit table.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Project the action applies to (partition key).
+    pub project: String,
+
+    /// Sort key: `at` zero-padded to 10 digits, `#`, and a random UUID, so
+    /// entries sort chronologically and never collide.
+    pub event_id: String,
+
+    /// Unix epoch seconds when the action happened.
+    pub at: i64,
+
+    pub action: AuditAction,
+
+    /// Prefix of the API key that performed the action; `None` for the key CLI.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_prefix: Option<String>,
+
+    /// Description of the API key (or CLI user) that performed the action.
+    pub actor: String,
+
+    /// Slug of the affected monitor, for monitor actions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slug: Option<String>,
+
+    /// Prefix of the affected API key, for key actions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_key: Option<String>,
+
+    /// Client IP of the request, for actions made through the API.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_ip: Option<String>,
+
+    /// Free-form details, e.g. the old and new interval of an update.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+
+    /// TTL: `at` + [`AUDIT_TTL_SECS`]. DynamoDB auto-deletes after this.
+    pub expires_at: i64,
+}
+
+impl AuditEntry {
+    /// Create an entry for `action` in `project` at `at`, performed by `actor`.
+    ///
+    /// Optional fields are unset; callers fill them in.
+    pub fn new(
+        project: impl Into<String>,
+        at: i64,
+        action: AuditAction,
+        actor: impl Into<String>,
+    ) -> Self {
+        Self {
+            project: project.into(),
+            event_id: format!("{}#{}", audit_event_prefix(at), uuid::Uuid::new_v4()),
+            at,
+            action,
+            key_prefix: None,
+            actor: actor.into(),
+            slug: None,
+            target_key: None,
+            source_ip: None,
+            detail: None,
+            expires_at: at + AUDIT_TTL_SECS,
+        }
+    }
+}
+
+/// The time part of an audit `event_id`, which sorts like `at`.
+pub fn audit_event_prefix(at: i64) -> String {
+    format!("{:010}", at.max(0))
+}
+
+// ---------------------------------------------------------------------------
+// Checker status
+// ---------------------------------------------------------------------------
+
+/// Partition key of the checker's item in the status table.
+pub const CHECKER_COMPONENT: &str = "checker";
+
+/// Partition key of the lease electing the checker that runs the cycle
+/// among several daemon replicas.
+pub const CHECKER_LEASE: &str = "checker-lease";
+
+/// Partition key of the lease held for the duration of a check cycle, so
+/// that cycles never overlap, whichever checker runs them.
+pub const CHECK_CYCLE_LEASE: &str = "check-cycle";
+
+/// Partition key of the item recording that the alert with `idempotency_key`
+/// was sent.
+pub fn alert_sent_key(idempotency_key: &str) -> String {
+    format!("alert#{idempotency_key}")
+}
+
+/// The last completed check cycle, recorded by the checker so that its own
+/// absence can be detected.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckerHeartbeat {
+    /// Unix epoch seconds when the cycle completed.
+    pub at: i64,
+
+    /// Monitors returned as overdue or failed.
+    pub overdue: u64,
+
+    /// Monitors with an active alert, checked for recovery.
+    pub alerted: u64,
+
+    /// Alerts and recovery notifications sent.
+    pub alerts_sent: u64,
+
+    /// Errors during the cycle, e.g. alerts that could not be sent.
+    pub errors: u64,
+
+    /// The last error of the cycle, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+impl CheckerHeartbeat {
+    /// Whether the cycle completed more than `max_age_secs` before `now_epoch`.
+    pub fn is_stale(&self, now_epoch: i64, max_age_secs: i64) -> bool {
+        now_epoch - self.at > max_age_secs
+    }
+}
+
+// ---------------------------------------------------------------------------
+// MonitorStatus (derived; stored only as the status of an alert)
+// ---------------------------------------------------------------------------
+
+/// Monitor status, derived from the monitor at a point in time by
+/// [`MonitorStatus::derive`] for the API and metrics. The current status is
+/// never stored; only the status an alert was sent for is persisted, as
+/// [`Monitor::alert_status`], so the checker can tell which kind of
+/// incident it is repeating or recovering from.
 ///
 /// - `Paused`: `monitor.paused == Some(true)`
+/// - `Failed`: a failure was reported after the last successful ping
 /// - `Overdue`: `monitor.next_due < now`
+/// - `New`: no ping received since the one that created the monitor
 /// - `Ok`: otherwise
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum MonitorStatus {
+    New,
     Ok,
     Overdue,
+    Failed,
     Paused,
 }
 
 impl MonitorStatus {
+    /// All statuses.
+    pub const ALL: [MonitorStatus; 5] = [
+        MonitorStatus::New,
+        MonitorStatus::Ok,
+        MonitorStatus::Overdue,
+        MonitorStatus::Failed,
+        MonitorStatus::Paused,
+    ];
+
+    /// The lowercase name used in API responses.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MonitorStatus::New => "new",
+            MonitorStatus::Ok => "ok",
+            MonitorStatus::Overdue => "overdue",
+            MonitorStatus::Failed => "failed",
+            MonitorStatus::Paused => "paused",
+        }
+    }
+
     /// Derive the status of a monitor at a given point in time.
     pub fn derive(monitor: &Monitor, now_epoch: i64) -> Self {
         if monitor.paused == Some(true) {
             Self::Paused
+        } else if monitor.is_failed() {
+            Self::Failed
         } else if monitor.next_due < now_epoch {
             Self::Overdue
+        } else if monitor.last_ping == monitor.created_at {
+            Self::New
         } else {
             Self::Ok
         }
@@ -220,9 +743,18 @@ mod tests {
 
     // -- MonitorStatus tests --
 
+    #[test]
+    fn status_names_match_serde() {
+        for status in MonitorStatus::ALL {
+            let json = serde_json::to_value(status).unwrap();
+            assert_eq!(json, status.as_str());
+        }
+    }
+
     fn make_monitor(next_due: i64, paused: Option<bool>) -> Monitor {
         Monitor {
             slug: "test".into(),
+            project: DEFAULT_PROJECT.into(),
             interval_secs: 300,
             last_ping: 1000,
             next_due,
@@ -230,9 +762,14 @@ mod tests {
             first_alerted_at: None,
             last_alerted_at: None,
             alert_count: None,
-            created_at: 1000,
+            alert_status: None,
+            created_at: 900,
             paused,
             expires_at: 1000 + 90 * 86400,
+            last_failure: None,
+            last_ping_meta: None,
+            ping_token: None,
+            timezone: None,
         }
     }
 
@@ -260,4 +797,234 @@ mod tests {
         let m = make_monitor(2000, Some(false));
         assert_eq!(MonitorStatus::derive(&m, 1500), MonitorStatus::Ok);
     }
+
+    #[test]
+    fn status_new_until_second_ping() {
+        let mut m = make_monitor(2000, None);
+        m.created_at = m.last_ping;
+        assert_eq!(MonitorStatus::derive(&m, 1500), MonitorStatus::New);
+    }
+
+    #[test]
+    fn status_new_monitor_can_be_overdue() {
+        let mut m = make_monitor(1000, None);
+        m.created_at = m.last_ping;
+        assert_eq!(MonitorStatus::derive(&m, 1500), MonitorStatus::Overdue);
+    }
+
+    // -- Failure tests --
+
+    fn make_failure(at: i64) -> Failure {
+        Failure {
+            at,
+            exit_code: Some(1),
+            message: None,
+            log: None,
+        }
+    }
+
+    #[test]
+    fn failed_when_failure_after_last_ping() {
+        let mut m = make_monitor(2000, None);
+        m.last_failure = Some(make_failure(1200));
+        assert!(m.is_failed());
+    }
+
+    #[test]
+    fn not_failed_after_successful_ping() {
+        let mut m = make_monitor(2000, None);
+        m.last_failure = Some(make_failure(900));
+        assert!(!m.is_failed());
+    }
+
+    #[test]
+    fn failed_when_failure_in_same_second_as_last_ping() {
+        let mut m = make_monitor(2000, None);
+        m.last_failure = Some(make_failure(m.last_ping));
+        assert!(m.is_failed());
+    }
+
+    #[test]
+    fn not_failed_without_failure() {
+        let m = make_monitor(2000, None);
+        assert!(!m.is_failed());
+    }
+
+    #[test]
+    fn status_failed() {
+        let mut m = make_monitor(1200, None);
+        m.last_failure = Some(make_failure(1200));
+        // Failed takes precedence over overdue
+        assert_eq!(MonitorStatus::derive(&m, 1500), MonitorStatus::Failed);
+    }
+
+    #[test]
+    fn status_failed_paused() {
+        let mut m = make_monitor(1200, Some(true));
+        m.last_failure = Some(make_failure(1200));
+        assert_eq!(MonitorStatus::derive(&m, 1500), MonitorStatus::Paused);
+    }
+
+    // -- API key tests --
+
+    fn make_key(scopes: Option<Vec<Scope>>, slug_prefix: Option<&str>) -> ApiKeyRecord {
+        ApiKeyRecord {
+            scopes,
+            slug_prefix: slug_prefix.map(String::from),
+            ..ApiKeyRecord::new("0123456789abcdef", "test", 1000)
+        }
+    }
+
+    #[test]
+    fn scope_parse_and_display() {
+        for scope in Scope::ALL {
+            assert_eq!(scope.to_string().parse::<Scope>(), Ok(scope));
+        }
+        assert!("root".parse::<Scope>().is_err());
+    }
+
+    #[test]
+    fn scope_grants() {
+        assert!(Scope::Admin.grants(Scope::Ping));
+        assert!(Scope::Manage.grants(Scope::Read));
+        assert!(!Scope::Manage.grants(Scope::Ping));
+        assert!(!Scope::Read.grants(Scope::Manage));
+        assert!(!Scope::Ping.grants(Scope::Read));
+    }
+
+    #[test]
+    fn key_without_scopes_has_full_access() {
+        let key = make_key(None, None);
+        assert!(Scope::ALL.into_iter().all(|scope| key.grants(scope)));
+    }
+
+    #[test]
+    fn key_with_scopes() {
+        let key = make_key(Some(vec![Scope::Ping, Scope::Read]), None);
+        assert!(key.grants(Scope::Ping));
+        assert!(key.grants(Scope::Read));
+        assert!(!key.grants(Scope::Manage));
+        assert!(!key.grants(Scope::Admin));
+    }
+
+    #[test]
+    fn key_slug_prefix() {
+        let key = make_key(None, Some("team-a-"));
+        assert!(key.allows_slug("team-a-backup"));
+        assert!(!key.allows_slug("team-b-backup"));
+        assert!(make_key(None, None).allows_slug("anything"));
+    }
+
+    #[test]
+    fn key_active_until_expiry() {
+        let mut key = make_key(None, None);
+        assert!(key.is_active(1500));
+        key.expires_at = Some(2000);
+        assert!(key.is_active(1999));
+        assert!(!key.is_active(2000));
+    }
+
+    #[test]
+    fn revoked_key_is_inactive() {
+        let mut key = make_key(None, None);
+        key.revoked_at = Some(1200);
+        assert!(!key.is_active(1500));
+    }
+
+    #[test]
+    fn new_key_stores_hash_and_prefix() {
+        let key = ApiKeyRecord::new("0123456789abcdef", "test", 1000);
+        assert_eq!(key.key_prefix.as_deref(), Some("01234567"));
+        assert_eq!(key.key_hash, hash_api_key("0123456789abcdef"));
+        assert!(!key.is_plaintext());
+    }
+
+    #[test]
+    fn hash_is_hex_sha256() {
+        assert_eq!(
+            hash_api_key("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn key_hash_stored_as_api_key_attribute() {
+        let key = ApiKeyRecord::new("0123456789abcdef", "test", 1000);
+        let item: serde_dynamo::Item = serde_dynamo::to_item(&key).unwrap();
+        assert!(item.contains_key("api_key"));
+        assert!(!item.contains_key("key_hash"));
+    }
+
+    // -- Project tests --
+
+    #[test]
+    fn monitor_key_roundtrip() {
+        assert_eq!(monitor_key("team-a", "backup"), "team-a#backup");
+        assert_eq!(split_monitor_key("team-a#backup"), ("team-a", "backup"));
+    }
+
+    #[test]
+    fn default_project_keeps_bare_slug() {
+        assert_eq!(monitor_key(DEFAULT_PROJECT, "backup"), "backup");
+        assert_eq!(split_monitor_key("backup"), (DEFAULT_PROJECT, "backup"));
+    }
+
+    #[test]
+    fn monitor_id_from_key() {
+        let id = MonitorId::from_key("team-a#backup").unwrap();
+        assert_eq!(id.project, "team-a");
+        assert_eq!(id.slug.as_ref(), "backup");
+        assert_eq!(id.key(), "team-a#backup");
+        assert!(MonitorId::from_key("team-a#Bad").is_err());
+    }
+
+    #[test]
+    fn display_name_includes_project() {
+        let mut m = make_monitor(2000, None);
+        assert_eq!(m.display_name(), "test");
+        m.project = "team-a".into();
+        assert_eq!(m.display_name(), "team-a/test");
+        assert_eq!(m.key(), "team-a#test");
+    }
+
+    #[test]
+    fn legacy_key_belongs_to_default_project() {
+        let mut item: serde_dynamo::Item = serde_dynamo::to_item(make_key(None, None)).unwrap();
+        item.remove("project");
+        let key: ApiKeyRecord = serde_dynamo::from_item(item).unwrap();
+        assert_eq!(key.project, DEFAULT_PROJECT);
+    }
+
+    // -- Audit tests --
+
+    #[test]
+    fn audit_event_ids_sort_chronologically() {
+        let earlier = AuditEntry::new("default", 999, AuditAction::Pause, "ops");
+        let later = AuditEntry::new("default", 1000, AuditAction::Unpause, "ops");
+        assert!(earlier.event_id < later.event_id);
+        assert!(later.event_id.starts_with("0000001000#"));
+        assert!(later.event_id >= audit_event_prefix(1000));
+    }
+
+    #[test]
+    fn audit_action_serializes_snake_case() {
+        let json = serde_json::to_string(&AuditAction::RotatePingToken).unwrap();
+        assert_eq!(json, r#""rotate_ping_token""#);
+    }
+
+    // -- Checker status tests --
+
+    #[test]
+    fn checker_heartbeat_staleness() {
+        let heartbeat = CheckerHeartbeat {
+            at: 1000,
+            overdue: 2,
+            alerted: 1,
+            alerts_sent: 1,
+            errors: 0,
+            last_error: None,
+        };
+        assert!(!heartbeat.is_stale(1600, 600));
+        assert!(heartbeat.is_stale(1601, 600));
+    }
 }
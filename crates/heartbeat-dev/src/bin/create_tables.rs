@@ -0,0 +1,36 @@
+//! Create the DynamoDB tables for local development, e.g. in DynamoDB Local:
+//!
+//! ```text
+//! DYNAMODB_ENDPOINT_URL=http://localhost:8000 create-tables
+//! ```
+//!
+//! Reads the same table variables as the API and leaves existing tables
+//! alone. The tables in AWS are managed by Terraform instead.
+
+use heartbeat_core::{DynamoStore, DynamoTables};
+
+#[tokio::main]
+async fn main() {
+    let mut tables = DynamoTables::default();
+    for (var, name) in [
+        ("MONITORS_TABLE", &mut tables.monitors),
+        ("KEYS_TABLE", &mut tables.api_keys),
+        ("AUDIT_TABLE", &mut tables.audit),
+        ("STATUS_TABLE", &mut tables.status),
+    ] {
+        if let Ok(value) = std::env::var(var) {
+            *name = value;
+        }
+    }
+    tables.endpoint_url = std::env::var("DYNAMODB_ENDPOINT_URL").ok();
+
+    let store = DynamoStore::new(&tables.monitors, tables.endpoint_url.as_deref()).await;
+    match store.create_tables(&tables).await {
+        Ok(created) if created.is_empty() => println!("all tables already exist"),
+        Ok(created) => println!("created tables: {}", created.join(", ")),
+        Err(e) => {
+            eprintln!("failed to create tables: {e}");
+            std::process::exit(1);
+        }
+    }
+}
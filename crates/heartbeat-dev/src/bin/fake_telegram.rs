@@ -0,0 +1,56 @@
+//! A stand-in for the Telegram Bot API during local development.
+//!
+//! Point `TELEGRAM_API_URL` at it: every `sendMessage` call is accepted and
+//! printed, and the messages received so far are listed on `GET /messages`.
+
+use std::sync::{Arc, Mutex};
+
+use axum::extract::State;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tokio::net::TcpListener;
+
+/// A message as sent to `sendMessage`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Message {
+    chat_id: String,
+    text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    parse_mode: Option<String>,
+}
+
+type Messages = Arc<Mutex<Vec<Message>>>;
+
+#[tokio::main]
+async fn main() {
+    let bind_addr =
+        std::env::var("FAKE_TELEGRAM_ADDR").unwrap_or_else(|_| "127.0.0.1:8081".to_string());
+    let messages = Messages::default();
+
+    // The bot token is part of the path: /bot<token>/sendMessage
+    let app = Router::new()
+        .route("/{bot}/sendMessage", post(send_message))
+        .route("/messages", get(list_messages))
+        .with_state(messages);
+
+    let listener = TcpListener::bind(&bind_addr)
+        .await
+        .expect("Failed to bind address");
+    println!("fake Telegram API listening on http://{bind_addr}");
+    axum::serve(listener, app).await.expect("Server error");
+}
+
+async fn send_message(
+    State(messages): State<Messages>,
+    Json(message): Json<Message>,
+) -> Json<Value> {
+    println!("--- chat {} ---\n{}\n", message.chat_id, message.text);
+    messages.lock().unwrap().push(message);
+    Json(json!({ "ok": true, "result": {} }))
+}
+
+async fn list_messages(State(messages): State<Messages>) -> Json<Vec<Message>> {
+    Json(messages.lock().unwrap().clone())
+}
@@ -5,6 +5,8 @@ cargo lambda build \
   --release \
   --arm64 \
   --output-format zip \
-  --package heartbeat-checker
+  --package heartbeat-checker \
+  --no-default-features \
+  --bin heartbeat-checker
 
 echo "Build complete: target/lambda/heartbeat-checker/bootstrap.zip"
@@ -0,0 +1,40 @@
+#!/usr/bin/env bash
+# Run heartbeat locally: DynamoDB Local in a container, the API with the
+# embedded checker, and alerts printed by a fake Telegram API.
+#
+# Usage: scripts/dev-stack.sh
+# Stop with Ctrl+C; the DynamoDB Local container keeps its (in-memory) data
+# until removed with `podman rm -f heartbeat-dynamodb`.
+set -euo pipefail
+
+DYNAMODB_PORT="${DYNAMODB_PORT:-8000}"
+TELEGRAM_PORT="${TELEGRAM_PORT:-8081}"
+
+# DynamoDB Local accepts any credentials, but the SDK requires some
+export AWS_ACCESS_KEY_ID=local
+export AWS_SECRET_ACCESS_KEY=local
+export AWS_REGION=eu-north-1
+export DYNAMODB_ENDPOINT_URL="http://localhost:${DYNAMODB_PORT}"
+
+if ! podman container exists heartbeat-dynamodb; then
+  podman run -d --name heartbeat-dynamodb -p "${DYNAMODB_PORT}:8000" \
+    docker.io/amazon/dynamodb-local:latest
+  sleep 2
+fi
+
+cargo build --bin heartbeat-api --bin api-keys --bin create-tables --bin fake-telegram
+target/debug/create-tables
+
+FAKE_TELEGRAM_ADDR="127.0.0.1:${TELEGRAM_PORT}" target/debug/fake-telegram &
+trap 'kill $!' EXIT
+
+# A fresh admin key for this session
+target/debug/api-keys create --description "dev stack" --scope admin
+
+BIND_ADDR=127.0.0.1:3000 \
+EMBEDDED_CHECKER=true \
+CHECK_INTERVAL=10s \
+TELEGRAM_API_URL="http://127.0.0.1:${TELEGRAM_PORT}" \
+TELEGRAM_BOT_TOKEN=local \
+TELEGRAM_CHAT_ID=local \
+  target/debug/heartbeat-api
@@ -18,6 +18,11 @@ resource "aws_dynamodb_table" "monitors" {
     type = "N"
   }
 
+  attribute {
+    name = "ping_token"
+    type = "S"
+  }
+
   global_secondary_index {
     name            = "overdue-check-index"
     hash_key        = "check_partition"
@@ -25,6 +30,12 @@ resource "aws_dynamodb_table" "monitors" {
     projection_type = "ALL"
   }
 
+  global_secondary_index {
+    name            = "ping-token-index"
+    hash_key        = "ping_token"
+    projection_type = "KEYS_ONLY"
+  }
+
   ttl {
     attribute_name = "expires_at"
     enabled        = true
@@ -49,3 +60,50 @@ resource "aws_dynamodb_table" "api_keys" {
     Name = "${var.prefix}-api-keys"
   }
 }
+
+resource "aws_dynamodb_table" "audit" {
+  name         = "${var.prefix}-audit"
+  billing_mode = "PAY_PER_REQUEST"
+  hash_key     = "project"
+  range_key    = "event_id"
+
+  attribute {
+    name = "project"
+    type = "S"
+  }
+
+  attribute {
+    name = "event_id"
+    type = "S"
+  }
+
+  ttl {
+    attribute_name = "expires_at"
+    enabled        = true
+  }
+
+  tags = {
+    Name = "${var.prefix}-audit"
+  }
+}
+
+resource "aws_dynamodb_table" "status" {
+  name         = "${var.prefix}-status"
+  billing_mode = "PAY_PER_REQUEST"
+  hash_key     = "component"
+
+  attribute {
+    name = "component"
+    type = "S"
+  }
+
+  # Expires leases and sent-alert records; the checker heartbeat has no TTL
+  ttl {
+    attribute_name = "expires_at"
+    enabled        = true
+  }
+
+  tags = {
+    Name = "${var.prefix}-status"
+  }
+}
@@ -34,6 +34,18 @@ data "aws_iam_policy_document" "checker_dynamodb" {
     ]
   }
 
+  statement {
+    sid = "StatusAccess"
+    actions = [
+      "dynamodb:PutItem",
+      "dynamodb:UpdateItem",
+      "dynamodb:DeleteItem",
+    ]
+    resources = [
+      aws_dynamodb_table.status.arn,
+    ]
+  }
+
   statement {
     sid = "SSMAccess"
     actions = [
@@ -42,6 +54,8 @@ data "aws_iam_policy_document" "checker_dynamodb" {
     resources = [
       aws_ssm_parameter.telegram_bot_token.arn,
       aws_ssm_parameter.telegram_chat_id.arn,
+      aws_ssm_parameter.telegram_project_chat_ids.arn,
+      aws_ssm_parameter.alert_templates.arn,
     ]
   }
 }
@@ -66,11 +80,14 @@ data "aws_iam_policy_document" "api_dynamodb" {
       "dynamodb:DeleteItem",
       "dynamodb:Scan",
       "dynamodb:Query",
+      "dynamodb:DescribeTable",
     ]
     resources = [
       aws_dynamodb_table.monitors.arn,
       "${aws_dynamodb_table.monitors.arn}/index/*",
       aws_dynamodb_table.api_keys.arn,
+      aws_dynamodb_table.audit.arn,
+      aws_dynamodb_table.status.arn,
     ]
   }
 }
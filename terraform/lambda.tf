@@ -31,10 +31,14 @@ resource "aws_lambda_function" "checker" {
 
   environment {
     variables = {
-      HEARTBEAT_TABLE_NAME          = aws_dynamodb_table.monitors.name
-      HEARTBEAT_API_KEYS_TABLE_NAME = aws_dynamodb_table.api_keys.name
-      TELEGRAM_BOT_TOKEN_PARAM      = aws_ssm_parameter.telegram_bot_token.name
-      TELEGRAM_CHAT_ID_PARAM        = aws_ssm_parameter.telegram_chat_id.name
+      HEARTBEAT_TABLE_NAME            = aws_dynamodb_table.monitors.name
+      HEARTBEAT_API_KEYS_TABLE_NAME   = aws_dynamodb_table.api_keys.name
+      HEARTBEAT_STATUS_TABLE_NAME     = aws_dynamodb_table.status.name
+      TELEGRAM_BOT_TOKEN_PARAM        = aws_ssm_parameter.telegram_bot_token.name
+      TELEGRAM_CHAT_ID_PARAM          = aws_ssm_parameter.telegram_chat_id.name
+      TELEGRAM_PROJECT_CHAT_IDS_PARAM = aws_ssm_parameter.telegram_project_chat_ids.name
+      ALERT_TEMPLATES_PARAM           = aws_ssm_parameter.alert_templates.name
+      ALERT_TIMEZONE                  = var.alert_timezone
     }
   }
 }
@@ -17,3 +17,23 @@ resource "aws_ssm_parameter" "telegram_chat_id" {
     Name = "${var.prefix}-telegram-chat-id"
   }
 }
+
+resource "aws_ssm_parameter" "telegram_project_chat_ids" {
+  name  = "/${var.prefix}/telegram-project-chat-ids"
+  type  = "SecureString"
+  value = jsonencode(var.project_telegram_chat_ids)
+
+  tags = {
+    Name = "${var.prefix}-telegram-project-chat-ids"
+  }
+}
+
+resource "aws_ssm_parameter" "alert_templates" {
+  name  = "/${var.prefix}/alert-templates"
+  type  = "String"
+  value = jsonencode(var.alert_templates)
+
+  tags = {
+    Name = "${var.prefix}-alert-templates"
+  }
+}
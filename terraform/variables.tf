@@ -22,6 +22,25 @@ variable "telegram_chat_id" {
   sensitive   = true
 }
 
+variable "project_telegram_chat_ids" {
+  description = "Telegram chat IDs receiving alerts per project; other projects use telegram_chat_id"
+  type        = map(list(string))
+  default     = {}
+  sensitive   = true
+}
+
+variable "alert_templates" {
+  description = "Telegram alert templates by alert kind (overdue, failed, repeat, failed_repeat, recovery, failed_recovery); other kinds use the default message"
+  type        = map(string)
+  default     = {}
+}
+
+variable "alert_timezone" {
+  description = "IANA time zone of the times in alerts, for monitors without their own (pinged with ?tz=)"
+  type        = string
+  default     = "UTC"
+}
+
 variable "alert_email" {
   description = "Email address for CloudWatch alarm notifications"
   type        = string